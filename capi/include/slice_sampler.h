@@ -0,0 +1,87 @@
+#ifndef SLICE_SAMPLER_H
+#define SLICE_SAMPLER_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define SLICE_SUCCESS 0
+#define SLICE_ERROR_NULL_POINTER -1
+#define SLICE_ERROR_INVALID_WIDTH -2
+#define SLICE_ERROR_INVALID_STEP_LIMIT -3
+#define SLICE_ERROR_INVALID_MAX_EVALUATIONS -4
+#define SLICE_ERROR_INVALID_BOUNDS -5
+#define SLICE_ERROR_NON_FINITE_START -6
+#define SLICE_ERROR_ZERO_DENSITY_AT_START -7
+#define SLICE_ERROR_NAN_DENSITY -8
+#define SLICE_ERROR_EVALUATION_BUDGET_EXHAUSTED -9
+#define SLICE_ERROR_INVALID_FACTOR -10
+#define SLICE_ERROR_INVALID_TEMPERATURE -11
+#define SLICE_ERROR_INVALID_SCHEDULE -12
+#define SLICE_ERROR_INVALID_MIN_WIDTH -13
+#define SLICE_ERROR_DEGENERATE_INTERVAL -14
+#define SLICE_ERROR_INFINITE_DENSITY_AT_START -15
+#define SLICE_ERROR_INVALID_TARGET_EVALUATIONS -16
+#define SLICE_ERROR_INVALID_LEARNING_RATE -17
+#define SLICE_ERROR_INVALID_DECAY -18
+#define SLICE_ERROR_INVALID_SHAPE -19
+#define SLICE_ERROR_INVALID_STEP_SIZE -20
+
+#define SLICE_STEP_LIMIT_UNLIMITED 0
+#define SLICE_STEP_LIMIT_NONE 1
+#define SLICE_STEP_LIMIT_MAX 2
+
+/*
+ * Tuning parameters, where step_limit is one of the SLICE_STEP_LIMIT constants (max_steps is
+ * the limit for SLICE_STEP_LIMIT_MAX), max_evaluations is zero for no evaluation budget, and
+ * min_width is zero for no minimum interval width.
+ */
+typedef struct SliceTuningParameters {
+  double width;
+  uint32_t step_limit;
+  uint32_t max_steps;
+  uint64_t max_evaluations;
+  double min_width;
+} SliceTuningParameters;
+
+typedef double (*SliceTarget)(double x, void *context);
+
+/*
+ * Stepping out and shrinkage update of x for the target f with the given context.
+ */
+int32_t slice_sampler_stepping_out(double x,
+                                   SliceTarget f,
+                                   void *context,
+                                   bool on_log_scale,
+                                   const SliceTuningParameters *tuning_parameters,
+                                   uint64_t *rng_state,
+                                   double *out_x,
+                                   uint64_t *out_evaluations);
+
+/*
+ * Doubling and shrinkage update of x for the target f with the given context.
+ */
+int32_t slice_sampler_doubling(double x,
+                               SliceTarget f,
+                               void *context,
+                               bool on_log_scale,
+                               const SliceTuningParameters *tuning_parameters,
+                               uint64_t *rng_state,
+                               double *out_x,
+                               uint64_t *out_evaluations);
+
+/*
+ * Shrinkage update of x within [left, right] for the target f with the given context, stepping
+ * out in steps of `width` on a side whose bound is infinite.
+ */
+int32_t slice_sampler_shrinkage(double x,
+                                SliceTarget f,
+                                void *context,
+                                bool on_log_scale,
+                                double left,
+                                double right,
+                                double width,
+                                uint64_t *rng_state,
+                                double *out_x,
+                                uint64_t *out_evaluations);
+
+#endif /* SLICE_SAMPLER_H */
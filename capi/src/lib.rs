@@ -0,0 +1,111 @@
+// Builds the C interface of slice_sampler (its ffi module) as shared and static libraries, which
+// C, C++, Julia, R, and Python code can link or load, declared by include/slice_sampler.h.
+pub use slice_sampler::ffi::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_matches_constants() {
+        let header = include_str!("../include/slice_sampler.h");
+        let constants: [(&str, i64); 24] = [
+            ("SLICE_SUCCESS", SLICE_SUCCESS.into()),
+            ("SLICE_ERROR_NULL_POINTER", SLICE_ERROR_NULL_POINTER.into()),
+            (
+                "SLICE_ERROR_INVALID_WIDTH",
+                SLICE_ERROR_INVALID_WIDTH.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_STEP_LIMIT",
+                SLICE_ERROR_INVALID_STEP_LIMIT.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_MAX_EVALUATIONS",
+                SLICE_ERROR_INVALID_MAX_EVALUATIONS.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_BOUNDS",
+                SLICE_ERROR_INVALID_BOUNDS.into(),
+            ),
+            (
+                "SLICE_ERROR_NON_FINITE_START",
+                SLICE_ERROR_NON_FINITE_START.into(),
+            ),
+            (
+                "SLICE_ERROR_ZERO_DENSITY_AT_START",
+                SLICE_ERROR_ZERO_DENSITY_AT_START.into(),
+            ),
+            ("SLICE_ERROR_NAN_DENSITY", SLICE_ERROR_NAN_DENSITY.into()),
+            (
+                "SLICE_ERROR_EVALUATION_BUDGET_EXHAUSTED",
+                SLICE_ERROR_EVALUATION_BUDGET_EXHAUSTED.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_FACTOR",
+                SLICE_ERROR_INVALID_FACTOR.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_TEMPERATURE",
+                SLICE_ERROR_INVALID_TEMPERATURE.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_SCHEDULE",
+                SLICE_ERROR_INVALID_SCHEDULE.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_MIN_WIDTH",
+                SLICE_ERROR_INVALID_MIN_WIDTH.into(),
+            ),
+            (
+                "SLICE_ERROR_DEGENERATE_INTERVAL",
+                SLICE_ERROR_DEGENERATE_INTERVAL.into(),
+            ),
+            (
+                "SLICE_ERROR_INFINITE_DENSITY_AT_START",
+                SLICE_ERROR_INFINITE_DENSITY_AT_START.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_TARGET_EVALUATIONS",
+                SLICE_ERROR_INVALID_TARGET_EVALUATIONS.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_LEARNING_RATE",
+                SLICE_ERROR_INVALID_LEARNING_RATE.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_DECAY",
+                SLICE_ERROR_INVALID_DECAY.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_SHAPE",
+                SLICE_ERROR_INVALID_SHAPE.into(),
+            ),
+            (
+                "SLICE_ERROR_INVALID_STEP_SIZE",
+                SLICE_ERROR_INVALID_STEP_SIZE.into(),
+            ),
+            (
+                "SLICE_STEP_LIMIT_UNLIMITED",
+                SLICE_STEP_LIMIT_UNLIMITED.into(),
+            ),
+            ("SLICE_STEP_LIMIT_NONE", SLICE_STEP_LIMIT_NONE.into()),
+            ("SLICE_STEP_LIMIT_MAX", SLICE_STEP_LIMIT_MAX.into()),
+        ];
+        let defines: Vec<(&str, i64)> = header
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.strip_prefix("#define ")?.split_whitespace();
+                Some((fields.next()?, fields.next()?.parse().ok()?))
+            })
+            .collect();
+        assert_eq!(defines, constants);
+        for function in [
+            "slice_sampler_stepping_out(",
+            "slice_sampler_doubling(",
+            "slice_sampler_shrinkage(",
+        ] {
+            assert!(header.contains(function));
+        }
+    }
+}
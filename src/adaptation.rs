@@ -0,0 +1,336 @@
+use crate::error::{check_width, TuningError};
+use crate::univariate::doubling::{self, doubling_and_shrinkage_with_interval};
+use crate::univariate::stepping_out::{self, stepping_out_and_shrinkage_with_interval};
+use crate::univariate::Phases;
+
+// Warmup adaptation of the initial width: over the first n_warmup updates, the width is set to
+// the mean width of the intervals from which the new states were drawn, after which it is
+// frozen so that the remaining updates form a valid Markov chain. The initial width must be
+// positive and finite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmupAdapter {
+    pub(crate) width: f64,
+    pub(crate) n_warmup: u32,
+    pub(crate) n_updates: u32,
+    pub(crate) n_recorded: u32,
+    pub(crate) sum_of_widths: f64,
+}
+
+impl WarmupAdapter {
+    pub fn new(initial_width: f64, n_warmup: u32) -> Result<Self, TuningError> {
+        check_width(initial_width)?;
+        Ok(Self {
+            width: initial_width,
+            n_warmup,
+            n_updates: 0,
+            n_recorded: 0,
+            sum_of_widths: 0.0,
+        })
+    }
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    pub fn is_frozen(&self) -> bool {
+        self.n_updates >= self.n_warmup
+    }
+    pub fn record(&mut self, interval_width: f64) {
+        if self.is_frozen() {
+            return;
+        }
+        self.n_updates += 1;
+        if interval_width.is_finite() && interval_width > 0.0 {
+            self.n_recorded += 1;
+            self.sum_of_widths += interval_width;
+            self.width = self.sum_of_widths / (self.n_recorded as f64);
+        }
+    }
+    pub fn stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &stepping_out::TuningParameters,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        self.stepping_out_and_shrinkage_with_phases(
+            x,
+            f,
+            on_log_scale,
+            tuning_parameters,
+            &mut Phases::default(),
+            rng,
+        )
+    }
+    pub(crate) fn stepping_out_and_shrinkage_with_phases<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &stepping_out::TuningParameters,
+        phases: &mut Phases,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        let (x1, evaluation_counter, (l, r)) = stepping_out_and_shrinkage_with_interval(
+            x,
+            f,
+            on_log_scale,
+            self.width,
+            tuning_parameters,
+            phases,
+            rng,
+        );
+        self.record(r - l);
+        (x1, evaluation_counter)
+    }
+    pub fn doubling_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &doubling::TuningParameters,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        self.doubling_and_shrinkage_with_phases(
+            x,
+            f,
+            on_log_scale,
+            tuning_parameters,
+            &mut Phases::default(),
+            rng,
+        )
+    }
+    pub(crate) fn doubling_and_shrinkage_with_phases<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &doubling::TuningParameters,
+        phases: &mut Phases,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        let (x1, evaluation_counter, (l, r)) = doubling_and_shrinkage_with_interval(
+            x,
+            f,
+            on_log_scale,
+            self.width,
+            tuning_parameters,
+            phases,
+            rng,
+        );
+        self.record(r - l);
+        (x1, evaluation_counter)
+    }
+}
+
+// Robbins-Monro adaptation of the initial width so that the mean number of target evaluations
+// per draw converges to target_evaluations. The log width moves by a step of size
+// learning_rate / n^decay after the nth update, which diminishes (for 0.5 < decay <= 1) so that
+// the adapted chain remains valid. If the interval from which the new state was drawn is wider
+// than the width, expansion dominated the cost and the width is increased; otherwise it is
+// decreased when there were too many evaluations (from shrinkage) and increased when too few.
+// A single update changes the width by at most a factor of e. The initial width and the learning
+// rate must be positive and finite, target_evaluations finite and at least 1, and decay in
+// (0.5, 1].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobbinsMonroAdapter {
+    log_width: f64,
+    target_evaluations: f64,
+    learning_rate: f64,
+    decay: f64,
+    n_updates: u32,
+}
+
+impl RobbinsMonroAdapter {
+    pub fn new(initial_width: f64, target_evaluations: f64) -> Result<Self, TuningError> {
+        check_width(initial_width)?;
+        if !(target_evaluations.is_finite() && target_evaluations >= 1.0) {
+            return Err(TuningError::InvalidTargetEvaluations(target_evaluations));
+        }
+        Ok(Self {
+            log_width: initial_width.ln(),
+            target_evaluations,
+            learning_rate: 1.0,
+            decay: 0.6,
+            n_updates: 0,
+        })
+    }
+    pub fn learning_rate(self, value: f64) -> Result<Self, TuningError> {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(TuningError::InvalidLearningRate(value));
+        }
+        Ok(Self {
+            learning_rate: value,
+            ..self
+        })
+    }
+    pub fn decay(self, value: f64) -> Result<Self, TuningError> {
+        if !(value > 0.5 && value <= 1.0) {
+            return Err(TuningError::InvalidDecay(value));
+        }
+        Ok(Self {
+            decay: value,
+            ..self
+        })
+    }
+    pub fn width(&self) -> f64 {
+        self.log_width.exp()
+    }
+    pub fn record(&mut self, evaluations: u64, interval_width: f64) {
+        self.n_updates += 1;
+        let step = self.learning_rate / (self.n_updates as f64).powf(self.decay);
+        let discrepancy = (evaluations as f64) - self.target_evaluations;
+        let change = if interval_width > self.width() {
+            step * discrepancy.abs()
+        } else {
+            -step * discrepancy
+        };
+        self.log_width += change.clamp(-1.0, 1.0);
+    }
+    pub fn stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &stepping_out::TuningParameters,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        let (x1, evaluation_counter, (l, r)) = stepping_out_and_shrinkage_with_interval(
+            x,
+            f,
+            on_log_scale,
+            self.width(),
+            tuning_parameters,
+            &mut Phases::default(),
+            rng,
+        );
+        self.record(evaluation_counter, r - l);
+        (x1, evaluation_counter)
+    }
+    pub fn doubling_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &doubling::TuningParameters,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, u64) {
+        let (x1, evaluation_counter, (l, r)) = doubling_and_shrinkage_with_interval(
+            x,
+            f,
+            on_log_scale,
+            self.width(),
+            tuning_parameters,
+            &mut Phases::default(),
+            rng,
+        );
+        self.record(evaluation_counter, r - l);
+        (x1, evaluation_counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_adapts_then_freezes() {
+        let tuning_parameters = stepping_out::TuningParameters::new();
+        let mut adapter = WarmupAdapter::new(0.01, 1_000).unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(13));
+        let mut x = 0.0;
+        for _ in 0..1_000 {
+            (x, _) = adapter.stepping_out_and_shrinkage(
+                x,
+                |x| -0.5 * x * x / 100.0,
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+        }
+        assert!(adapter.is_frozen());
+        let width = adapter.width();
+        assert!(width > 1.0 && width < 100.0);
+        for _ in 0..100 {
+            (x, _) = adapter.stepping_out_and_shrinkage(
+                x,
+                |x| -0.5 * x * x / 100.0,
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+        }
+        assert_eq!(adapter.width(), width);
+    }
+
+    #[test]
+    fn test_invalid_initial_width() {
+        for w in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                WarmupAdapter::new(w, 100),
+                Err(TuningError::InvalidWidth(w))
+            );
+        }
+        assert!(matches!(
+            WarmupAdapter::new(f64::NAN, 100),
+            Err(TuningError::InvalidWidth(_))
+        ));
+    }
+
+    #[test]
+    fn test_robbins_monro_target_evaluations() {
+        let tuning_parameters = stepping_out::TuningParameters::new();
+        for initial_width in [0.001, 1000.0] {
+            let mut adapter = RobbinsMonroAdapter::new(initial_width, 7.0).unwrap();
+            let mut rng = Some(fastrand::Rng::with_seed(19));
+            let mut x = 0.0;
+            let mut total_calls = 0;
+            let n_samples = 20_000;
+            for i in 0..2 * n_samples {
+                let calls;
+                (x, calls) = adapter.stepping_out_and_shrinkage(
+                    x,
+                    |x| -0.5 * x * x,
+                    true,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+                if i >= n_samples {
+                    total_calls += calls;
+                }
+            }
+            let mean_calls = (total_calls as f64) / (n_samples as f64);
+            assert!((mean_calls - 7.0).abs() < 0.5);
+        }
+    }
+
+    #[test]
+    fn test_invalid_robbins_monro_settings() {
+        assert_eq!(
+            RobbinsMonroAdapter::new(-1.0, 7.0),
+            Err(TuningError::InvalidWidth(-1.0))
+        );
+        assert_eq!(
+            RobbinsMonroAdapter::new(1.0, 0.5),
+            Err(TuningError::InvalidTargetEvaluations(0.5))
+        );
+        assert!(matches!(
+            RobbinsMonroAdapter::new(1.0, f64::NAN),
+            Err(TuningError::InvalidTargetEvaluations(_))
+        ));
+        let adapter = RobbinsMonroAdapter::new(1.0, 7.0).unwrap();
+        assert_eq!(
+            adapter.clone().learning_rate(0.0),
+            Err(TuningError::InvalidLearningRate(0.0))
+        );
+        for decay in [0.5, 1.5] {
+            assert_eq!(
+                adapter.clone().decay(decay),
+                Err(TuningError::InvalidDecay(decay))
+            );
+        }
+        assert!(adapter
+            .learning_rate(0.5)
+            .and_then(|a| a.decay(1.0))
+            .is_ok());
+    }
+}
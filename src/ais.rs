@@ -0,0 +1,154 @@
+use crate::error::TuningError;
+use crate::math::default_rng;
+use crate::target::LogDensity;
+use crate::univariate::variant::{sample, SliceVariant};
+
+// Estimate of the log marginal likelihood from annealed importance sampling, with its standard
+// error (by the delta method), the log importance weight of each run, and the total number of
+// evaluations of the annealed targets and of the likelihood for the weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AisEstimate {
+    pub log_marginal_likelihood: f64,
+    pub standard_error: f64,
+    pub log_weights: Vec<f64>,
+    pub evaluations: u64,
+}
+
+pub(crate) fn check_schedule(schedule: &[f64]) -> Result<(), TuningError> {
+    let mut previous = 0.0;
+    for &lambda in schedule {
+        if !(previous..=1.0).contains(&lambda) {
+            return Err(TuningError::InvalidSchedule(lambda));
+        }
+        previous = lambda;
+    }
+    match (schedule.first(), schedule.last()) {
+        (Some(&0.0), Some(&1.0)) => Ok(()),
+        (Some(&first), Some(&1.0)) => Err(TuningError::InvalidSchedule(first)),
+        (_, last) => Err(TuningError::InvalidSchedule(
+            last.copied().unwrap_or(f64::NAN),
+        )),
+    }
+}
+
+// Annealed importance sampling (Neal, 2001) of the marginal likelihood, the integral of
+// likelihood times prior, where the prior is normalized and draw_prior samples from it. Each of
+// n_runs runs starts from a prior draw and moves through the distributions proportional to
+// prior(x) * likelihood(x)^lambda for the schedule of lambda values, which must increase from 0
+// to 1, applying n_updates updates of the slice variant at each lambda.
+#[allow(clippy::too_many_arguments)]
+pub fn annealed_importance_sampling<P, L, D>(
+    schedule: &[f64],
+    n_runs: usize,
+    n_updates: usize,
+    mut log_prior: P,
+    mut log_likelihood: L,
+    mut draw_prior: D,
+    variant: &SliceVariant,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<AisEstimate, TuningError>
+where
+    P: FnMut(f64) -> f64,
+    L: FnMut(f64) -> f64,
+    D: FnMut(&mut fastrand::Rng) -> f64,
+{
+    check_schedule(schedule)?;
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let mut evaluations = 0;
+    let mut log_weights = Vec::with_capacity(n_runs);
+    for _ in 0..n_runs {
+        let mut x = draw_prior(rng.get_or_insert_with(default_rng));
+        let mut log_weight = 0.0;
+        for pair in schedule.windows(2) {
+            let (previous, lambda) = (pair[0], pair[1]);
+            log_weight += (lambda - previous) * log_likelihood(x);
+            evaluations += 1;
+            let mut target = LogDensity(|x| {
+                let lp = log_prior(x);
+                if lp == f64::NEG_INFINITY {
+                    lp
+                } else {
+                    lp + lambda * log_likelihood(x)
+                }
+            });
+            for _ in 0..n_updates {
+                let evaluation_counter;
+                (x, evaluation_counter) = sample(variant, x, &mut target, rng);
+                evaluations += evaluation_counter;
+            }
+        }
+        log_weights.push(log_weight);
+    }
+    let max = log_weights
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let n = log_weights.len() as f64;
+    let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+    let mean = weights.iter().sum::<f64>() / n;
+    let variance = weights.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Ok(AisEstimate {
+        log_marginal_likelihood: max + mean.ln(),
+        standard_error: (variance / n).sqrt() / mean,
+        log_weights,
+        evaluations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::inverse_normal_cdf;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_normal_marginal_likelihood() {
+        // x ~ N(0, 1) and y | x ~ N(x, 1) with y = 1, so that y ~ N(0, 2)
+        let schedule: Vec<f64> = (0..=50).map(|i| (i as f64) / 50.0).collect();
+        let mut rng = Some(fastrand::Rng::with_seed(73));
+        let estimate = annealed_importance_sampling(
+            &schedule,
+            200,
+            1,
+            |x| -0.5 * x * x - 0.5 * (2.0 * std::f64::consts::PI).ln(),
+            |x| -0.5 * (1.0 - x) * (1.0 - x) - 0.5 * (2.0 * std::f64::consts::PI).ln(),
+            |rng| inverse_normal_cdf(rng.f64()),
+            &SliceVariant::SteppingOut(TuningParameters::new()),
+            &mut rng,
+        )
+        .unwrap();
+        let truth = -0.5 * (4.0 * std::f64::consts::PI).ln() - 0.25;
+        assert_eq!(estimate.log_weights.len(), 200);
+        assert!(estimate.standard_error > 0.0 && estimate.standard_error < 0.05);
+        assert!((estimate.log_marginal_likelihood - truth).abs() < 4.0 * estimate.standard_error);
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let run = |schedule: &[f64]| {
+            annealed_importance_sampling(
+                schedule,
+                1,
+                1,
+                |x| -x * x,
+                |x| -x * x,
+                |rng| rng.f64(),
+                &SliceVariant::SteppingOut(TuningParameters::new()),
+                &mut None,
+            )
+        };
+        assert_eq!(run(&[0.0, 0.5]), Err(TuningError::InvalidSchedule(0.5)));
+        assert_eq!(run(&[0.5, 1.0]), Err(TuningError::InvalidSchedule(0.5)));
+        assert_eq!(
+            run(&[0.0, 0.6, 0.4, 1.0]),
+            Err(TuningError::InvalidSchedule(0.4))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::diagnostics::effective_sample_size;
+use crate::target::{MultivariateTarget, UnivariateTarget};
+use crate::targets::{CorrelatedNormal, Funnel, IllConditionedNormal, Normal, StudentT};
+use crate::univariate::variant::{sample, SliceVariant};
+use std::time::{Duration, Instant};
+
+// Univariate target seen as a multivariate target of dimension one.
+#[derive(Debug, Clone, Copy)]
+pub struct OneDimensional<T>(pub T);
+
+impl<T: UnivariateTarget> MultivariateTarget for OneDimensional<T> {
+    fn dimension(&self) -> usize {
+        1
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        self.0.evaluate(x[0])
+    }
+    fn on_log_scale(&self) -> bool {
+        self.0.on_log_scale()
+    }
+}
+
+// Target of a benchmark together with its starting point. The target is Send so that a suite
+// can be run with one benchmark per thread.
+pub struct Benchmark {
+    pub name: &'static str,
+    pub target: Box<dyn MultivariateTarget + Send>,
+    pub initial_state: Vec<f64>,
+}
+
+// Standard benchmarks in increasing order of difficulty: a standard normal, a bivariate normal
+// with correlation 0.99, a standard Cauchy, Neal's funnel in ten dimensions, and a ten-dimensional
+// normal whose covariance has condition number 1000.
+pub fn standard_suite() -> Vec<Benchmark> {
+    vec![
+        Benchmark {
+            name: "standard normal",
+            target: Box::new(OneDimensional(Normal {
+                mean: 0.0,
+                standard_deviation: 1.0,
+            })),
+            initial_state: vec![0.0],
+        },
+        Benchmark {
+            name: "correlated bivariate normal",
+            target: Box::new(CorrelatedNormal { correlation: 0.99 }),
+            initial_state: vec![0.0; 2],
+        },
+        Benchmark {
+            name: "Cauchy",
+            target: Box::new(OneDimensional(StudentT {
+                degrees_of_freedom: 1.0,
+                location: 0.0,
+                scale: 1.0,
+            })),
+            initial_state: vec![0.0],
+        },
+        Benchmark {
+            name: "Neal's funnel",
+            target: Box::new(Funnel { dimension: 10 }),
+            initial_state: vec![0.0; 10],
+        },
+        Benchmark {
+            name: "ill-conditioned normal",
+            target: Box::new(IllConditionedNormal {
+                dimension: 10,
+                condition_number: 1000.0,
+            }),
+            initial_state: vec![0.0; 10],
+        },
+    ]
+}
+
+// Cost of a benchmark run, where the effective sample size is the smallest over the coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub evaluations: u64,
+    pub effective_sample_size: f64,
+    pub evaluations_per_effective_sample: f64,
+    pub elapsed: Duration,
+}
+
+// Runs n_draws updates of the benchmark's target from its starting point, where update changes
+// the state in place and returns the number of target evaluations (e.g., coordinatewise).
+pub fn run_benchmark<U>(
+    benchmark: &mut Benchmark,
+    n_draws: usize,
+    mut update: U,
+    rng: &mut Option<fastrand::Rng>,
+) -> BenchmarkResult
+where
+    U: FnMut(&mut [f64], &mut dyn MultivariateTarget, &mut Option<fastrand::Rng>) -> u64,
+{
+    let start = Instant::now();
+    let mut x = benchmark.initial_state.clone();
+    let mut chains = vec![Vec::with_capacity(n_draws); x.len()];
+    let mut evaluations = 0;
+    for _ in 0..n_draws {
+        evaluations += update(&mut x, benchmark.target.as_mut(), rng);
+        for (chain, &xi) in chains.iter_mut().zip(&x) {
+            chain.push(xi);
+        }
+    }
+    let elapsed = start.elapsed();
+    let effective_sample_size = chains
+        .iter()
+        .map(|chain| effective_sample_size(chain))
+        .fold(f64::INFINITY, f64::min);
+    BenchmarkResult {
+        name: benchmark.name,
+        evaluations,
+        effective_sample_size,
+        evaluations_per_effective_sample: evaluations as f64 / effective_sample_size,
+        elapsed,
+    }
+}
+
+// Runs every benchmark of the standard suite with a generator seeded with seed.
+pub fn run_standard_suite<U>(n_draws: usize, mut update: U, seed: u64) -> Vec<BenchmarkResult>
+where
+    U: FnMut(&mut [f64], &mut dyn MultivariateTarget, &mut Option<fastrand::Rng>) -> u64,
+{
+    let mut rng = Some(fastrand::Rng::with_seed(seed));
+    standard_suite()
+        .iter_mut()
+        .map(|benchmark| run_benchmark(benchmark, n_draws, &mut update, &mut rng))
+        .collect()
+}
+
+// Update of every coordinate in turn with the univariate variant, for use with run_benchmark.
+pub fn coordinatewise(
+    variant: SliceVariant,
+) -> impl FnMut(&mut [f64], &mut dyn MultivariateTarget, &mut Option<fastrand::Rng>) -> u64 {
+    move |x, target, rng| {
+        let mut evaluations = 0;
+        for i in 0..x.len() {
+            let xi = x[i];
+            let mut conditional = Conditional {
+                target: &mut *target,
+                x: &mut *x,
+                i,
+            };
+            let (xi, evaluation_counter) = sample(&variant, xi, &mut conditional, rng);
+            x[i] = xi;
+            evaluations += evaluation_counter;
+        }
+        evaluations
+    }
+}
+
+// Target as a function of its ith coordinate, with the others held fixed.
+struct Conditional<'a, 'b> {
+    target: &'a mut dyn MultivariateTarget,
+    x: &'b mut [f64],
+    i: usize,
+}
+
+impl UnivariateTarget for Conditional<'_, '_> {
+    fn evaluate(&mut self, xi: f64) -> f64 {
+        self.x[self.i] = xi;
+        self.target.evaluate(self.x)
+    }
+    fn on_log_scale(&self) -> bool {
+        self.target.on_log_scale()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out;
+
+    #[test]
+    fn test_standard_suite() {
+        let variant = SliceVariant::SteppingOut(stepping_out::TuningParameters::new());
+        let results = run_standard_suite(2_000, coordinatewise(variant), 79);
+        assert_eq!(results.len(), 5);
+        for result in &results {
+            assert!(result.evaluations > 0);
+            assert!(result.effective_sample_size > 0.0);
+        }
+        // Coordinatewise updates mix far worse on the correlated targets
+        assert!(
+            results[1].evaluations_per_effective_sample
+                > 5.0 * results[0].evaluations_per_effective_sample
+        );
+        assert!(
+            results[4].evaluations_per_effective_sample
+                > 5.0 * results[0].evaluations_per_effective_sample
+        );
+    }
+}
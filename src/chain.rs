@@ -0,0 +1,871 @@
+use crate::diagnostics;
+use crate::efficiency::EfficiencyReport;
+use crate::sampler::{stream_seed, Sampler, UpdateMetadata};
+use crate::summary::{kernel_density, Bandwidth, OnlineSummary};
+use crate::target::UnivariateTargetWithStatistics;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+// Draws from a univariate chain, together with the number of target evaluations and the state
+// of the random number generator before each draw, so that the chain can later be replayed.
+// Posterior predictive draws, if requested, are stored with the index of their draw, and the
+// metadata of each update, if requested, with the draw of the same index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chain {
+    pub initial_state: f64,
+    pub draws: Vec<f64>,
+    pub evaluations: Vec<u64>,
+    pub rng_states: Vec<u64>,
+    pub predictions: Vec<(usize, Vec<f64>)>,
+    pub metadata: Vec<UpdateMetadata>,
+}
+
+impl Chain {
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+    pub fn mean(&self) -> f64 {
+        self.draws.iter().sum::<f64>() / (self.draws.len() as f64)
+    }
+    // Sample variance, NaN for fewer than two draws.
+    pub fn variance(&self) -> f64 {
+        if self.draws.len() < 2 {
+            return f64::NAN;
+        }
+        let mean = self.mean();
+        self.draws.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ((self.draws.len() - 1) as f64)
+    }
+    pub fn standard_deviation(&self) -> f64 {
+        self.variance().sqrt()
+    }
+    pub fn effective_sample_size(&self) -> f64 {
+        diagnostics::effective_sample_size(&self.draws)
+    }
+    pub fn monte_carlo_standard_error(&self) -> f64 {
+        diagnostics::monte_carlo_standard_error(&self.draws)
+    }
+    // Sample quantiles for the given probabilities, interpolating linearly between the order
+    // statistics (type 7 in R's quantile). NaN for an empty chain.
+    pub fn quantiles(&self, probabilities: &[f64]) -> Vec<f64> {
+        let sorted = self.sorted_draws();
+        probabilities
+            .iter()
+            .map(|&p| {
+                if sorted.is_empty() {
+                    return f64::NAN;
+                }
+                let h = p.clamp(0.0, 1.0) * ((sorted.len() - 1) as f64);
+                let lower = h.floor() as usize;
+                let upper = (lower + 1).min(sorted.len() - 1);
+                sorted[lower] + (h - (lower as f64)) * (sorted[upper] - sorted[lower])
+            })
+            .collect()
+    }
+    // Highest posterior density interval with the given probability: the shortest interval
+    // between draws containing at least that proportion of them, which for a unimodal marginal
+    // estimates the interval of highest density (for a multimodal one, the union of several
+    // intervals would be shorter). NaN bounds for an empty chain.
+    pub fn hpd_interval(&self, probability: f64) -> (f64, f64) {
+        let sorted = self.sorted_draws();
+        if sorted.is_empty() {
+            return (f64::NAN, f64::NAN);
+        }
+        let n = sorted.len();
+        let k = ((probability.clamp(0.0, 1.0) * (n as f64)).ceil() as usize).clamp(1, n);
+        (0..=n - k)
+            .map(|i| (sorted[i], sorted[i + k - 1]))
+            .min_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))
+            .unwrap()
+    }
+    // Gaussian kernel density estimate of the marginal at each point of the grid, with the
+    // Sheather-Jones bandwidth, e.g., for plotting.
+    pub fn density(&self, grid: &[f64]) -> Vec<f64> {
+        self.density_with_bandwidth(grid, Bandwidth::SheatherJones)
+    }
+    pub fn density_with_bandwidth(&self, grid: &[f64], bandwidth: Bandwidth) -> Vec<f64> {
+        kernel_density(&self.draws, grid, bandwidth)
+    }
+    fn sorted_draws(&self) -> Vec<f64> {
+        let mut sorted = self.draws.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        sorted
+    }
+    pub fn integrated_autocorrelation_time(&self) -> f64 {
+        diagnostics::integrated_autocorrelation_time(&self.draws)
+    }
+    // Keeping every k-th draw for k the integrated autocorrelation time (rounded up) leaves
+    // nearly independent draws while losing little of the effective sample size, for storing
+    // long chains compactly. Computed after discarding the suggested burn-in.
+    pub fn suggested_thinning(&self) -> usize {
+        let draws = &self.draws[self.suggested_burn_in()..];
+        (diagnostics::integrated_autocorrelation_time(draws).ceil() as usize).max(1)
+    }
+    // Number of initial draws to discard: where the Heidelberger-Welch stationarity test first
+    // passes (half of the chain if it never does), but at least twice the integrated
+    // autocorrelation time of the remaining draws, so that the start is forgotten.
+    pub fn suggested_burn_in(&self) -> usize {
+        let n = self.draws.len();
+        let start = diagnostics::heidelberger_welch(&self.draws, 0.1)
+            .start
+            .unwrap_or(n / 2);
+        let tau = diagnostics::integrated_autocorrelation_time(&self.draws[start..]);
+        start.max((2.0 * tau).ceil() as usize).min(n)
+    }
+}
+
+// Runs a chain of n_draws updates from x, where kernel performs one update using the sampler
+// (e.g., |sampler, x| sampler.stepping_out_and_shrinkage(x, f, true, &tuning_parameters)).
+pub fn run_chain<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    n_draws: usize,
+    kernel: K,
+    sampler: &mut Sampler,
+) -> Chain {
+    run_chain_with_predictive(x, n_draws, kernel, 0, |_, _| Vec::new(), sampler)
+}
+
+// Like run_chain, but also evaluates the posterior predictive closure at every k-th draw (never
+// if k is zero), storing its results in the predictions of the chain.
+pub fn run_chain_with_predictive<K, P>(
+    x: f64,
+    n_draws: usize,
+    kernel: K,
+    k: usize,
+    predictive: P,
+    sampler: &mut Sampler,
+) -> Chain
+where
+    K: FnMut(&mut Sampler, f64) -> (f64, u64),
+    P: FnMut(f64, &mut fastrand::Rng) -> Vec<f64>,
+{
+    run(
+        x,
+        n_draws,
+        kernel,
+        k,
+        predictive,
+        0,
+        |_, _, _| ControlFlow::Continue(()),
+        false,
+        None,
+        sampler,
+    )
+}
+
+// Like run_chain, but also records the metadata of each update (see Sampler::last_update) in the
+// metadata of the chain, e.g., to find the regions of the posterior where updates are expensive.
+// For a kernel making several updates through the sampler, the last is recorded, and an update
+// made without the sampler is recorded with a NaN width and slice level.
+pub fn run_chain_with_metadata<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    n_draws: usize,
+    kernel: K,
+    sampler: &mut Sampler,
+) -> Chain {
+    run(
+        x,
+        n_draws,
+        kernel,
+        0,
+        |_, _| Vec::new(),
+        0,
+        |_, _, _| ControlFlow::Continue(()),
+        true,
+        None,
+        sampler,
+    )
+}
+
+// Like run_chain, but draw t uses the random number stream stream_seed(seed, t) regardless of
+// how many uniforms the earlier draws consumed, so that runs with the same seed and slightly
+// perturbed targets (or tuning parameters) stay largely coupled draw by draw: common random
+// numbers, under which differences between their posterior summaries, e.g., finite-difference
+// derivatives with respect to a hyperparameter, have much less Monte Carlo variance than between
+// independent runs. The recorded generator states still allow the chain to be replayed.
+pub fn run_chain_with_common_random_numbers<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    n_draws: usize,
+    kernel: K,
+    seed: u64,
+    sampler: &mut Sampler,
+) -> Chain {
+    run(
+        x,
+        n_draws,
+        kernel,
+        0,
+        |_, _| Vec::new(),
+        0,
+        |_, _, _| ControlFlow::Continue(()),
+        false,
+        Some(seed),
+        sampler,
+    )
+}
+
+// Like run_chain, but calls monitor after every k-th draw (never if k is zero) with the number
+// of draws so far, the chain so far, and the efficiency report of the sampler, e.g., to update a
+// progress bar or plot the draws. The chain stops early if monitor returns ControlFlow::Break.
+pub fn run_chain_with_monitor<K, M>(
+    x: f64,
+    n_draws: usize,
+    kernel: K,
+    k: usize,
+    monitor: M,
+    sampler: &mut Sampler,
+) -> Chain
+where
+    K: FnMut(&mut Sampler, f64) -> (f64, u64),
+    M: FnMut(usize, &Chain, &EfficiencyReport) -> ControlFlow<()>,
+{
+    run(
+        x,
+        n_draws,
+        kernel,
+        0,
+        |_, _| Vec::new(),
+        k,
+        monitor,
+        false,
+        None,
+        sampler,
+    )
+}
+
+// Like run_chain, but the kernel updates the given target, whose statistics at each draw are
+// accumulated into an online summary per statistic, e.g., for posterior means of functionals
+// that would be expensive to recompute from the stored draws.
+pub fn run_chain_with_statistics<T, K>(
+    x: f64,
+    n_draws: usize,
+    target: &mut T,
+    mut kernel: K,
+    sampler: &mut Sampler,
+) -> (Chain, Vec<OnlineSummary>)
+where
+    T: UnivariateTargetWithStatistics + ?Sized,
+    K: FnMut(&mut Sampler, f64, &mut T) -> (f64, u64),
+{
+    let mut summaries = vec![OnlineSummary::new(&[]); target.n_statistics()];
+    let mut statistics = vec![0.0; summaries.len()];
+    let chain = run_chain(
+        x,
+        n_draws,
+        |sampler: &mut Sampler, x: f64| {
+            let draw = kernel(sampler, x, target);
+            target.statistics(draw.0, &mut statistics);
+            for (summary, &statistic) in summaries.iter_mut().zip(&statistics) {
+                summary.update(statistic);
+            }
+            draw
+        },
+        sampler,
+    );
+    (chain, summaries)
+}
+
+// Precision at which a chain may stop early: once its effective sample size reaches the given
+// value, or once the Monte Carlo standard error of its mean falls to the given value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoppingRule {
+    EffectiveSampleSize(f64),
+    MonteCarloStandardError(f64),
+}
+
+impl StoppingRule {
+    pub fn is_satisfied(&self, chain: &Chain) -> bool {
+        match *self {
+            StoppingRule::EffectiveSampleSize(ess) => chain.effective_sample_size() >= ess,
+            StoppingRule::MonteCarloStandardError(mcse) => {
+                chain.monte_carlo_standard_error() <= mcse
+            }
+        }
+    }
+}
+
+// Like run_chain, but checks the stopping rule after every k-th draw and stops as soon as it is
+// satisfied, running at most max_draws updates. Checking costs time linear in the length of the
+// chain, so k should not be too small.
+pub fn run_chain_until<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    max_draws: usize,
+    kernel: K,
+    k: usize,
+    rule: StoppingRule,
+    sampler: &mut Sampler,
+) -> Chain {
+    let monitor = |_: usize, chain: &Chain, _: &EfficiencyReport| {
+        if rule.is_satisfied(chain) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+    run_chain_with_monitor(x, max_draws, kernel, k, monitor, sampler)
+}
+
+// Like run_chain, but runs until the time budget is spent rather than for a fixed number of
+// draws, finishing the update in progress when it expires, so the length of the chain is the
+// number of draws obtained.
+pub fn run_chain_for<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    budget: Duration,
+    kernel: K,
+    sampler: &mut Sampler,
+) -> Chain {
+    let start = Instant::now();
+    let monitor = |_: usize, _: &Chain, _: &EfficiencyReport| {
+        if start.elapsed() >= budget {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+    run_chain_with_monitor(x, usize::MAX, kernel, 1, monitor, sampler)
+}
+
+// Like run_chain, but thins by cost rather than by iteration: the state is recorded each time
+// another evaluations_per_draw target evaluations have been spent, until n_draws are recorded, so
+// that stored draws cost the same across parameters whose updates differ in evaluations. An
+// update spending several budgets records its state once per budget, and the evaluations of a
+// draw are those spent since the previous draw (zero for such repeats). Since a draw spans
+// several updates, the random number generator states are not recorded and the chain cannot be
+// replayed. Panics if evaluations_per_draw is zero.
+pub fn run_chain_thinned_by_evaluations<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    x: f64,
+    n_draws: usize,
+    mut kernel: K,
+    evaluations_per_draw: u64,
+    sampler: &mut Sampler,
+) -> Chain {
+    assert!(evaluations_per_draw > 0);
+    let reserved = n_draws.min(MAX_RESERVED_DRAWS);
+    let mut chain = Chain {
+        initial_state: x,
+        draws: Vec::with_capacity(reserved),
+        evaluations: Vec::with_capacity(reserved),
+        rng_states: Vec::new(),
+        predictions: Vec::new(),
+        metadata: Vec::new(),
+    };
+    let mut x = x;
+    let mut spent = 0;
+    let mut since_last_draw = 0;
+    while chain.len() < n_draws {
+        let evaluations;
+        (x, evaluations) = kernel(sampler, x);
+        spent += evaluations;
+        since_last_draw += evaluations;
+        while spent >= evaluations_per_draw && chain.len() < n_draws {
+            spent -= evaluations_per_draw;
+            chain.draws.push(x);
+            chain.evaluations.push(since_last_draw);
+            since_last_draw = 0;
+        }
+    }
+    chain
+}
+
+// Draws for which storage is reserved up front, so that a chain with no fixed length (or a very
+// large maximum) does not reserve more memory than it uses.
+const MAX_RESERVED_DRAWS: usize = 1 << 16;
+
+#[allow(clippy::too_many_arguments)]
+fn run<K, P, M>(
+    x: f64,
+    n_draws: usize,
+    mut kernel: K,
+    k_predictive: usize,
+    mut predictive: P,
+    k_monitor: usize,
+    mut monitor: M,
+    record_metadata: bool,
+    common_random_numbers: Option<u64>,
+    sampler: &mut Sampler,
+) -> Chain
+where
+    K: FnMut(&mut Sampler, f64) -> (f64, u64),
+    P: FnMut(f64, &mut fastrand::Rng) -> Vec<f64>,
+    M: FnMut(usize, &Chain, &EfficiencyReport) -> ControlFlow<()>,
+{
+    let reserved = n_draws.min(MAX_RESERVED_DRAWS);
+    let mut chain = Chain {
+        initial_state: x,
+        draws: Vec::with_capacity(reserved),
+        evaluations: Vec::with_capacity(reserved),
+        rng_states: Vec::with_capacity(reserved),
+        predictions: Vec::new(),
+        metadata: Vec::new(),
+    };
+    let mut x = x;
+    for i in 0..n_draws {
+        if let Some(seed) = common_random_numbers {
+            sampler.restore_rng_state(stream_seed(seed, i as u64));
+        }
+        chain.rng_states.push(sampler.rng_state());
+        let iteration = sampler.iteration();
+        let evaluations;
+        (x, evaluations) = kernel(sampler, x);
+        chain.draws.push(x);
+        chain.evaluations.push(evaluations);
+        if record_metadata {
+            let metadata = match sampler.last_update() {
+                Some(metadata) if sampler.iteration() != iteration => UpdateMetadata {
+                    evaluations,
+                    ..metadata
+                },
+                _ => UpdateMetadata {
+                    evaluations,
+                    width: f64::NAN,
+                    slice_level: f64::NAN,
+                },
+            };
+            chain.metadata.push(metadata);
+        }
+        if k_predictive > 0 && (i + 1) % k_predictive == 0 {
+            chain.predictions.push((i, predictive(x, sampler.rng())));
+        }
+        if k_monitor > 0
+            && (i + 1) % k_monitor == 0
+            && monitor(i + 1, &chain, sampler.efficiency_report()).is_break()
+        {
+            break;
+        }
+    }
+    chain
+}
+
+// Reruns a recorded chain with a (possibly different) kernel, restoring the recorded random
+// number generator state before every update so that each draw uses the same uniform stream.
+pub fn replay_chain<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    chain: &Chain,
+    mut kernel: K,
+) -> Chain {
+    let mut sampler = Sampler::with_seed(0);
+    let mut replayed = Chain {
+        initial_state: chain.initial_state,
+        draws: Vec::with_capacity(chain.len()),
+        evaluations: Vec::with_capacity(chain.len()),
+        rng_states: chain.rng_states.clone(),
+        predictions: Vec::new(),
+        metadata: Vec::new(),
+    };
+    let mut x = chain.initial_state;
+    for &state in &chain.rng_states {
+        sampler.restore_rng_state(state);
+        let evaluations;
+        (x, evaluations) = kernel(&mut sampler, x);
+        replayed.draws.push(x);
+        replayed.evaluations.push(evaluations);
+    }
+    replayed
+}
+
+// Problem found while pooling chains, which is reported alongside the pooled draws rather than
+// preventing pooling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolingWarning {
+    // The chains had different lengths after burn-in and were truncated to the shortest.
+    UnequalLengths { shortest: usize, longest: usize },
+    // Split-Rhat exceeded the threshold, so the chains may not have converged to the same
+    // distribution.
+    HighRHat(f64),
+}
+
+// Draws of several chains pooled after burn-in, with the split-Rhat of the chains, the sum of
+// their effective sample sizes, and any warnings raised while pooling.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PooledChains {
+    pub draws: Vec<f64>,
+    pub r_hat: f64,
+    pub effective_sample_size: f64,
+    pub warnings: Vec<PoolingWarning>,
+}
+
+impl PooledChains {
+    // Whether pooling raised no warnings.
+    pub fn is_valid(&self) -> bool {
+        self.warnings.is_empty()
+    }
+    pub fn mean(&self) -> f64 {
+        self.draws.iter().sum::<f64>() / (self.draws.len() as f64)
+    }
+}
+
+// Pools chains of the same target after discarding the first burn_in draws of each, truncating
+// them to a common length and warning when their split-Rhat exceeds max_r_hat (1.01 is usual).
+// Returns None if there are no chains or fewer than four draws remain in a chain.
+pub fn pool_chains(chains: &[Chain], burn_in: usize, max_r_hat: f64) -> Option<PooledChains> {
+    let shortest = chains.iter().map(|chain| chain.len()).min()?;
+    let longest = chains.iter().map(|chain| chain.len()).max()?;
+    let n = shortest.checked_sub(burn_in).filter(|&n| n >= 4)?;
+    let kept: Vec<Vec<f64>> = chains
+        .iter()
+        .map(|chain| chain.draws[burn_in..burn_in + n].to_vec())
+        .collect();
+    let mut warnings = Vec::new();
+    if shortest < longest {
+        warnings.push(PoolingWarning::UnequalLengths { shortest, longest });
+    }
+    let r_hat = diagnostics::split_r_hat(&kept);
+    if r_hat.is_nan() || r_hat > max_r_hat {
+        warnings.push(PoolingWarning::HighRHat(r_hat));
+    }
+    let effective_sample_size = kept
+        .iter()
+        .map(|draws| diagnostics::effective_sample_size(draws))
+        .sum();
+    Some(PooledChains {
+        draws: kept.concat(),
+        r_hat,
+        effective_sample_size,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_replay_reproduces_chain() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let chain = run_chain(0.0, 1_000, kernel, &mut Sampler::with_seed(3));
+        let replayed = replay_chain(&chain, kernel);
+        assert_eq!(chain.draws, replayed.draws);
+        assert_eq!(chain.evaluations, replayed.evaluations);
+    }
+
+    #[test]
+    fn test_suggested_thinning_and_burn_in() {
+        // AR(1) with coefficient 0.9 and integrated autocorrelation time 19, started at 30
+        let mut rng = fastrand::Rng::with_seed(5);
+        let mut x = 30.0;
+        let draws: Vec<f64> = (0..20_000)
+            .map(|_| {
+                x = 0.9 * x + diagnostics::inverse_normal_cdf(rng.f64());
+                x
+            })
+            .collect();
+        let chain = Chain {
+            draws,
+            ..Default::default()
+        };
+        let burn_in = chain.suggested_burn_in();
+        assert!((38..=4_000).contains(&burn_in));
+        assert!(chain.draws[burn_in..].iter().all(|x| x.abs() < 15.0));
+        let thinning = chain.suggested_thinning();
+        assert!((15..=25).contains(&thinning));
+    }
+
+    #[test]
+    fn test_variance_of_few_draws() {
+        assert!(Chain::default().variance().is_nan());
+        let mut chain = Chain {
+            draws: vec![1.0],
+            ..Default::default()
+        };
+        assert!(chain.standard_deviation().is_nan());
+        chain.draws.push(3.0);
+        assert_eq!(chain.variance(), 2.0);
+    }
+
+    #[test]
+    fn test_quantiles_and_hpd_interval() {
+        let chain = Chain {
+            draws: vec![3.0, 1.0, 2.0, 4.0],
+            ..Default::default()
+        };
+        assert_eq!(
+            chain.quantiles(&[0.0, 0.5, 1.0, 0.25]),
+            vec![1.0, 2.5, 4.0, 1.75]
+        );
+        assert!(Chain::default().quantiles(&[0.5])[0].is_nan());
+        // Exponential draws, whose 90% HPD interval is (0, ln 10) rather than the equal-tailed
+        // (-ln 0.95, ln 20)
+        let mut rng = fastrand::Rng::with_seed(7);
+        let chain = Chain {
+            draws: (0..100_000).map(|_| -(1.0 - rng.f64()).ln()).collect(),
+            ..Default::default()
+        };
+        let (lower, upper) = chain.hpd_interval(0.9);
+        assert!(lower < 0.001);
+        assert!((upper - 10f64.ln()).abs() < 0.03);
+        let quantiles = chain.quantiles(&[0.05, 0.95]);
+        assert!((quantiles[0] + 0.95f64.ln()).abs() < 0.01);
+        assert!((quantiles[1] - 20f64.ln()).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_density() {
+        let mut rng = fastrand::Rng::with_seed(11);
+        let chain = Chain {
+            draws: (0..20_000)
+                .map(|_| diagnostics::inverse_normal_cdf(rng.f64()))
+                .collect(),
+            ..Default::default()
+        };
+        let density = chain.density(&[0.0, 1.0, 2.0]);
+        let normal = |x: f64| (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt();
+        for (estimate, x) in density.iter().zip([0.0, 1.0, 2.0]) {
+            assert!((estimate - normal(x)).abs() < 0.01);
+        }
+        let silverman = chain.density_with_bandwidth(&[0.0], Bandwidth::Silverman);
+        assert!((silverman[0] - normal(0.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_common_random_numbers() {
+        // Finite differences of the second moment of N(0, s^2) in s, whose derivative at s = 1
+        // is 2, have much smaller errors under common random numbers than between independent
+        // runs (though not zero, as a perturbation occasionally changes the number of uniforms
+        // an update consumes, decoupling the runs until the states happen to meet again)
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let second_moment = |scale: f64, seed: u64| {
+            let kernel = |sampler: &mut Sampler, x: f64| {
+                sampler.stepping_out_and_shrinkage(
+                    x,
+                    |x| -0.5 * (x / scale) * (x / scale),
+                    true,
+                    &tuning_parameters,
+                )
+            };
+            let chain =
+                run_chain_with_common_random_numbers(0.0, 2_000, kernel, seed, &mut Sampler::new());
+            chain.draws.iter().map(|x| x * x).sum::<f64>() / (chain.len() as f64)
+        };
+        let delta = 0.05;
+        let mut common = 0.0;
+        let mut independent = 0.0;
+        for seed in 0..10 {
+            let base = second_moment(1.0, seed);
+            common += ((second_moment(1.0 + delta, seed) - base) / delta - 2.0).powi(2);
+            independent += ((second_moment(1.0 + delta, seed + 10) - base) / delta - 2.0).powi(2);
+        }
+        assert!(common < 0.25 * independent);
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let chain = run_chain_with_common_random_numbers(0.0, 100, kernel, 17, &mut Sampler::new());
+        assert_eq!(replay_chain(&chain, kernel).draws, chain.draws);
+    }
+
+    #[test]
+    fn test_posterior_predictive() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let predictive = |x: f64, rng: &mut fastrand::Rng| vec![x + rng.f64(), x];
+        let chain =
+            run_chain_with_predictive(0.0, 100, kernel, 10, predictive, &mut Sampler::with_seed(3));
+        assert_eq!(chain.predictions.len(), 10);
+        for (i, prediction) in &chain.predictions {
+            assert_eq!(i % 10, 9);
+            assert_eq!(prediction[1], chain.draws[*i]);
+        }
+    }
+
+    #[test]
+    fn test_monitor() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let mut calls = Vec::new();
+        let monitor = |n: usize, chain: &Chain, report: &EfficiencyReport| {
+            assert_eq!(chain.len(), n);
+            assert_eq!(
+                report.evaluations.total(),
+                chain.evaluations.iter().sum::<u64>()
+            );
+            calls.push(n);
+            if n == 300 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let chain = run_chain_with_monitor(0.0, 1_000, kernel, 100, monitor, &mut Sampler::new());
+        assert_eq!(calls, [100, 200, 300]);
+        assert_eq!(chain.len(), 300);
+    }
+
+    #[test]
+    fn test_run_chain_until() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let rule = StoppingRule::EffectiveSampleSize(500.0);
+        let chain = run_chain_until(0.0, 100_000, kernel, 250, rule, &mut Sampler::with_seed(5));
+        assert!(rule.is_satisfied(&chain));
+        assert!(chain.len() < 100_000);
+        assert_eq!(chain.len() % 250, 0);
+        let rule = StoppingRule::MonteCarloStandardError(0.05);
+        let chain = run_chain_until(0.0, 100_000, kernel, 250, rule, &mut Sampler::with_seed(5));
+        assert!(chain.monte_carlo_standard_error() <= 0.05);
+        assert!(chain.len() < 100_000);
+    }
+
+    #[test]
+    fn test_run_chain_for() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let start = Instant::now();
+        let chain = run_chain_for(0.0, Duration::from_millis(50), kernel, &mut Sampler::new());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(!chain.is_empty());
+        assert_eq!(chain.evaluations.len(), chain.len());
+    }
+
+    #[test]
+    fn test_thinned_by_evaluations() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let chain =
+            run_chain_thinned_by_evaluations(0.0, 1_000, kernel, 50, &mut Sampler::with_seed(7));
+        assert_eq!(chain.len(), 1_000);
+        assert!(chain.rng_states.is_empty());
+        // All evaluations up to the last draw are attributed to draws
+        let total: u64 = chain.evaluations.iter().sum();
+        assert!((50_000..50_050).contains(&total));
+        assert!(chain.mean().abs() < 0.1);
+        // A kernel spending more than the budget in one update records its state repeatedly
+        let expensive = |_: &mut Sampler, x: f64| (x + 1.0, 120);
+        let chain = run_chain_thinned_by_evaluations(0.0, 5, expensive, 50, &mut Sampler::new());
+        assert_eq!(chain.draws, [1.0, 1.0, 2.0, 2.0, 3.0]);
+        assert_eq!(chain.evaluations, [120, 0, 120, 0, 120]);
+    }
+
+    #[test]
+    fn test_pool_chains() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let mut chains: Vec<Chain> = (0..4)
+            .map(|seed| run_chain(10.0, 2_000, kernel, &mut Sampler::with_seed(seed)))
+            .collect();
+        let pooled = pool_chains(&chains, 100, 1.01).unwrap();
+        assert!(pooled.is_valid());
+        assert_eq!(pooled.draws.len(), 4 * 1_900);
+        assert_eq!(pooled.draws[..1_900], chains[0].draws[100..]);
+        assert!(pooled.mean().abs() < 0.1);
+        assert!(pooled.effective_sample_size > 1_000.0);
+        // A chain stuck elsewhere, and of a different length
+        chains[3] = run_chain(10.0, 2_500, |_, x| (x, 1), &mut Sampler::new());
+        let pooled = pool_chains(&chains, 100, 1.01).unwrap();
+        assert!(!pooled.is_valid());
+        assert_eq!(
+            pooled.warnings[0],
+            PoolingWarning::UnequalLengths {
+                shortest: 2_000,
+                longest: 2_500
+            }
+        );
+        assert!(matches!(pooled.warnings[1], PoolingWarning::HighRHat(_)));
+        assert!(pool_chains(&chains, 1_997, 1.01).is_none());
+        assert!(pool_chains(&[], 0, 1.01).is_none());
+    }
+
+    #[test]
+    fn test_run_chain_with_metadata() {
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let kernel = |sampler: &mut Sampler, x: f64| {
+            sampler.stepping_out_and_shrinkage(x, |x| -0.5 * x * x, true, &tuning_parameters)
+        };
+        let chain = run_chain_with_metadata(0.0, 1_000, kernel, &mut Sampler::with_seed(11));
+        // The same draws as without metadata
+        let plain = run_chain(0.0, 1_000, kernel, &mut Sampler::with_seed(11));
+        assert_eq!(chain.draws, plain.draws);
+        assert_eq!(chain.metadata.len(), chain.len());
+        let mut previous = 0.0;
+        for ((x, evaluations), metadata) in chain
+            .draws
+            .iter()
+            .zip(&chain.evaluations)
+            .zip(&chain.metadata)
+        {
+            assert_eq!(metadata.evaluations, *evaluations);
+            assert!(metadata.width > 0.0);
+            // The slice level lies below the log density at both the previous and new states
+            assert!(metadata.slice_level < -0.5 * x * x);
+            assert!(metadata.slice_level <= -0.5 * previous * previous);
+            previous = *x;
+        }
+        let chain = run_chain_with_metadata(0.0, 3, |_, x| (x + 1.0, 2), &mut Sampler::new());
+        assert!(chain
+            .metadata
+            .iter()
+            .all(|metadata| metadata.width.is_nan()));
+    }
+
+    #[test]
+    fn test_run_chain_with_statistics() {
+        use crate::target::UnivariateTarget;
+        // Standard normal whose statistics are x^2 and the indicator of x > 1, reusing the
+        // square computed for the density at the latest point
+        struct Normal {
+            latest: (f64, f64),
+            n_statistic_evaluations: u64,
+        }
+        impl UnivariateTarget for Normal {
+            fn evaluate(&mut self, x: f64) -> f64 {
+                self.latest = (x, x * x);
+                -0.5 * self.latest.1
+            }
+        }
+        impl UnivariateTargetWithStatistics for Normal {
+            fn n_statistics(&self) -> usize {
+                2
+            }
+            fn statistics(&mut self, x: f64, out: &mut [f64]) {
+                let square = if self.latest.0 == x {
+                    self.latest.1
+                } else {
+                    self.n_statistic_evaluations += 1;
+                    x * x
+                };
+                out[0] = square;
+                out[1] = if x > 1.0 { 1.0 } else { 0.0 };
+            }
+        }
+        let mut target = Normal {
+            latest: (f64::NAN, f64::NAN),
+            n_statistic_evaluations: 0,
+        };
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
+        let (chain, summaries) = run_chain_with_statistics(
+            0.0,
+            20_000,
+            &mut target,
+            |sampler: &mut Sampler, x: f64, target: &mut Normal| {
+                sampler.stepping_out_and_shrinkage(
+                    x,
+                    |x| target.evaluate(x),
+                    true,
+                    &tuning_parameters,
+                )
+            },
+            &mut Sampler::with_seed(13),
+        );
+        assert_eq!(chain.len(), 20_000);
+        assert_eq!(summaries[0].count(), 20_000);
+        assert!((summaries[0].mean() - 1.0).abs() < 0.05);
+        assert!((summaries[1].mean() - 0.1587).abs() < 0.02);
+        // The accepted point is the latest evaluated
+        assert_eq!(target.n_statistic_evaluations, 0);
+    }
+}
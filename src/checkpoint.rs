@@ -0,0 +1,186 @@
+use crate::adaptation::WarmupAdapter;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+// Everything needed to resume a sampler exactly: the random number generator state, the number
+// of updates so far, the latest state, and the warmup adaptation (if any). Checkpoints are
+// stored as text with floating point values written as their bit patterns, so that resuming
+// reproduces the uninterrupted run bit for bit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub rng_state: u64,
+    pub iteration: u64,
+    pub state: Option<f64>,
+    pub adapter: Option<WarmupAdapter>,
+}
+
+const HEADER: &str = "slice_sampler checkpoint 1";
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn parse<T: std::str::FromStr>(value: Option<&str>) -> io::Result<T> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| invalid("malformed checkpoint value"))
+}
+
+fn parse_f64(value: Option<&str>) -> io::Result<f64> {
+    parse::<u64>(value).map(f64::from_bits)
+}
+
+// Fields may appear at most once, so that a corrupted file is not silently read.
+fn set_once<T>(slot: &mut Option<T>, value: T, field: &str) -> io::Result<()> {
+    match slot.replace(value) {
+        Some(_) => Err(invalid(&format!("duplicate {}", field))),
+        None => Ok(()),
+    }
+}
+
+impl Checkpoint {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "rng_state {}", self.rng_state)?;
+        writeln!(writer, "iteration {}", self.iteration)?;
+        if let Some(state) = self.state {
+            writeln!(writer, "state {}", state.to_bits())?;
+        }
+        if let Some(adapter) = &self.adapter {
+            writeln!(
+                writer,
+                "adapter {} {} {} {} {}",
+                adapter.width.to_bits(),
+                adapter.n_warmup,
+                adapter.n_updates,
+                adapter.n_recorded,
+                adapter.sum_of_widths.to_bits()
+            )?;
+        }
+        writer.flush()
+    }
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid("not a slice_sampler checkpoint"));
+        }
+        let mut rng_state = None;
+        let mut iteration = None;
+        let mut state = None;
+        let mut adapter = None;
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("rng_state") => set_once(&mut rng_state, parse(fields.next())?, "rng_state")?,
+                Some("iteration") => set_once(&mut iteration, parse(fields.next())?, "iteration")?,
+                Some("state") => set_once(&mut state, parse_f64(fields.next())?, "state")?,
+                Some("adapter") => {
+                    let value = WarmupAdapter {
+                        width: parse_f64(fields.next())?,
+                        n_warmup: parse(fields.next())?,
+                        n_updates: parse(fields.next())?,
+                        n_recorded: parse(fields.next())?,
+                        sum_of_widths: parse_f64(fields.next())?,
+                    };
+                    set_once(&mut adapter, value, "adapter")?
+                }
+                None => {}
+                Some(_) => return Err(invalid("unknown checkpoint field")),
+            }
+        }
+        Ok(Checkpoint {
+            rng_state: rng_state.ok_or_else(|| invalid("missing rng_state"))?,
+            iteration: iteration.ok_or_else(|| invalid("missing iteration"))?,
+            state,
+            adapter,
+        })
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Sampler;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_resume_matches_uninterrupted_run() {
+        let tuning_parameters = TuningParameters::new();
+        let f = |x: f64| -0.5 * x * x;
+        let new_sampler =
+            || Sampler::with_seed(37).with_warmup_adapter(WarmupAdapter::new(0.1, 50).unwrap());
+        let mut uninterrupted = new_sampler();
+        let mut x = 0.0;
+        let mut draws = Vec::new();
+        for _ in 0..100 {
+            (x, _) = uninterrupted.stepping_out_and_shrinkage(x, f, true, &tuning_parameters);
+            draws.push(x);
+        }
+        let mut interrupted = new_sampler();
+        let mut x = 0.0;
+        for _ in 0..30 {
+            (x, _) = interrupted.stepping_out_and_shrinkage(x, f, true, &tuning_parameters);
+        }
+        let path = std::env::temp_dir().join(format!(
+            "slice_sampler_checkpoint_{}.txt",
+            std::process::id()
+        ));
+        interrupted.checkpoint().save(&path).unwrap();
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut resumed = Sampler::resume(checkpoint);
+        assert_eq!(resumed.iteration(), 30);
+        let mut x = resumed.state().unwrap();
+        for draw in &draws[30..] {
+            (x, _) = resumed.stepping_out_and_shrinkage(x, f, true, &tuning_parameters);
+            assert_eq!(x, *draw);
+        }
+        assert_eq!(resumed.adapter(), uninterrupted.adapter());
+    }
+
+    #[test]
+    fn test_incomplete_checkpoints() {
+        let read = |text: &str| Checkpoint::read(text.as_bytes()).map_err(|e| e.to_string());
+        let header = format!("{}\n", HEADER);
+        assert_eq!(
+            read(&format!("{}rng_state 7\niteration 3\n", header)),
+            Ok(Checkpoint {
+                rng_state: 7,
+                iteration: 3,
+                state: None,
+                adapter: None,
+            })
+        );
+        assert_eq!(
+            read(&format!("{}iteration 3\n", header)),
+            Err("missing rng_state".to_string())
+        );
+        assert_eq!(
+            read(&format!("{}rng_state 7\n", header)),
+            Err("missing iteration".to_string())
+        );
+        assert_eq!(
+            read(&format!(
+                "{}rng_state 7\niteration 3\nrng_state 8\n",
+                header
+            )),
+            Err("duplicate rng_state".to_string())
+        );
+        assert_eq!(
+            read(&format!(
+                "{}rng_state 7\niteration 3\nstate 0\nstate 0\n",
+                header
+            )),
+            Err("duplicate state".to_string())
+        );
+        assert!(read(&format!("{}rng_state\niteration 3\n", header)).is_err());
+    }
+}
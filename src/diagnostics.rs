@@ -0,0 +1,461 @@
+fn mean(chain: &[f64]) -> f64 {
+    chain.iter().sum::<f64>() / (chain.len() as f64)
+}
+
+fn autocovariance(chain: &[f64], mean: f64, lag: usize) -> f64 {
+    let n = chain.len();
+    chain[..n - lag]
+        .iter()
+        .zip(&chain[lag..])
+        .map(|(a, b)| (a - mean) * (b - mean))
+        .sum::<f64>()
+        / (n as f64)
+}
+
+// Sample autocorrelation function of a chain for lags 0, 1, ..., max_lag (at most n - 1).
+pub fn autocorrelation(chain: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = chain.len();
+    if n < 2 {
+        return vec![1.0; n.min(max_lag + 1)];
+    }
+    let m = mean(chain);
+    let variance = autocovariance(chain, m, 0);
+    (0..=max_lag.min(n - 1))
+        .map(|lag| autocovariance(chain, m, lag) / variance)
+        .collect()
+}
+
+// Integrated autocorrelation time using Geyer's (1992) initial monotone sequence estimator.
+pub fn integrated_autocorrelation_time(chain: &[f64]) -> f64 {
+    let n = chain.len();
+    if n < 4 {
+        return 1.0;
+    }
+    let m = mean(chain);
+    let variance = autocovariance(chain, m, 0);
+    if variance == 0.0 {
+        return 1.0;
+    }
+    let rho = |lag: usize| autocovariance(chain, m, lag) / variance;
+    let mut sum = 0.0;
+    let mut previous = f64::INFINITY;
+    let mut k = 0;
+    while 2 * k + 1 < n {
+        let gamma = rho(2 * k) + rho(2 * k + 1);
+        if gamma <= 0.0 {
+            break;
+        }
+        let gamma = gamma.min(previous);
+        sum += gamma;
+        previous = gamma;
+        k += 1;
+    }
+    (2.0 * sum - 1.0).max(1.0 / (n as f64).log10())
+}
+
+// Effective sample size n / tau, where tau is the integrated autocorrelation time.
+pub fn effective_sample_size(chain: &[f64]) -> f64 {
+    (chain.len() as f64) / integrated_autocorrelation_time(chain)
+}
+
+// Monte Carlo standard error of the mean by the method of batch means, using floor(sqrt(n))
+// batches of floor(sqrt(n)) draws.
+pub fn monte_carlo_standard_error(chain: &[f64]) -> f64 {
+    let batch_size = (chain.len() as f64).sqrt().floor() as usize;
+    if batch_size < 2 {
+        return f64::NAN;
+    }
+    let batch_means: Vec<f64> = chain.chunks_exact(batch_size).map(mean).collect();
+    let a = batch_means.len();
+    let m = mean(&batch_means);
+    let variance = batch_means.iter().map(|x| (x - m).powi(2)).sum::<f64>() / ((a - 1) as f64);
+    (variance * (batch_size as f64) / (chain.len() as f64)).sqrt()
+}
+
+// Spectral density at frequency zero divided by 2 pi, i.e., the asymptotic variance of sqrt(n)
+// times the mean, by batch means, which a trend inflates less than estimates from the
+// autocorrelations do.
+fn spectral_density_at_zero(chain: &[f64]) -> f64 {
+    (chain.len() as f64) * monte_carlo_standard_error(chain).powi(2)
+}
+
+// Geweke's (1992) convergence diagnostic: the difference between the means of the first
+// fraction first and the last fraction last of the chain (e.g., 0.1 and 0.5), over its standard
+// error from the spectral densities at zero of the two parts. Approximately standard normal for
+// a stationary chain, so values beyond about 2 in magnitude suggest that the start of the chain
+// has not yet converged. NaN if either part has fewer than four draws.
+pub fn geweke_z_score(chain: &[f64], first: f64, last: f64) -> f64 {
+    let n = chain.len();
+    let n_first = ((first * (n as f64)) as usize).min(n);
+    let n_last = ((last * (n as f64)) as usize).min(n);
+    if n_first < 4 || n_last < 4 {
+        return f64::NAN;
+    }
+    let a = &chain[..n_first];
+    let b = &chain[n - n_last..];
+    let variance = spectral_density_at_zero(a) / (n_first as f64)
+        + spectral_density_at_zero(b) / (n_last as f64);
+    (mean(a) - mean(b)) / variance.sqrt()
+}
+
+// Heidelberger and Welch's (1983) diagnostic, as in R's coda package.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeidelbergerWelch {
+    // Index of the first draw of the stationary part, or None if the stationarity test failed
+    // even after discarding half of the chain.
+    pub start: Option<usize>,
+    // Cramer-von Mises statistic of the last test made, which failed if it exceeds 0.461 (the
+    // 5% critical value).
+    pub cramer_von_mises: f64,
+    // Mean of the stationary part and the half-width of its 95% confidence interval (NaN if the
+    // stationarity test failed).
+    pub mean: f64,
+    pub half_width: f64,
+    // Whether the half-width is less than epsilon times the magnitude of the mean, i.e., whether
+    // the chain is long enough to estimate the mean to that relative accuracy.
+    pub half_width_passed: bool,
+}
+
+// Heidelberger and Welch's (1983) diagnostic for a single chain: a Cramer-von Mises test of
+// stationarity is applied to the chain after discarding its first 0%, 10%, ..., 50% in turn
+// until it passes, and then the half-width test compares the confidence interval of the mean of
+// the remaining draws with epsilon (e.g., 0.1) times the magnitude of the mean. The spectral
+// density at zero is estimated from the second half of the chain throughout.
+pub fn heidelberger_welch(chain: &[f64], epsilon: f64) -> HeidelbergerWelch {
+    const CRITICAL_VALUE: f64 = 0.461;
+    let n = chain.len();
+    let mut result = HeidelbergerWelch {
+        start: None,
+        cramer_von_mises: f64::NAN,
+        mean: f64::NAN,
+        half_width: f64::NAN,
+        half_width_passed: false,
+    };
+    if n < 8 {
+        return result;
+    }
+    let spectral_density = spectral_density_at_zero(&chain[n / 2..]);
+    for tenth in 0..=5 {
+        let start = tenth * n / 10;
+        let x = &chain[start..];
+        let m = x.len() as f64;
+        let x_bar = mean(x);
+        // The Brownian bridge at k / m is the scaled partial sum of the first k deviations
+        let mut partial_sum = 0.0;
+        let mut integral = 0.0;
+        for xi in x {
+            partial_sum += xi - x_bar;
+            integral += partial_sum * partial_sum;
+        }
+        result.cramer_von_mises = integral / (m * m * spectral_density);
+        if result.cramer_von_mises <= CRITICAL_VALUE {
+            result.start = Some(start);
+            result.mean = x_bar;
+            result.half_width = 1.959_963_984_540_054 * (spectral_density / m).sqrt();
+            result.half_width_passed = result.half_width < epsilon * x_bar.abs();
+            break;
+        }
+    }
+    result
+}
+
+fn polynomial(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |sum, &c| sum * x + c)
+}
+
+// Inverse of the standard normal distribution function (Wichura's AS241 algorithm, PPND16).
+#[allow(clippy::excessive_precision)]
+pub(crate) fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 8] = [
+        3.387132872796366608,
+        133.14166789178437745,
+        1971.5909503065514427,
+        13731.693765509461125,
+        45921.953931549871457,
+        67265.770927008700853,
+        33430.575583588128105,
+        2509.0809287301226727,
+    ];
+    const B: [f64; 8] = [
+        1.0,
+        42.313330701600911252,
+        687.1870074920579083,
+        5394.1960214247511077,
+        21213.794301586595867,
+        39307.89580009271061,
+        28729.085735721942674,
+        5226.495278852545925,
+    ];
+    const C: [f64; 8] = [
+        1.42343711074968357734,
+        4.6303378461565452959,
+        5.7694972214606914055,
+        3.64784832476320460504,
+        1.27045825245236838258,
+        0.24178072517745061177,
+        0.0227238449892691845833,
+        7.7454501427834140764e-4,
+    ];
+    const D: [f64; 8] = [
+        1.0,
+        2.05319162663775882187,
+        1.6763848301838038494,
+        0.68976733498510000455,
+        0.14810397642748007459,
+        0.0151986665636164571966,
+        5.475938084995344946e-4,
+        1.05075007164441684324e-9,
+    ];
+    const E: [f64; 8] = [
+        6.6579046435011037772,
+        5.4637849111641143699,
+        1.7848265399172913358,
+        0.29656057182850489123,
+        0.026532189526576123093,
+        0.0012426609473880784386,
+        2.71155556874348757815e-5,
+        2.01033439929228813265e-7,
+    ];
+    const F: [f64; 8] = [
+        1.0,
+        0.59983220655588793769,
+        0.13692988092273580531,
+        0.0148753612908506148525,
+        7.868691311456132591e-4,
+        1.8463183175100546818e-5,
+        1.4215117583164458887e-7,
+        2.04426310338993978564e-15,
+    ];
+    let q = p - 0.5;
+    if q.abs() <= 0.425 {
+        let r = 0.180625 - q * q;
+        return q * polynomial(&A, r) / polynomial(&B, r);
+    }
+    let r = if q < 0.0 { p } else { 1.0 - p };
+    if r <= 0.0 {
+        return if q < 0.0 {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+    let r = (-r.ln()).sqrt();
+    let value = if r <= 5.0 {
+        polynomial(&C, r - 1.6) / polynomial(&D, r - 1.6)
+    } else {
+        polynomial(&E, r - 5.0) / polynomial(&F, r - 5.0)
+    };
+    if q < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
+
+// Replaces the pooled draws by normal scores of their ranks, (r - 3/8) / (S + 1/4), with ties
+// given their average rank.
+fn rank_normalize(chains: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let mut pooled: Vec<(f64, usize, usize)> = chains
+        .iter()
+        .enumerate()
+        .flat_map(|(c, chain)| chain.iter().enumerate().map(move |(t, &x)| (x, c, t)))
+        .collect();
+    pooled.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let s = pooled.len() as f64;
+    let mut z: Vec<Vec<f64>> = chains.iter().map(|chain| vec![0.0; chain.len()]).collect();
+    let mut i = 0;
+    while i < pooled.len() {
+        let mut j = i;
+        while j + 1 < pooled.len() && pooled[j + 1].0 == pooled[i].0 {
+            j += 1;
+        }
+        let rank = 0.5 * ((i + 1 + j + 1) as f64);
+        let score = inverse_normal_cdf((rank - 0.375) / (s + 0.25));
+        for &(_, c, t) in &pooled[i..=j] {
+            z[c][t] = score;
+        }
+        i = j + 1;
+    }
+    z
+}
+
+fn variance(chain: &[f64]) -> f64 {
+    let m = mean(chain);
+    chain.iter().map(|x| (x - m).powi(2)).sum::<f64>() / ((chain.len() - 1) as f64)
+}
+
+// Potential scale reduction factor of Gelman and Rubin, after splitting each chain in half.
+fn split_potential_scale_reduction(chains: &[Vec<f64>]) -> f64 {
+    let halves: Vec<&[f64]> = chains
+        .iter()
+        .flat_map(|chain| {
+            let n = chain.len() / 2;
+            [&chain[..n], &chain[chain.len() - n..]]
+        })
+        .collect();
+    let n = halves[0].len() as f64;
+    let means: Vec<f64> = halves.iter().map(|half| mean(half)).collect();
+    let within = mean(&halves.iter().map(|half| variance(half)).collect::<Vec<_>>());
+    let between_over_n = variance(&means);
+    ((n - 1.0) / n + between_over_n / within).sqrt()
+}
+
+// Rank-normalized split-Rhat of Vehtari et al. (2021) for one parameter, given its draws in each
+// chain (of equal lengths, at least four draws each): the maximum of the bulk and tail (folded)
+// versions. Values above about 1.01 indicate that the chains have not converged. NaN if there
+// are no chains, or if they are shorter than four draws or of unequal lengths.
+pub fn split_r_hat(chains: &[Vec<f64>]) -> f64 {
+    let n = match chains.first() {
+        Some(chain) => chain.len(),
+        None => return f64::NAN,
+    };
+    if n < 4 || chains.iter().any(|chain| chain.len() != n) {
+        return f64::NAN;
+    }
+    let bulk = split_potential_scale_reduction(&rank_normalize(chains));
+    let mut pooled: Vec<f64> = chains.iter().flatten().copied().collect();
+    pooled.sort_by(|a, b| a.total_cmp(b));
+    let median = 0.5 * (pooled[(pooled.len() - 1) / 2] + pooled[pooled.len() / 2]);
+    let folded: Vec<Vec<f64>> = chains
+        .iter()
+        .map(|chain| chain.iter().map(|x| (x - median).abs()).collect())
+        .collect();
+    let tail = split_potential_scale_reduction(&rank_normalize(&folded));
+    bulk.max(tail)
+}
+
+// Rank-normalized split-Rhat for each parameter, where chains[c][t] holds the parameters of the
+// t-th draw of the c-th chain, empty if there are no draws and NaN for every parameter if the
+// draws are not all of the same length.
+pub fn split_r_hat_per_parameter(chains: &[Vec<Vec<f64>>]) -> Vec<f64> {
+    let n_parameters = match chains.iter().flatten().next() {
+        Some(draw) => draw.len(),
+        None => return Vec::new(),
+    };
+    if chains
+        .iter()
+        .flatten()
+        .any(|draw| draw.len() != n_parameters)
+    {
+        return vec![f64::NAN; n_parameters];
+    }
+    (0..n_parameters)
+        .map(|p| {
+            let draws: Vec<Vec<f64>> = chains
+                .iter()
+                .map(|chain| chain.iter().map(|draw| draw[p]).collect())
+                .collect();
+            split_r_hat(&draws)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ar1(phi: f64, n: usize, seed: u64) -> Vec<f64> {
+        // Standard normal innovations by the Box-Muller transform
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut x = 0.0;
+        (0..n)
+            .map(|_| {
+                let z = (-2.0 * (1.0 - rng.f64()).ln()).sqrt()
+                    * (2.0 * std::f64::consts::PI * rng.f64()).cos();
+                x = phi * x + z;
+                x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_autocorrelation_ar1() {
+        let chain = ar1(0.8, 100_000, 1);
+        let acf = autocorrelation(&chain, 3);
+        assert_eq!(acf[0], 1.0);
+        assert!((acf[1] - 0.8).abs() < 0.02);
+        assert!((acf[3] - 0.512).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_effective_sample_size() {
+        let n = 100_000;
+        let iid = ar1(0.0, n, 2);
+        assert!((effective_sample_size(&iid) / (n as f64) - 1.0).abs() < 0.1);
+        let phi = 0.9;
+        let chain = ar1(phi, n, 3);
+        let expected = (n as f64) * (1.0 - phi) / (1.0 + phi);
+        assert!((effective_sample_size(&chain) / expected - 1.0).abs() < 0.2);
+        // Var(x) = 1 / (1 - phi^2), so the MCSE is about sqrt(Var(x) / ESS)
+        let mcse = (1.0 / (1.0 - phi * phi) / expected).sqrt();
+        assert!((monte_carlo_standard_error(&chain) / mcse - 1.0).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_single_chain_convergence() {
+        let stationary: Vec<f64> = ar1(0.5, 10_000, 4).iter().map(|x| x + 10.0).collect();
+        assert!(geweke_z_score(&stationary, 0.1, 0.5).abs() < 3.0);
+        let result = heidelberger_welch(&stationary, 0.1);
+        assert_eq!(result.start, Some(0));
+        assert!((result.mean - 10.0).abs() < result.half_width);
+        assert!(result.half_width_passed);
+        // Stuck at a shifted level for the first 15% of the chain
+        let transient: Vec<f64> = stationary
+            .iter()
+            .enumerate()
+            .map(|(t, x)| if t < 1_500 { x + 2.0 } else { *x })
+            .collect();
+        assert!(geweke_z_score(&transient, 0.1, 0.5) > 3.0);
+        let result = heidelberger_welch(&transient, 0.1);
+        assert!(result.start.unwrap() > 0);
+        assert!(result.start.unwrap() <= 2_000);
+        // Drifting throughout
+        let drifting: Vec<f64> = stationary
+            .iter()
+            .enumerate()
+            .map(|(t, x)| x + 0.001 * (t as f64))
+            .collect();
+        let result = heidelberger_welch(&drifting, 0.1);
+        assert_eq!(result.start, None);
+        assert!(!result.half_width_passed);
+    }
+
+    #[test]
+    fn test_inverse_normal_cdf() {
+        assert_eq!(inverse_normal_cdf(0.5), 0.0);
+        assert!((inverse_normal_cdf(0.975) - 1.959_963_984_540_054).abs() < 1e-12);
+        assert!((inverse_normal_cdf(1e-10) + 6.361_340_902_404_056).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_r_hat() {
+        let chains: Vec<Vec<f64>> = (0..4).map(|c| ar1(0.5, 1_000, 10 + c)).collect();
+        assert!(split_r_hat(&chains) < 1.01);
+        let mut shifted = chains.clone();
+        for x in shifted[0].iter_mut() {
+            *x += 3.0;
+        }
+        assert!(split_r_hat(&shifted) > 1.1);
+        let draws: Vec<Vec<Vec<f64>>> = chains
+            .iter()
+            .zip(shifted.iter())
+            .map(|(a, b)| a.iter().zip(b.iter()).map(|(&x, &y)| vec![x, y]).collect())
+            .collect();
+        let r_hats = split_r_hat_per_parameter(&draws);
+        assert!(r_hats[0] < 1.01 && r_hats[1] > 1.1);
+    }
+
+    #[test]
+    fn test_split_r_hat_of_too_few_draws() {
+        assert!(split_r_hat(&[]).is_nan());
+        assert!(split_r_hat(&[vec![], vec![]]).is_nan());
+        assert!(split_r_hat(&[vec![1.0, 2.0, 3.0]]).is_nan());
+        assert!(split_r_hat(&[vec![1.0, 2.0, 3.0, 4.0], vec![1.0, 2.0, 3.0]]).is_nan());
+        assert!(split_r_hat_per_parameter(&[]).is_empty());
+        assert!(split_r_hat_per_parameter(&[vec![], vec![]]).is_empty());
+        let r_hats = split_r_hat_per_parameter(&[vec![vec![1.0, 2.0], vec![3.0, 4.0]]]);
+        assert!(r_hats.len() == 2 && r_hats.iter().all(|r_hat| r_hat.is_nan()));
+        let r_hats = split_r_hat_per_parameter(&[vec![vec![1.0, 2.0]; 4], vec![vec![1.0]; 4]]);
+        assert!(r_hats.len() == 2 && r_hats.iter().all(|r_hat| r_hat.is_nan()));
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+// Histogram of a count recorded once per draw, e.g., the number of target evaluations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountHistogram {
+    frequencies: BTreeMap<u64, u64>,
+    n_draws: u64,
+    total: u64,
+}
+
+impl CountHistogram {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn record(&mut self, count: u64) {
+        *self.frequencies.entry(count).or_insert(0) += 1;
+        self.n_draws += 1;
+        self.total += count;
+    }
+    pub fn n_draws(&self) -> u64 {
+        self.n_draws
+    }
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+    pub fn mean(&self) -> f64 {
+        self.total as f64 / self.n_draws as f64
+    }
+    pub fn max(&self) -> Option<u64> {
+        self.frequencies.keys().next_back().copied()
+    }
+    // Pairs of a count and the number of draws with that count, in increasing order of count.
+    pub fn frequencies(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.frequencies.iter().map(|(&count, &n)| (count, n))
+    }
+}
+
+// Cost of the draws of a run broken down by phase: all target evaluations, the evaluations made
+// while expanding the initial interval, and the proposals rejected while shrinking. Frequent
+// expansion suggests a larger width and frequent rejections a smaller one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EfficiencyReport {
+    pub evaluations: CountHistogram,
+    pub expansion_evaluations: CountHistogram,
+    pub shrinkage_rejections: CountHistogram,
+}
+
+impl EfficiencyReport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn n_draws(&self) -> u64 {
+        self.evaluations.n_draws()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_histogram() {
+        let mut histogram = CountHistogram::new();
+        for count in [3, 5, 3, 4, 3] {
+            histogram.record(count);
+        }
+        assert_eq!(histogram.n_draws(), 5);
+        assert_eq!(histogram.total(), 18);
+        assert_eq!(histogram.mean(), 3.6);
+        assert_eq!(histogram.max(), Some(5));
+        assert_eq!(
+            histogram.frequencies().collect::<Vec<_>>(),
+            [(3, 3), (4, 1), (5, 1)]
+        );
+    }
+}
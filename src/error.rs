@@ -0,0 +1,413 @@
+use crate::univariate::StepLimit;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuningError {
+    InvalidWidth(f64),
+    InvalidStepLimit(StepLimit),
+    InvalidMaxEvaluations(u64),
+    InvalidFactor(u32),
+    InvalidTemperature(f64),
+    InvalidSchedule(f64),
+    InvalidMinWidth(f64),
+    InvalidBounds { lower: f64, upper: f64 },
+    InvalidTargetEvaluations(f64),
+    InvalidLearningRate(f64),
+    InvalidDecay(f64),
+    InvalidShape(f64),
+    InvalidStepSize(f64),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::InvalidWidth(w) => {
+                write!(f, "initial width must be finite and positive, got {}", w)
+            }
+            TuningError::InvalidStepLimit(limit) => {
+                write!(
+                    f,
+                    "step limit {:?} allows no expansion; use StepLimit::None",
+                    limit
+                )
+            }
+            TuningError::InvalidMaxEvaluations(n) => {
+                write!(
+                    f,
+                    "maximum number of evaluations must be positive, got {}",
+                    n
+                )
+            }
+            TuningError::InvalidFactor(factor) => {
+                write!(f, "expansion factor must be at least 2, got {}", factor)
+            }
+            TuningError::InvalidTemperature(temperature) => {
+                write!(
+                    f,
+                    "temperature must be finite and positive, got {}",
+                    temperature
+                )
+            }
+            TuningError::InvalidSchedule(lambda) => {
+                write!(
+                    f,
+                    "schedule must increase from 0 to 1, got {} out of order",
+                    lambda
+                )
+            }
+            TuningError::InvalidMinWidth(min_width) => {
+                write!(
+                    f,
+                    "minimum width must be positive and less than the initial width, got {}",
+                    min_width
+                )
+            }
+            TuningError::InvalidBounds { lower, upper } => {
+                write!(
+                    f,
+                    "bounds must not be NaN and must satisfy lower < upper, got [{}, {}]",
+                    lower, upper
+                )
+            }
+            TuningError::InvalidTargetEvaluations(n) => {
+                write!(
+                    f,
+                    "target number of evaluations must be finite and at least 1, got {}",
+                    n
+                )
+            }
+            TuningError::InvalidLearningRate(rate) => {
+                write!(f, "learning rate must be finite and positive, got {}", rate)
+            }
+            TuningError::InvalidDecay(decay) => {
+                write!(f, "decay must be in (0.5, 1], got {}", decay)
+            }
+            TuningError::InvalidShape(shape) => {
+                write!(f, "shape must be finite and positive, got {}", shape)
+            }
+            TuningError::InvalidStepSize(step_size) => {
+                write!(
+                    f,
+                    "step size must be finite and positive, got {}",
+                    step_size
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for TuningError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SliceError {
+    InvalidBounds { left: f64, right: f64 },
+    NonFiniteStart(f64),
+    ZeroDensityAtStart(f64),
+    NanDensity(f64),
+    EvaluationBudgetExhausted(u64),
+    DegenerateInterval(f64),
+    InfiniteDensityAtStart(f64),
+    InvalidTuning(TuningError),
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::InvalidBounds { left, right } => {
+                write!(
+                    f,
+                    "invalid bounds [{}, {}] for the current state",
+                    left, right
+                )
+            }
+            SliceError::NonFiniteStart(x) => write!(f, "starting point {} is not finite", x),
+            SliceError::ZeroDensityAtStart(x) => {
+                write!(f, "target has zero density at the starting point {}", x)
+            }
+            SliceError::NanDensity(x) => write!(f, "target evaluated to NaN at {}", x),
+            SliceError::InfiniteDensityAtStart(x) => write!(
+                f,
+                "target has infinite density at the starting point {}; a density with a pole \
+                 there may be evaluated on the log scale, or the starting point moved",
+                x
+            ),
+            SliceError::EvaluationBudgetExhausted(n) => {
+                write!(f, "exhausted the budget of {} target evaluations", n)
+            }
+            SliceError::DegenerateInterval(x) => write!(
+                f,
+                "interval shrank below the minimum width around {} without an acceptable \
+                 proposal; the target may be discontinuous or mis-scaled",
+                x
+            ),
+            SliceError::InvalidTuning(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for SliceError {}
+
+impl From<TuningError> for SliceError {
+    fn from(error: TuningError) -> Self {
+        SliceError::InvalidTuning(error)
+    }
+}
+
+// Wraps a target so that NaN evaluations, and a zero or infinite density at the first evaluation
+// (which every sampler makes at the current state), are reported as errors.
+pub(crate) fn checked_target<S: FnMut(f64) -> f64>(
+    mut f: S,
+    on_log_scale: bool,
+) -> impl FnMut(f64) -> Result<f64, SliceError> {
+    let mut at_start = true;
+    move |x: f64| {
+        let fx = f(x);
+        if fx.is_nan() {
+            return Err(SliceError::NanDensity(x));
+        }
+        let first = core::mem::take(&mut at_start);
+        if first && ((on_log_scale && fx == f64::NEG_INFINITY) || (!on_log_scale && fx <= 0.0)) {
+            return Err(SliceError::ZeroDensityAtStart(x));
+        }
+        if first && fx == f64::INFINITY {
+            return Err(SliceError::InfiniteDensityAtStart(x));
+        }
+        Ok(fx)
+    }
+}
+
+// Marker error for an exhausted evaluation budget in the infallible samplers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BudgetExhausted;
+
+// Wraps a fallible target so that evaluations beyond max_evaluations (if any) fail with error.
+pub(crate) fn budgeted_target<E: Copy, S: FnMut(f64) -> Result<f64, E>>(
+    mut f: S,
+    max_evaluations: Option<u64>,
+    error: E,
+) -> impl FnMut(f64) -> Result<f64, E> {
+    let mut evaluation_counter = 0;
+    move |x: f64| {
+        if Some(evaluation_counter) == max_evaluations {
+            return Err(error);
+        }
+        evaluation_counter += 1;
+        f(x)
+    }
+}
+
+pub(crate) fn check_width(w: f64) -> Result<f64, TuningError> {
+    if w.is_finite() && w > 0.0 {
+        Ok(w)
+    } else {
+        Err(TuningError::InvalidWidth(w))
+    }
+}
+
+pub(crate) fn check_step_limit(limit: StepLimit) -> Result<StepLimit, TuningError> {
+    match limit {
+        StepLimit::Max(0) => Err(TuningError::InvalidStepLimit(limit)),
+        _ => Ok(limit),
+    }
+}
+
+pub(crate) fn check_max_evaluations(n: Option<u64>) -> Result<Option<u64>, TuningError> {
+    match n {
+        Some(0) => Err(TuningError::InvalidMaxEvaluations(0)),
+        _ => Ok(n),
+    }
+}
+
+pub(crate) fn check_min_width(min_width: Option<f64>, w: f64) -> Result<Option<f64>, TuningError> {
+    match min_width {
+        Some(m) if !(m > 0.0 && m < w) => Err(TuningError::InvalidMinWidth(m)),
+        _ => Ok(min_width),
+    }
+}
+
+// The result of a fallible sampler with a minimum width, where an unchanged state drawn from an
+// interval narrower than the minimum means that shrinkage stopped there.
+pub(crate) fn check_degenerate(
+    x: f64,
+    (x1, evaluation_counter, (l, r)): (f64, u64, (f64, f64)),
+    min_width: Option<f64>,
+) -> Result<(f64, u64), SliceError> {
+    match min_width {
+        Some(m) if x1 == x && r - l < m => Err(SliceError::DegenerateInterval(x)),
+        _ => Ok((x1, evaluation_counter)),
+    }
+}
+
+pub(crate) fn check_start(x: f64) -> Result<f64, SliceError> {
+    if x.is_finite() {
+        Ok(x)
+    } else {
+        Err(SliceError::NonFiniteStart(x))
+    }
+}
+
+// Bounds, as tuning parameters, may be infinite but must not be NaN and must satisfy
+// lower < upper.
+pub(crate) fn check_support(lower: f64, upper: f64) -> Result<(f64, f64), TuningError> {
+    if lower < upper {
+        Ok((lower, upper))
+    } else {
+        Err(TuningError::InvalidBounds { lower, upper })
+    }
+}
+
+// Bounds may be infinite but must not be NaN and must contain x with left < right.
+pub(crate) fn check_bounds(x: f64, left: f64, right: f64) -> Result<(), SliceError> {
+    if left <= x && x <= right && left < right {
+        Ok(())
+    } else {
+        Err(SliceError::InvalidBounds { left, right })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::doubling::{self, try_univariate_slice_sampler_doubling_and_shrinkage};
+    use crate::univariate::shrinkage::try_univariate_slice_sampler_shrinkage;
+    use crate::univariate::stepping_out::{
+        self, try_univariate_slice_sampler_stepping_out_and_shrinkage,
+    };
+
+    #[test]
+    fn test_nan_target() {
+        let tuning_parameters = stepping_out::TuningParameters::builder()
+            .width(1.)
+            .build()
+            .unwrap();
+        let result = try_univariate_slice_sampler_stepping_out_and_shrinkage(
+            0.5,
+            |x| if x < 0.5 { f64::NAN } else { -x },
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert!(matches!(result, Err(SliceError::NanDensity(x)) if x < 0.5));
+    }
+
+    #[test]
+    fn test_zero_density_at_start() {
+        let tuning_parameters = doubling::TuningParameters::builder()
+            .width(1.)
+            .build()
+            .unwrap();
+        let result = try_univariate_slice_sampler_doubling_and_shrinkage(
+            -1.0,
+            |x| if x < 0.0 { f64::NEG_INFINITY } else { -x },
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert_eq!(result, Err(SliceError::ZeroDensityAtStart(-1.0)));
+        let result = try_univariate_slice_sampler_shrinkage(0.0, |x| x, false, 0.0, 1.0, &mut None);
+        assert_eq!(result, Err(SliceError::ZeroDensityAtStart(0.0)));
+        let result =
+            try_univariate_slice_sampler_shrinkage(0.0, |x| 1.0 / x, false, -1.0, 1.0, &mut None);
+        assert_eq!(result, Err(SliceError::InfiniteDensityAtStart(0.0)));
+    }
+
+    #[test]
+    fn test_invalid_tuning_parameters() {
+        for w in [0.0, -1.0, f64::INFINITY, f64::NAN] {
+            let result = stepping_out::TuningParameters::builder().width(w).build();
+            assert!(matches!(result, Err(TuningError::InvalidWidth(_))));
+        }
+        let result = doubling::TuningParameters::builder()
+            .max_number_of_doubles(StepLimit::Max(0))
+            .build();
+        assert_eq!(
+            result.unwrap_err(),
+            TuningError::InvalidStepLimit(StepLimit::Max(0))
+        );
+        let result = stepping_out::TuningParameters::builder()
+            .max_evaluations(0)
+            .build();
+        assert_eq!(result.unwrap_err(), TuningError::InvalidMaxEvaluations(0));
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let f = |x: f64| -x * x;
+        let tuning_parameters = doubling::TuningParameters::new();
+        let result = try_univariate_slice_sampler_doubling_and_shrinkage(
+            f64::NAN,
+            f,
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert!(matches!(result, Err(SliceError::NonFiniteStart(_))));
+        let result = try_univariate_slice_sampler_shrinkage(2.0, f, true, 0.0, 1.0, &mut None);
+        assert_eq!(
+            result,
+            Err(SliceError::InvalidBounds {
+                left: 0.0,
+                right: 1.0
+            })
+        );
+        let result = try_univariate_slice_sampler_shrinkage(0.5, f, true, f64::NAN, 1.0, &mut None);
+        assert!(matches!(result, Err(SliceError::InvalidBounds { .. })));
+        let result = try_univariate_slice_sampler_shrinkage(0.5, f, true, 0.0, 1.0, &mut None);
+        assert!(result.is_ok());
+        let result =
+            try_univariate_slice_sampler_shrinkage(0.5, f, true, 0.0, f64::INFINITY, &mut None);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_min_width() {
+        for min_width in [0.0, 1.0, f64::NAN] {
+            let result = stepping_out::TuningParameters::builder()
+                .min_width(min_width)
+                .build();
+            assert!(matches!(result, Err(TuningError::InvalidMinWidth(_))));
+        }
+        let tuning_parameters = stepping_out::TuningParameters::builder()
+            .min_width(1e-6)
+            .build()
+            .unwrap();
+        // After its first evaluation the density is zero everywhere, so shrinkage would otherwise
+        // continue until the interval collapses onto the current state
+        let vanishing = || {
+            let mut first = true;
+            move |_: f64| {
+                let value = if first { 1.0 } else { 0.0 };
+                first = false;
+                value
+            }
+        };
+        let result = try_univariate_slice_sampler_stepping_out_and_shrinkage(
+            0.5,
+            vanishing(),
+            false,
+            &tuning_parameters,
+            &mut Some(fastrand::Rng::with_seed(157)),
+        );
+        assert_eq!(result, Err(SliceError::DegenerateInterval(0.5)));
+        let (x, evaluations) = stepping_out::univariate_slice_sampler_stepping_out_and_shrinkage(
+            0.5,
+            vanishing(),
+            false,
+            &tuning_parameters,
+            &mut Some(fastrand::Rng::with_seed(157)),
+        );
+        assert_eq!(x, 0.5);
+        assert!(evaluations < 40);
+        let result = try_univariate_slice_sampler_doubling_and_shrinkage(
+            0.5,
+            |x| -x * x,
+            true,
+            &doubling::TuningParameters::builder()
+                .min_width(1e-6)
+                .build()
+                .unwrap(),
+            &mut None,
+        );
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,325 @@
+use crate::chain::Chain;
+use crate::trace::Trace;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+// Writes draws of several parameters from several chains as CSV in long format (one row per
+// draw with columns chain, draw, and one per parameter), where chains[c][t][p] is parameter p of
+// draw t in chain c. This layout loads directly into pandas, ArviZ (via from_dataframe) and R.
+pub fn write_draws_csv<W: Write>(
+    mut writer: W,
+    parameter_names: &[&str],
+    chains: &[Vec<Vec<f64>>],
+) -> io::Result<()> {
+    write!(writer, "chain,draw")?;
+    for name in parameter_names {
+        write!(writer, ",{}", name)?;
+    }
+    writeln!(writer)?;
+    for (c, chain) in chains.iter().enumerate() {
+        for (t, draw) in chain.iter().enumerate() {
+            write!(writer, "{},{}", c, t)?;
+            for x in draw {
+                write!(writer, ",{}", x)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()
+}
+
+// Writes univariate chains as CSV with columns chain, draw, the parameter, and evaluations.
+pub fn write_chains_csv<W: Write>(mut writer: W, name: &str, chains: &[Chain]) -> io::Result<()> {
+    writeln!(writer, "chain,draw,{},evaluations", name)?;
+    for (c, chain) in chains.iter().enumerate() {
+        for (t, (x, evaluations)) in chain.draws.iter().zip(&chain.evaluations).enumerate() {
+            writeln!(writer, "{},{},{},{}", c, t, x, evaluations)?;
+        }
+    }
+    writer.flush()
+}
+
+// Writes univariate chains recorded with run_chain_with_metadata as CSV with columns chain, draw,
+// the parameter, evaluations, width, and slice_level (the last two empty if not recorded).
+pub fn write_chains_with_metadata_csv<W: Write>(
+    mut writer: W,
+    name: &str,
+    chains: &[Chain],
+) -> io::Result<()> {
+    writeln!(writer, "chain,draw,{},evaluations,width,slice_level", name)?;
+    for (c, chain) in chains.iter().enumerate() {
+        for (t, (x, evaluations)) in chain.draws.iter().zip(&chain.evaluations).enumerate() {
+            write!(writer, "{},{},{},{},", c, t, x, evaluations)?;
+            match chain.metadata.get(t) {
+                Some(metadata) if !metadata.width.is_nan() => {
+                    writeln!(writer, "{},{}", metadata.width, metadata.slice_level)?
+                }
+                _ => writeln!(writer, ",")?,
+            }
+        }
+    }
+    writer.flush()
+}
+
+// Writes a trace as CSV with columns draw, the parameter, evaluations, and slice_level (empty if
+// not recorded), reading spilled records back in chunks.
+pub fn write_trace_csv<W: Write>(mut writer: W, name: &str, trace: &mut Trace) -> io::Result<()> {
+    writeln!(writer, "draw,{},evaluations,slice_level", name)?;
+    let chunk_size = 4096;
+    let mut start = 0;
+    while start < trace.len() {
+        for (t, record) in trace
+            .read_range(start, start + chunk_size)?
+            .iter()
+            .enumerate()
+        {
+            write!(
+                writer,
+                "{},{},{},",
+                start + t,
+                record.draw,
+                record.evaluations
+            )?;
+            if let Some(slice_level) = record.slice_level {
+                write!(writer, "{}", slice_level)?;
+            }
+            writeln!(writer)?;
+        }
+        start += chunk_size;
+    }
+    writer.flush()
+}
+
+// Writes a variable with dimensions (chain, draw) of an xarray dataset stored as a Zarr (v2)
+// group, as a single uncompressed little-endian chunk.
+fn write_zarr_array(
+    group: &Path,
+    name: &str,
+    dtype: &str,
+    shape: [usize; 2],
+    bytes: &[u8],
+) -> io::Result<()> {
+    write_zarr_array_with_dimensions(group, name, dtype, &shape, &["chain", "draw"], bytes)
+}
+
+fn write_zarr_array_with_dimensions(
+    group: &Path,
+    name: &str,
+    dtype: &str,
+    shape: &[usize],
+    dimensions: &[&str],
+    bytes: &[u8],
+) -> io::Result<()> {
+    let directory = group.join(name);
+    fs::create_dir_all(&directory)?;
+    let shape = shape
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    fs::write(
+        directory.join(".zarray"),
+        format!(
+            "{{\"chunks\": [{shape}], \"compressor\": null, \"dtype\": \"{dtype}\", \"fill_value\": null, \"filters\": null, \"order\": \"C\", \"shape\": [{shape}], \"zarr_format\": 2}}"
+        ),
+    )?;
+    let dimensions = dimensions
+        .iter()
+        .map(|d| format!("\"{}\"", d))
+        .collect::<Vec<_>>()
+        .join(", ");
+    fs::write(
+        directory.join(".zattrs"),
+        format!("{{\"_ARRAY_DIMENSIONS\": [{}]}}", dimensions),
+    )?;
+    let chunk = vec!["0"; shape.split(", ").count()].join(".");
+    fs::write(directory.join(chunk), bytes)
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn write_zarr_group(group: &Path, n_chains: usize, n_draws: usize) -> io::Result<()> {
+    fs::create_dir_all(group)?;
+    fs::write(group.join(".zgroup"), "{\"zarr_format\": 2}")?;
+    fs::write(group.join(".zattrs"), "{}")?;
+    for (name, n) in [("chain", n_chains), ("draw", n_draws)] {
+        let bytes: Vec<u8> = (0..n as i64).flat_map(|i| i.to_le_bytes()).collect();
+        write_zarr_array_with_dimensions(group, name, "<i8", &[n], &[name], &bytes)?;
+    }
+    Ok(())
+}
+
+// Writes draws in the ArviZ InferenceData layout as a Zarr (v2) store readable by
+// arviz.from_zarr: a posterior group with one (chain, draw) variable per parameter, where
+// chains[c][t][p] is parameter p of draw t in chain c (chains of equal length), and, if given, a
+// sample_stats group with the number of target evaluations per draw as n_evaluations. Fails with
+// io::ErrorKind::InvalidInput, before writing anything, if the chains (or the evaluations) are
+// not all of the same length or a draw does not have one value per parameter name.
+pub fn write_inference_data_zarr<P: AsRef<Path>>(
+    path: P,
+    parameter_names: &[&str],
+    chains: &[Vec<Vec<f64>>],
+    evaluations: Option<&[Vec<u64>]>,
+) -> io::Result<()> {
+    let root = path.as_ref();
+    let n_chains = chains.len();
+    let n_draws = chains.first().map_or(0, |chain| chain.len());
+    if chains.iter().any(|chain| chain.len() != n_draws) {
+        return Err(invalid_input(
+            "chains must all have the same number of draws",
+        ));
+    }
+    if chains
+        .iter()
+        .flatten()
+        .any(|draw| draw.len() != parameter_names.len())
+    {
+        return Err(invalid_input(
+            "each draw must have one value per parameter name",
+        ));
+    }
+    if let Some(evaluations) = evaluations {
+        if evaluations.len() != n_chains || evaluations.iter().any(|chain| chain.len() != n_draws) {
+            return Err(invalid_input(
+                "evaluations must have the same shape as the chains",
+            ));
+        }
+    }
+    fs::create_dir_all(root)?;
+    fs::write(root.join(".zgroup"), "{\"zarr_format\": 2}")?;
+    fs::write(root.join(".zattrs"), "{}")?;
+    let posterior = root.join("posterior");
+    write_zarr_group(&posterior, n_chains, n_draws)?;
+    for (p, name) in parameter_names.iter().enumerate() {
+        let bytes: Vec<u8> = chains
+            .iter()
+            .flat_map(|chain| chain.iter().flat_map(|draw| draw[p].to_le_bytes()))
+            .collect();
+        write_zarr_array(&posterior, name, "<f8", [n_chains, n_draws], &bytes)?;
+    }
+    if let Some(evaluations) = evaluations {
+        let sample_stats = root.join("sample_stats");
+        write_zarr_group(&sample_stats, n_chains, n_draws)?;
+        let bytes: Vec<u8> = evaluations
+            .iter()
+            .flat_map(|chain| chain.iter().flat_map(|&n| (n as i64).to_le_bytes()))
+            .collect();
+        write_zarr_array(
+            &sample_stats,
+            "n_evaluations",
+            "<i8",
+            [n_chains, n_draws],
+            &bytes,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::UpdateMetadata;
+    use crate::trace::TraceRecord;
+
+    #[test]
+    fn test_csv_layouts() {
+        let mut out = Vec::new();
+        let chains = vec![vec![vec![1.0, 2.5]], vec![vec![-1.0, 0.5], vec![3.0, 4.0]]];
+        write_draws_csv(&mut out, &["mu", "sigma"], &chains).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chain,draw,mu,sigma\n0,0,1,2.5\n1,0,-1,0.5\n1,1,3,4\n"
+        );
+        let mut out = Vec::new();
+        let mut chain = Chain {
+            draws: vec![0.25, 0.5],
+            evaluations: vec![4, 6],
+            ..Default::default()
+        };
+        write_chains_csv(&mut out, "x", &[chain.clone()]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chain,draw,x,evaluations\n0,0,0.25,4\n0,1,0.5,6\n"
+        );
+        chain.metadata = vec![
+            UpdateMetadata {
+                evaluations: 4,
+                width: 1.5,
+                slice_level: -0.5,
+            },
+            UpdateMetadata {
+                evaluations: 6,
+                width: f64::NAN,
+                slice_level: f64::NAN,
+            },
+        ];
+        let mut out = Vec::new();
+        write_chains_with_metadata_csv(&mut out, "x", &[chain]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chain,draw,x,evaluations,width,slice_level\n0,0,0.25,4,1.5,-0.5\n0,1,0.5,6,,\n"
+        );
+        let mut trace = Trace::new();
+        for (draw, slice_level) in [(1.5, Some(-2.0)), (2.5, None)] {
+            trace
+                .push(TraceRecord {
+                    draw,
+                    evaluations: 3,
+                    slice_level,
+                })
+                .unwrap();
+        }
+        let mut out = Vec::new();
+        write_trace_csv(&mut out, "x", &mut trace).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "draw,x,evaluations,slice_level\n0,1.5,3,-2\n1,2.5,3,\n"
+        );
+    }
+
+    #[test]
+    fn test_inference_data_zarr_layout() {
+        let root =
+            std::env::temp_dir().join(format!("slice_sampler_idata_{}.zarr", std::process::id()));
+        let chains = vec![vec![vec![1.0, 2.0]; 3], vec![vec![3.0, 4.0]; 3]];
+        let evaluations = vec![vec![5; 3], vec![6; 3]];
+        write_inference_data_zarr(&root, &["mu", "sigma"], &chains, Some(&evaluations)).unwrap();
+        let zarray = std::fs::read_to_string(root.join("posterior/mu/.zarray")).unwrap();
+        assert!(zarray.contains("\"shape\": [2, 3]"));
+        let zattrs = std::fs::read_to_string(root.join("posterior/mu/.zattrs")).unwrap();
+        assert_eq!(zattrs, "{\"_ARRAY_DIMENSIONS\": [\"chain\", \"draw\"]}");
+        let bytes = std::fs::read(root.join("posterior/sigma/0.0")).unwrap();
+        assert_eq!(bytes.len(), 6 * 8);
+        assert_eq!(f64::from_le_bytes(bytes[24..32].try_into().unwrap()), 4.0);
+        let bytes = std::fs::read(root.join("sample_stats/n_evaluations/0.0")).unwrap();
+        assert_eq!(i64::from_le_bytes(bytes[40..48].try_into().unwrap()), 6);
+        assert_eq!(
+            std::fs::read(root.join("posterior/draw/0")).unwrap().len(),
+            3 * 8
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_inference_data_zarr_invalid_input() {
+        let root = std::env::temp_dir().join(format!(
+            "slice_sampler_invalid_idata_{}.zarr",
+            std::process::id()
+        ));
+        let unequal_chains = vec![vec![vec![1.0]; 3], vec![vec![2.0]; 2]];
+        let short_draw = vec![vec![vec![1.0, 2.0], vec![3.0]]];
+        let chains = vec![vec![vec![1.0]; 3]];
+        let evaluations = vec![vec![5; 2]];
+        for result in [
+            write_inference_data_zarr(&root, &["mu"], &unequal_chains, None),
+            write_inference_data_zarr(&root, &["mu", "sigma"], &short_draw, None),
+            write_inference_data_zarr(&root, &["mu"], &chains, Some(&evaluations)),
+        ] {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,333 @@
+// C interface to the univariate samplers. Targets are function pointers taking the point and an
+// opaque context pointer, tuning parameters are plain structs, and the random number generator
+// is a 64-bit state owned by the caller and advanced by every call. Functions return
+// SLICE_SUCCESS or a negative error code, writing the new state and the number of target
+// evaluations through the output pointers. The slice_sampler_capi crate (capi/) builds these
+// functions as shared and static libraries, declared by capi/include/slice_sampler.h.
+use crate::error::{SliceError, TuningError};
+use crate::univariate::doubling::{self, try_univariate_slice_sampler_doubling_and_shrinkage};
+use crate::univariate::shrinkage::try_univariate_slice_sampler_shrinkage;
+use crate::univariate::stepping_out::{
+    self, try_univariate_slice_sampler_stepping_out_and_shrinkage,
+};
+use crate::univariate::StepLimit;
+use core::ffi::c_void;
+
+pub type SliceTarget = Option<unsafe extern "C" fn(x: f64, context: *mut c_void) -> f64>;
+
+pub const SLICE_SUCCESS: i32 = 0;
+pub const SLICE_ERROR_NULL_POINTER: i32 = -1;
+pub const SLICE_ERROR_INVALID_WIDTH: i32 = -2;
+pub const SLICE_ERROR_INVALID_STEP_LIMIT: i32 = -3;
+pub const SLICE_ERROR_INVALID_MAX_EVALUATIONS: i32 = -4;
+pub const SLICE_ERROR_INVALID_BOUNDS: i32 = -5;
+pub const SLICE_ERROR_NON_FINITE_START: i32 = -6;
+pub const SLICE_ERROR_ZERO_DENSITY_AT_START: i32 = -7;
+pub const SLICE_ERROR_NAN_DENSITY: i32 = -8;
+pub const SLICE_ERROR_EVALUATION_BUDGET_EXHAUSTED: i32 = -9;
+pub const SLICE_ERROR_INVALID_FACTOR: i32 = -10;
+pub const SLICE_ERROR_INVALID_TEMPERATURE: i32 = -11;
+pub const SLICE_ERROR_INVALID_SCHEDULE: i32 = -12;
+pub const SLICE_ERROR_INVALID_MIN_WIDTH: i32 = -13;
+pub const SLICE_ERROR_DEGENERATE_INTERVAL: i32 = -14;
+pub const SLICE_ERROR_INFINITE_DENSITY_AT_START: i32 = -15;
+pub const SLICE_ERROR_INVALID_TARGET_EVALUATIONS: i32 = -16;
+pub const SLICE_ERROR_INVALID_LEARNING_RATE: i32 = -17;
+pub const SLICE_ERROR_INVALID_DECAY: i32 = -18;
+pub const SLICE_ERROR_INVALID_SHAPE: i32 = -19;
+pub const SLICE_ERROR_INVALID_STEP_SIZE: i32 = -20;
+
+pub const SLICE_STEP_LIMIT_UNLIMITED: u32 = 0;
+pub const SLICE_STEP_LIMIT_NONE: u32 = 1;
+pub const SLICE_STEP_LIMIT_MAX: u32 = 2;
+
+// Tuning parameters, where step_limit is one of the SLICE_STEP_LIMIT constants (max_steps is
+// the limit for SLICE_STEP_LIMIT_MAX), max_evaluations is zero for no evaluation budget, and
+// min_width is zero for no minimum interval width.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SliceTuningParameters {
+    pub width: f64,
+    pub step_limit: u32,
+    pub max_steps: u32,
+    pub max_evaluations: u64,
+    pub min_width: f64,
+}
+
+fn step_limit(tuning_parameters: &SliceTuningParameters) -> Result<StepLimit, i32> {
+    match tuning_parameters.step_limit {
+        SLICE_STEP_LIMIT_UNLIMITED => Ok(StepLimit::Unlimited),
+        SLICE_STEP_LIMIT_NONE => Ok(StepLimit::None),
+        SLICE_STEP_LIMIT_MAX => Ok(StepLimit::Max(tuning_parameters.max_steps)),
+        _ => Err(SLICE_ERROR_INVALID_STEP_LIMIT),
+    }
+}
+
+fn tuning_error_code(error: TuningError) -> i32 {
+    match error {
+        TuningError::InvalidWidth(_) => SLICE_ERROR_INVALID_WIDTH,
+        TuningError::InvalidStepLimit(_) => SLICE_ERROR_INVALID_STEP_LIMIT,
+        TuningError::InvalidMaxEvaluations(_) => SLICE_ERROR_INVALID_MAX_EVALUATIONS,
+        TuningError::InvalidFactor(_) => SLICE_ERROR_INVALID_FACTOR,
+        TuningError::InvalidTemperature(_) => SLICE_ERROR_INVALID_TEMPERATURE,
+        TuningError::InvalidSchedule(_) => SLICE_ERROR_INVALID_SCHEDULE,
+        TuningError::InvalidMinWidth(_) => SLICE_ERROR_INVALID_MIN_WIDTH,
+        TuningError::InvalidBounds { .. } => SLICE_ERROR_INVALID_BOUNDS,
+        TuningError::InvalidTargetEvaluations(_) => SLICE_ERROR_INVALID_TARGET_EVALUATIONS,
+        TuningError::InvalidLearningRate(_) => SLICE_ERROR_INVALID_LEARNING_RATE,
+        TuningError::InvalidDecay(_) => SLICE_ERROR_INVALID_DECAY,
+        TuningError::InvalidShape(_) => SLICE_ERROR_INVALID_SHAPE,
+        TuningError::InvalidStepSize(_) => SLICE_ERROR_INVALID_STEP_SIZE,
+    }
+}
+
+fn slice_error_code(error: SliceError) -> i32 {
+    match error {
+        SliceError::InvalidBounds { .. } => SLICE_ERROR_INVALID_BOUNDS,
+        SliceError::NonFiniteStart(_) => SLICE_ERROR_NON_FINITE_START,
+        SliceError::ZeroDensityAtStart(_) => SLICE_ERROR_ZERO_DENSITY_AT_START,
+        SliceError::NanDensity(_) => SLICE_ERROR_NAN_DENSITY,
+        SliceError::EvaluationBudgetExhausted(_) => SLICE_ERROR_EVALUATION_BUDGET_EXHAUSTED,
+        SliceError::DegenerateInterval(_) => SLICE_ERROR_DEGENERATE_INTERVAL,
+        SliceError::InfiniteDensityAtStart(_) => SLICE_ERROR_INFINITE_DENSITY_AT_START,
+        SliceError::InvalidTuning(error) => tuning_error_code(error),
+    }
+}
+
+// Runs a fallible sampler with a generator restored from, and saved back to, *rng_state.
+unsafe fn call<D>(rng_state: *mut u64, out_x: *mut f64, out_evaluations: *mut u64, draw: D) -> i32
+where
+    D: FnOnce(&mut Option<fastrand::Rng>) -> Result<(f64, u64), i32>,
+{
+    if rng_state.is_null() || out_x.is_null() {
+        return SLICE_ERROR_NULL_POINTER;
+    }
+    let mut rng = Some(fastrand::Rng::with_seed(*rng_state));
+    let result = draw(&mut rng);
+    if let Some(rng) = rng {
+        *rng_state = rng.get_seed();
+    }
+    match result {
+        Ok((x, evaluations)) => {
+            *out_x = x;
+            if !out_evaluations.is_null() {
+                *out_evaluations = evaluations;
+            }
+            SLICE_SUCCESS
+        }
+        Err(code) => code,
+    }
+}
+
+/// Stepping out and shrinkage update of x for the target f with the given context.
+///
+/// # Safety
+///
+/// `f` must be safe to call with `context`, `tuning_parameters` must point to a valid
+/// `SliceTuningParameters`, `rng_state` and `out_x` must be valid for reads and writes, and
+/// `out_evaluations` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn slice_sampler_stepping_out(
+    x: f64,
+    f: SliceTarget,
+    context: *mut c_void,
+    on_log_scale: bool,
+    tuning_parameters: *const SliceTuningParameters,
+    rng_state: *mut u64,
+    out_x: *mut f64,
+    out_evaluations: *mut u64,
+) -> i32 {
+    let (Some(f), Some(tp)) = (f, tuning_parameters.as_ref()) else {
+        return SLICE_ERROR_NULL_POINTER;
+    };
+    call(rng_state, out_x, out_evaluations, |rng| {
+        let mut builder = stepping_out::TuningParameters::builder()
+            .width(tp.width)
+            .max_number_of_steps(step_limit(tp)?);
+        if tp.max_evaluations > 0 {
+            builder = builder.max_evaluations(tp.max_evaluations);
+        }
+        if tp.min_width != 0.0 {
+            builder = builder.min_width(tp.min_width);
+        }
+        let tuning_parameters = builder.build().map_err(tuning_error_code)?;
+        try_univariate_slice_sampler_stepping_out_and_shrinkage(
+            x,
+            |x| f(x, context),
+            on_log_scale,
+            &tuning_parameters,
+            rng,
+        )
+        .map_err(slice_error_code)
+    })
+}
+
+/// Doubling and shrinkage update of x for the target f with the given context.
+///
+/// # Safety
+///
+/// As for `slice_sampler_stepping_out`.
+#[no_mangle]
+pub unsafe extern "C" fn slice_sampler_doubling(
+    x: f64,
+    f: SliceTarget,
+    context: *mut c_void,
+    on_log_scale: bool,
+    tuning_parameters: *const SliceTuningParameters,
+    rng_state: *mut u64,
+    out_x: *mut f64,
+    out_evaluations: *mut u64,
+) -> i32 {
+    let (Some(f), Some(tp)) = (f, tuning_parameters.as_ref()) else {
+        return SLICE_ERROR_NULL_POINTER;
+    };
+    call(rng_state, out_x, out_evaluations, |rng| {
+        let mut builder = doubling::TuningParameters::builder()
+            .width(tp.width)
+            .max_number_of_doubles(step_limit(tp)?);
+        if tp.max_evaluations > 0 {
+            builder = builder.max_evaluations(tp.max_evaluations);
+        }
+        if tp.min_width != 0.0 {
+            builder = builder.min_width(tp.min_width);
+        }
+        let tuning_parameters = builder.build().map_err(tuning_error_code)?;
+        try_univariate_slice_sampler_doubling_and_shrinkage(
+            x,
+            |x| f(x, context),
+            on_log_scale,
+            &tuning_parameters,
+            rng,
+        )
+        .map_err(slice_error_code)
+    })
+}
+
+/// Shrinkage update of x within [left, right] for the target f with the given context.
+///
+/// # Safety
+///
+/// `f` must be safe to call with `context`, `rng_state` and `out_x` must be valid for reads and
+/// writes, and `out_evaluations` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn slice_sampler_shrinkage(
+    x: f64,
+    f: SliceTarget,
+    context: *mut c_void,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng_state: *mut u64,
+    out_x: *mut f64,
+    out_evaluations: *mut u64,
+) -> i32 {
+    let Some(f) = f else {
+        return SLICE_ERROR_NULL_POINTER;
+    };
+    call(rng_state, out_x, out_evaluations, |rng| {
+        try_univariate_slice_sampler_shrinkage(x, |x| f(x, context), on_log_scale, left, right, rng)
+            .map_err(slice_error_code)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn normal(x: f64, context: *mut c_void) -> f64 {
+        let mean = *(context as *const f64);
+        -0.5 * (x - mean) * (x - mean)
+    }
+
+    #[test]
+    fn test_stepping_out_through_c_interface() {
+        let tuning_parameters = SliceTuningParameters {
+            width: 1.0,
+            step_limit: SLICE_STEP_LIMIT_UNLIMITED,
+            max_steps: 0,
+            max_evaluations: 0,
+            min_width: 0.0,
+        };
+        let mut mean = 3.0;
+        let context = &mut mean as *mut f64 as *mut c_void;
+        let mut rng_state = 43;
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let n_samples = 10_000;
+        for _ in 0..n_samples {
+            let mut evaluations = 0;
+            let status = unsafe {
+                slice_sampler_stepping_out(
+                    x,
+                    Some(normal),
+                    context,
+                    true,
+                    &tuning_parameters,
+                    &mut rng_state,
+                    &mut x,
+                    &mut evaluations,
+                )
+            };
+            assert_eq!(status, SLICE_SUCCESS);
+            assert!(evaluations > 0);
+            sum += x;
+        }
+        assert!((sum / (n_samples as f64) - 3.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let tuning_parameters = SliceTuningParameters {
+            width: -1.0,
+            step_limit: SLICE_STEP_LIMIT_UNLIMITED,
+            max_steps: 0,
+            max_evaluations: 0,
+            min_width: 0.0,
+        };
+        let mut mean = 0.0;
+        let context = &mut mean as *mut f64 as *mut c_void;
+        let mut rng_state = 1;
+        let mut x = 0.0;
+        let status = unsafe {
+            slice_sampler_doubling(
+                0.0,
+                Some(normal),
+                context,
+                true,
+                &tuning_parameters,
+                &mut rng_state,
+                &mut x,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, SLICE_ERROR_INVALID_WIDTH);
+        let status = unsafe {
+            slice_sampler_shrinkage(
+                2.0,
+                Some(normal),
+                context,
+                true,
+                0.0,
+                1.0,
+                &mut rng_state,
+                &mut x,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, SLICE_ERROR_INVALID_BOUNDS);
+        let status = unsafe {
+            slice_sampler_shrinkage(
+                0.5,
+                None,
+                context,
+                true,
+                0.0,
+                1.0,
+                &mut rng_state,
+                &mut x,
+                core::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, SLICE_ERROR_NULL_POINTER);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::error::{check_start, check_width, SliceError};
+use crate::target::UnivariateTarget;
+use crate::tempering::Tempered;
+use crate::univariate::variant::{sample, SliceVariant};
+
+// Number of steps of width w on each side tried by find_reasonable_start when the target has
+// zero density at the initial point.
+const MAX_SEARCH_STEPS: u32 = 1_000;
+
+// Schedule of lambda values for the greedy stages of find_reasonable_start, and the number of
+// updates at each stage.
+const GREEDY_SCHEDULE: [f64; 5] = [1.0, 2.0, 4.0, 8.0, 16.0];
+const UPDATES_PER_STAGE: usize = 2;
+
+// Starting points for a univariate target: the range [left, right] is split into n_chains
+// equal strata and one point is drawn uniformly within each stratum.
+pub fn stratified_starts(
+    n_chains: usize,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> Vec<f64> {
+    latin_hypercube_starts(n_chains, &[(left, right)], rng)
+        .into_iter()
+        .map(|x| x[0])
+        .collect()
+}
+
+// Latin hypercube starting points for multiple chains: each range is split into n_chains equal
+// strata and, in every dimension, every stratum is used by exactly one chain.
+pub fn latin_hypercube_starts(
+    n_chains: usize,
+    ranges: &[(f64, f64)],
+    rng: &mut Option<fastrand::Rng>,
+) -> Vec<Vec<f64>> {
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = fastrand::Rng::new();
+            &mut maybe
+        }
+    };
+    let mut starts = vec![Vec::with_capacity(ranges.len()); n_chains];
+    let mut strata: Vec<usize> = (0..n_chains).collect();
+    for &(left, right) in ranges {
+        rng.shuffle(&mut strata);
+        let h = (right - left) / (n_chains as f64);
+        for (start, &stratum) in starts.iter_mut().zip(strata.iter()) {
+            start.push(left + ((stratum as f64) + rng.f64()) * h);
+        }
+    }
+    starts
+}
+
+// Initialization search for a starting point with positive, finite density: if the target has
+// zero (or infinite) density at x, the points x + w, x - w, x + 2 w, x - 2 w, ... are tried in
+// turn, up to max_steps on each side. The samplers keep a starting point with zero or infinite
+// density, whose slice is the whole support or empty rather than a region around x. The width w
+// must be finite and positive.
+pub fn search_positive_density<S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    max_steps: u32,
+) -> Result<f64, SliceError> {
+    let x = check_start(x)?;
+    let w = check_width(w)?;
+    let mut positive = |x: f64| {
+        let fx = f(x);
+        if on_log_scale {
+            fx.is_finite()
+        } else {
+            fx > 0.0 && fx < f64::INFINITY
+        }
+    };
+    if positive(x) {
+        return Ok(x);
+    }
+    for k in 1..=max_steps {
+        for candidate in [x + (k as f64) * w, x - (k as f64) * w] {
+            if positive(candidate) {
+                return Ok(candidate);
+            }
+        }
+    }
+    Err(SliceError::ZeroDensityAtStart(x))
+}
+
+// Starting point from find_reasonable_start, with the log density of the target there and the
+// number of target evaluations used to find it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReasonableStart {
+    pub x: f64,
+    pub log_density: f64,
+    pub evaluations: u64,
+}
+
+fn width(variant: &SliceVariant) -> f64 {
+    match variant {
+        SliceVariant::SteppingOut(tuning_parameters) => tuning_parameters.width(),
+        SliceVariant::Doubling(tuning_parameters) => tuning_parameters.width(),
+        // Shrinkage steps out in unit steps on a side whose bound is infinite
+        SliceVariant::FixedInterval { left, right } if !(right - left).is_finite() => 1.0,
+        SliceVariant::FixedInterval { left, right } => right - left,
+    }
+}
+
+// Moves an arbitrary starting point x0 into a region of non-negligible density before regular
+// sampling begins. If the target has zero density at x0, search_positive_density first finds a
+// point with positive density using steps of the variant's width. Then a few updates of the
+// slice variant are applied to the target sharpened by increasing powers (GREEDY_SCHEDULE),
+// which move the point greedily towards a mode, with the expansion of the variant covering a
+// distant start in few evaluations (especially with doubling).
+pub fn find_reasonable_start<T: UnivariateTarget + ?Sized>(
+    target: &mut T,
+    x0: f64,
+    variant: &SliceVariant,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<ReasonableStart, SliceError> {
+    let on_log_scale = target.on_log_scale();
+    let mut evaluations = 0;
+    let mut x = search_positive_density(
+        x0,
+        |x| {
+            evaluations += 1;
+            target.evaluate(x)
+        },
+        on_log_scale,
+        width(variant),
+        MAX_SEARCH_STEPS,
+    )?;
+    for lambda in GREEDY_SCHEDULE {
+        let mut tempered = Tempered::new(&mut *target, lambda);
+        for _ in 0..UPDATES_PER_STAGE {
+            let evaluation_counter;
+            (x, evaluation_counter) = sample(variant, x, &mut tempered, rng);
+            evaluations += evaluation_counter;
+        }
+    }
+    let log_density = Tempered::new(target, 1.0).log_density(x);
+    Ok(ReasonableStart {
+        x,
+        log_density,
+        evaluations: evaluations + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TuningError;
+    use crate::targets::{Gamma, Normal};
+    use crate::univariate::doubling;
+
+    #[test]
+    fn test_latin_hypercube_strata() {
+        let n_chains = 8;
+        let ranges = [(0.0, 1.0), (-4.0, 4.0), (10.0, 12.0)];
+        let starts =
+            latin_hypercube_starts(n_chains, &ranges, &mut Some(fastrand::Rng::with_seed(1)));
+        assert_eq!(starts.len(), n_chains);
+        for (j, &(left, right)) in ranges.iter().enumerate() {
+            let h = (right - left) / (n_chains as f64);
+            let mut used = vec![false; n_chains];
+            for start in &starts {
+                assert!(left <= start[j] && start[j] < right);
+                let stratum = ((start[j] - left) / h).floor() as usize;
+                assert!(!used[stratum]);
+                used[stratum] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_starts() {
+        let starts = stratified_starts(4, 0.0, 4.0, &mut None);
+        let mut strata: Vec<usize> = starts.iter().map(|x| x.floor() as usize).collect();
+        strata.sort_unstable();
+        assert_eq!(strata, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_search_positive_density() {
+        let f = |x: f64| {
+            if (3.2..4.0).contains(&x) {
+                0.0
+            } else {
+                f64::NEG_INFINITY
+            }
+        };
+        assert_eq!(search_positive_density(0.0, f, true, 0.5, 10), Ok(3.5));
+        assert_eq!(search_positive_density(3.5, f, true, 0.5, 10), Ok(3.5));
+        assert_eq!(
+            search_positive_density(0.0, f, true, 0.5, 5),
+            Err(SliceError::ZeroDensityAtStart(0.0))
+        );
+        let density = |x: f64| if x < -1.0 { 1.0 } else { 0.0 };
+        assert_eq!(
+            search_positive_density(0.0, density, false, 1.0, 3),
+            Ok(-2.0)
+        );
+        let pole = |x: f64| 1.0 / x.abs();
+        assert_eq!(search_positive_density(0.0, pole, false, 1.0, 3), Ok(1.0));
+        for w in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                search_positive_density(0.0, f, true, w, 10),
+                Err(SliceError::InvalidTuning(TuningError::InvalidWidth(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_find_reasonable_start() {
+        let mut target = Normal {
+            mean: 1_000.0,
+            standard_deviation: 1.0,
+        };
+        let variant = SliceVariant::Doubling(doubling::TuningParameters::new());
+        let mut rng = Some(fastrand::Rng::with_seed(149));
+        let start = find_reasonable_start(&mut target, 0.0, &variant, &mut rng).unwrap();
+        assert!((start.x - 1_000.0).abs() < 2.0);
+        assert_eq!(start.log_density, target.evaluate(start.x));
+        assert!(start.evaluations < 500);
+        // A start with zero density is first moved into the support
+        let mut positive = Gamma {
+            shape: 2.0,
+            rate: 1.0,
+        };
+        let start = find_reasonable_start(&mut positive, -3.5, &variant, &mut rng).unwrap();
+        assert!(start.x > 0.0 && start.log_density.is_finite());
+    }
+}
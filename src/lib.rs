@@ -1 +1,47 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod adaptation;
+#[cfg(feature = "std")]
+pub mod ais;
+#[cfg(feature = "std")]
+pub mod benchmarks;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod efficiency;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod export;
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod initialization;
+mod math;
+#[cfg(feature = "std")]
+pub mod multivariate;
+#[cfg(feature = "plots")]
+pub mod plots;
+#[cfg(feature = "std")]
+pub mod sampler;
+#[cfg(feature = "std")]
+pub mod sensitivity;
+#[cfg(feature = "std")]
+pub mod smc;
+#[cfg(feature = "std")]
+pub mod summary;
+pub mod target;
+#[cfg(feature = "std")]
+pub mod targets;
+#[cfg(feature = "std")]
+pub mod tempering;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod transforms;
 pub mod univariate;
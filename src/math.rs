@@ -0,0 +1,78 @@
+// Natural logarithm, which is f64::ln with std and otherwise computed from the exponent and an
+// atanh series for the mantissa (accurate to about one unit in the last place).
+#[cfg(feature = "std")]
+pub(crate) fn ln(x: f64) -> f64 {
+    x.ln()
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn ln(x: f64) -> f64 {
+    ln_series(x)
+}
+
+#[cfg_attr(feature = "std", allow(dead_code))]
+fn ln_series(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x == f64::INFINITY {
+        return x;
+    }
+    let (x, offset) = if x < f64::MIN_POSITIVE {
+        (x * 18014398509481984.0, -54)
+    } else {
+        (x, 0)
+    };
+    let bits = x.to_bits();
+    let mut exponent = ((bits >> 52) & 0x7ff) as i64 - 1023 + offset;
+    let mut mantissa = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | 0x3ff0_0000_0000_0000);
+    if mantissa > core::f64::consts::SQRT_2 {
+        mantissa /= 2.0;
+        exponent += 1;
+    }
+    let z = (mantissa - 1.0) / (mantissa + 1.0);
+    let z2 = z * z;
+    let mut term = z;
+    let mut sum = 0.0;
+    let mut k = 1.0;
+    while term.abs() > 1e-18 {
+        sum += term / k;
+        term *= z2;
+        k += 2.0;
+    }
+    2.0 * sum + (exponent as f64) * core::f64::consts::LN_2
+}
+
+// Generator used when the caller does not supply one: seeded from entropy with std and from a
+// fixed seed otherwise, so without std callers should pass their own seeded generator.
+#[cfg(feature = "std")]
+pub(crate) fn default_rng() -> fastrand::Rng {
+    fastrand::Rng::new()
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn default_rng() -> fastrand::Rng {
+    fastrand::Rng::with_seed(0x2545_f491_4f6c_dd1d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ln_series() {
+        let mut rng = fastrand::Rng::with_seed(41);
+        for _ in 0..10_000 {
+            let x = rng.f64();
+            assert!((ln_series(x) - x.ln()).abs() <= 4.0 * f64::EPSILON * x.ln().abs().max(1.0));
+        }
+        for x in [1e-310, 1e-300, 0.5, 1.0, 2.0, 1e300] {
+            assert!((ln_series(x) - x.ln()).abs() <= 4.0 * f64::EPSILON * x.ln().abs().max(1.0));
+        }
+        assert_eq!(ln_series(0.0), f64::NEG_INFINITY);
+        assert!(ln_series(-1.0).is_nan());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error::{check_width, TuningError};
+use crate::math::default_rng;
+use crate::target::BivariateTarget;
+
+// Slice sampler for a target over pairs using Neal's (2003, Section 5.1) rectangle procedure
+// without allocation: a rectangle with the given widths is positioned uniformly at random around
+// the current point, and proposals are drawn uniformly from it, shrinking each side towards the
+// current point after each rejection until a proposal is in the slice. Unlike the univariate
+// case, stepping out the sides along the axes through the current point would not leave the
+// target invariant, so the widths should be on the scale of the slice (e.g., the marginal
+// standard deviations). Returns the new state and the number of target evaluations, or an error
+// if a width is not finite and positive.
+pub fn bivariate_slice_sampler<T: BivariateTarget + ?Sized>(
+    x: (f64, f64),
+    target: &mut T,
+    widths: (f64, f64),
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<((f64, f64), u64), TuningError> {
+    check_width(widths.0)?;
+    check_width(widths.1)?;
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let mut f = |x: (f64, f64)| {
+        let fx = target.evaluate(x);
+        if on_log_scale {
+            fx
+        } else {
+            fx.ln()
+        }
+    };
+    // Step 1 (slice)
+    let y = f(x) + rng.f64().ln();
+    let mut evaluations = 1;
+    // Step 2 (rectangle)
+    let l0 = x.0 - widths.0 * rng.f64();
+    let l1 = x.1 - widths.1 * rng.f64();
+    let (mut l, mut r) = ((l0, l1), (l0 + widths.0, l1 + widths.1));
+    // Step 3 (shrinkage)
+    loop {
+        let x1 = (l.0 + rng.f64() * (r.0 - l.0), l.1 + rng.f64() * (r.1 - l.1));
+        evaluations += 1;
+        if y < f(x1) {
+            return Ok((x1, evaluations));
+        }
+        // The rectangle has collapsed onto the current state (possible only if the target is
+        // not a deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.0.is_nan() || x1.1.is_nan() {
+            return Ok((x, evaluations));
+        }
+        if x1.0 < x.0 {
+            l.0 = x1.0;
+        } else {
+            r.0 = x1.0;
+        }
+        if x1.1 < x.1 {
+            l.1 = x1.1;
+        } else {
+            r.1 = x1.1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Normal location and log scale, with independent standard normal priors, for the
+    // observations.
+    struct LocationScale(Vec<f64>);
+
+    impl BivariateTarget for LocationScale {
+        fn evaluate(&mut self, (mu, log_sigma): (f64, f64)) -> f64 {
+            let sigma = log_sigma.exp();
+            let likelihood: f64 = self
+                .0
+                .iter()
+                .map(|y| -0.5 * ((y - mu) / sigma).powi(2) - log_sigma)
+                .sum();
+            likelihood - 0.5 * mu * mu - 0.5 * log_sigma * log_sigma
+        }
+    }
+
+    // Standard bivariate normal with correlation 0.8, on the natural scale.
+    struct Correlated;
+
+    impl BivariateTarget for Correlated {
+        fn evaluate(&mut self, (a, b): (f64, f64)) -> f64 {
+            (-(a * a - 1.6 * a * b + b * b) / (2.0 * 0.36)).exp()
+        }
+        fn on_log_scale(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_correlated_normal() {
+        let mut rng = Some(fastrand::Rng::with_seed(113));
+        let mut x = (0.0, 0.0);
+        let (mut sum_a, mut sum_ab, mut sum_bb) = (0.0, 0.0, 0.0);
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (x, _) = bivariate_slice_sampler(x, &mut Correlated, (2.0, 2.0), &mut rng).unwrap();
+            sum_a += x.0;
+            sum_ab += x.0 * x.1;
+            sum_bb += x.1 * x.1;
+        }
+        let n = n_samples as f64;
+        assert!((sum_a / n).abs() < 0.05);
+        assert!((sum_ab / n - 0.8).abs() < 0.05);
+        assert!((sum_bb / n - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_location_scale() {
+        let mut target = LocationScale(vec![1.2, 0.8, 1.1, 0.9, 1.0]);
+        let mut rng = Some(fastrand::Rng::with_seed(127));
+        let mut x = (0.0, 0.0);
+        let (mut sum_mu, mut sum_log_sigma) = (0.0, 0.0);
+        let n_samples = 10_000;
+        for _ in 0..n_samples {
+            (x, _) = bivariate_slice_sampler(x, &mut target, (0.5, 0.5), &mut rng).unwrap();
+            sum_mu += x.0;
+            sum_log_sigma += x.1;
+        }
+        // The scale is small relative to the prior, so the location is close to the sample mean
+        assert!((sum_mu / (n_samples as f64) - 1.0).abs() < 0.05);
+        assert!(sum_log_sigma / (n_samples as f64) < -1.0);
+    }
+
+    #[test]
+    fn test_invalid_widths() {
+        for w in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                bivariate_slice_sampler((0.0, 0.0), &mut Correlated, (w, 1.0), &mut None),
+                Err(TuningError::InvalidWidth(w))
+            );
+        }
+    }
+}
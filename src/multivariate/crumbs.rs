@@ -0,0 +1,429 @@
+use crate::diagnostics::inverse_normal_cdf;
+use crate::math::default_rng;
+use crate::multivariate::elliptical::cholesky;
+use crate::target::{MultivariateTarget, MultivariateTargetWithGradient};
+
+// Scheme for Neal's (2003, Section 5.2) crumb framework: after the slice level is drawn at the
+// current state x, each attempt drops a crumb drawn from a distribution around x, then proposes
+// a point from the distribution proportional to the product of the densities of all crumbs so
+// far given the point, repeating until a proposal is in the slice. A scheme may adapt later
+// crumbs to earlier ones and to the rejected proposals (and the target there), which rejected
+// reports along with the gradient of the log density at the proposal when the sampler has it,
+// but not to the current state in any other way. begin starts a new update.
+pub trait CrumbScheme {
+    fn begin(&mut self, dimension: usize);
+    fn draw_crumb(&mut self, x: &[f64], crumb: &mut [f64], rng: &mut fastrand::Rng);
+    fn draw_proposal(&mut self, proposal: &mut [f64], rng: &mut fastrand::Rng);
+    fn rejected(&mut self, _proposal: &[f64], _log_density: f64, _gradient: Option<&[f64]>) {}
+}
+
+// Gaussian crumbs centred at the current state with independent coordinates, the first with
+// standard deviation scale and each later one shrink times the previous one, so that proposals
+// (normal, with the precision-weighted mean of the crumbs) concentrate around the current state
+// after rejections, much as shrinkage does for intervals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaussianCrumbs {
+    scale: f64,
+    shrink: f64,
+    standard_deviation: f64,
+    precision: f64,
+    weighted_sum: Vec<f64>,
+}
+
+impl GaussianCrumbs {
+    pub fn new(scale: f64, shrink: f64) -> Self {
+        Self {
+            scale,
+            shrink,
+            standard_deviation: scale,
+            precision: 0.0,
+            weighted_sum: Vec::new(),
+        }
+    }
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+    pub fn shrink(&self) -> f64 {
+        self.shrink
+    }
+}
+
+impl CrumbScheme for GaussianCrumbs {
+    fn begin(&mut self, dimension: usize) {
+        self.standard_deviation = self.scale;
+        self.precision = 0.0;
+        self.weighted_sum.clear();
+        self.weighted_sum.resize(dimension, 0.0);
+    }
+    fn draw_crumb(&mut self, x: &[f64], crumb: &mut [f64], rng: &mut fastrand::Rng) {
+        let sd = self.standard_deviation;
+        let precision = 1.0 / (sd * sd);
+        for ((ci, xi), si) in crumb.iter_mut().zip(x).zip(self.weighted_sum.iter_mut()) {
+            *ci = xi + sd * inverse_normal_cdf(rng.f64());
+            *si += precision * *ci;
+        }
+        self.precision += precision;
+        self.standard_deviation *= self.shrink;
+    }
+    fn draw_proposal(&mut self, proposal: &mut [f64], rng: &mut fastrand::Rng) {
+        let sd = 1.0 / self.precision.sqrt();
+        for (pi, si) in proposal.iter_mut().zip(&self.weighted_sum) {
+            *pi = si / self.precision + sd * inverse_normal_cdf(rng.f64());
+        }
+    }
+}
+
+// Gaussian crumbs whose covariance adapts within an update (Thompson & Neal, 2010): the first
+// crumb has covariance scale^2 I, and after each rejection the precision of later crumbs along
+// the gradient of the log density at the rejected proposal is multiplied by 1 / shrink^2, leaving
+// other directions unchanged. The crumbs thereby contract along the directions in which the slice
+// is narrow, which handles strong local correlation without a global preconditioner. Without a
+// gradient (crumb_slice_sampler), the direction from the proposal mean to the rejected proposal
+// is used instead, which is much less informative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveGaussianCrumbs {
+    scale: f64,
+    shrink: f64,
+    dimension: usize,
+    crumb_precision: Vec<f64>,
+    precision: Vec<f64>,
+    weighted_sum: Vec<f64>,
+    mean: Vec<f64>,
+    z: Vec<f64>,
+}
+
+impl AdaptiveGaussianCrumbs {
+    pub fn new(scale: f64, shrink: f64) -> Self {
+        Self {
+            scale,
+            shrink,
+            dimension: 0,
+            crumb_precision: Vec::new(),
+            precision: Vec::new(),
+            weighted_sum: Vec::new(),
+            mean: Vec::new(),
+            z: Vec::new(),
+        }
+    }
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+    pub fn shrink(&self) -> f64 {
+        self.shrink
+    }
+    // Precision matrix (row major) of the next crumb.
+    pub fn crumb_precision(&self) -> &[f64] {
+        &self.crumb_precision
+    }
+    // Draws z ~ N(0, (L L')^-1) by solving L' v = z for a standard normal z, in place.
+    fn draw_normal(&mut self, factor: &[f64], rng: &mut fastrand::Rng) {
+        let n = self.dimension;
+        for zi in self.z.iter_mut() {
+            *zi = inverse_normal_cdf(rng.f64());
+        }
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..n).map(|k| factor[k * n + i] * self.z[k]).sum();
+            self.z[i] = (self.z[i] - sum) / factor[i * n + i];
+        }
+    }
+}
+
+impl CrumbScheme for AdaptiveGaussianCrumbs {
+    fn begin(&mut self, dimension: usize) {
+        let n = dimension;
+        self.dimension = n;
+        self.crumb_precision.clear();
+        self.crumb_precision.resize(n * n, 0.0);
+        for i in 0..n {
+            self.crumb_precision[i * n + i] = 1.0 / (self.scale * self.scale);
+        }
+        self.precision.clear();
+        self.precision.resize(n * n, 0.0);
+        self.weighted_sum.clear();
+        self.weighted_sum.resize(n, 0.0);
+        self.mean.clear();
+        self.mean.resize(n, 0.0);
+        self.z.clear();
+        self.z.resize(n, 0.0);
+    }
+    fn draw_crumb(&mut self, x: &[f64], crumb: &mut [f64], rng: &mut fastrand::Rng) {
+        let n = self.dimension;
+        // Positive definite by construction, short of floating point breakdown
+        let Some(factor) = cholesky(&self.crumb_precision, n) else {
+            crumb.copy_from_slice(x);
+            return;
+        };
+        self.draw_normal(&factor, rng);
+        for ((ci, xi), zi) in crumb.iter_mut().zip(x).zip(&self.z) {
+            *ci = xi + zi;
+        }
+        for i in 0..n {
+            let row = &self.crumb_precision[i * n..(i + 1) * n];
+            self.weighted_sum[i] += row
+                .iter()
+                .zip(crumb.iter())
+                .map(|(a, c)| a * c)
+                .sum::<f64>();
+        }
+        for (p, a) in self.precision.iter_mut().zip(&self.crumb_precision) {
+            *p += a;
+        }
+    }
+    fn draw_proposal(&mut self, proposal: &mut [f64], rng: &mut fastrand::Rng) {
+        let n = self.dimension;
+        let Some(factor) = cholesky(&self.precision, n) else {
+            proposal.fill(f64::NAN);
+            return;
+        };
+        // Mean by solving L w = b and then L' m = w
+        for i in 0..n {
+            let sum: f64 = (0..i).map(|k| factor[i * n + k] * self.mean[k]).sum();
+            self.mean[i] = (self.weighted_sum[i] - sum) / factor[i * n + i];
+        }
+        for i in (0..n).rev() {
+            let sum: f64 = (i + 1..n).map(|k| factor[k * n + i] * self.mean[k]).sum();
+            self.mean[i] = (self.mean[i] - sum) / factor[i * n + i];
+        }
+        self.draw_normal(&factor, rng);
+        for ((pi, mi), zi) in proposal.iter_mut().zip(&self.mean).zip(&self.z) {
+            *pi = mi + zi;
+        }
+    }
+    fn rejected(&mut self, proposal: &[f64], _log_density: f64, gradient: Option<&[f64]>) {
+        let n = self.dimension;
+        match gradient {
+            Some(gradient) => self.z.copy_from_slice(gradient),
+            None => {
+                for ((ui, pi), mi) in self.z.iter_mut().zip(proposal).zip(&self.mean) {
+                    *ui = pi - mi;
+                }
+            }
+        }
+        let norm = self.z.iter().map(|ui| ui * ui).sum::<f64>().sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            return;
+        }
+        let mut along = 0.0;
+        for i in 0..n {
+            let row = &self.crumb_precision[i * n..(i + 1) * n];
+            along += self.z[i] * row.iter().zip(&self.z).map(|(a, u)| a * u).sum::<f64>();
+        }
+        let kappa = (1.0 / (self.shrink * self.shrink) - 1.0) * along / (norm * norm * norm * norm);
+        for i in 0..n {
+            for j in 0..n {
+                self.crumb_precision[i * n + j] += kappa * self.z[i] * self.z[j];
+            }
+        }
+    }
+}
+
+// Slice sampler using the crumb framework with the given scheme, updating x in place and
+// returning the number of target evaluations.
+pub fn crumb_slice_sampler<T, C>(
+    x: &mut [f64],
+    target: &mut T,
+    scheme: &mut C,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64
+where
+    T: MultivariateTarget + ?Sized,
+    C: CrumbScheme + ?Sized,
+{
+    crumb_slice_sampler_core(x, target, scheme, |_, _, _| false, rng)
+}
+
+// As crumb_slice_sampler, but also passing the scheme the gradient of the log density at each
+// rejected proposal (one gradient evaluation per rejection), as AdaptiveGaussianCrumbs uses.
+pub fn crumb_slice_sampler_with_gradient<T, C>(
+    x: &mut [f64],
+    target: &mut T,
+    scheme: &mut C,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64
+where
+    T: MultivariateTargetWithGradient + ?Sized,
+    C: CrumbScheme + ?Sized,
+{
+    let gradient = |target: &mut T, x: &[f64], gradient: &mut [f64]| {
+        target.gradient(x, gradient);
+        true
+    };
+    crumb_slice_sampler_core(x, target, scheme, gradient, rng)
+}
+
+fn crumb_slice_sampler_core<T, C, G>(
+    x: &mut [f64],
+    target: &mut T,
+    scheme: &mut C,
+    mut gradient: G,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64
+where
+    T: MultivariateTarget + ?Sized,
+    C: CrumbScheme + ?Sized,
+    G: FnMut(&mut T, &[f64], &mut [f64]) -> bool,
+{
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let f = |target: &mut T, x: &[f64]| {
+        let fx = target.evaluate(x);
+        if on_log_scale {
+            fx
+        } else {
+            fx.ln()
+        }
+    };
+    // Step 1 (slice)
+    let y = f(target, x) + rng.f64().ln();
+    let mut evaluations = 1;
+    // Step 2 (crumbs and proposals)
+    scheme.begin(x.len());
+    let mut crumb = vec![0.0; x.len()];
+    let mut proposal = vec![0.0; x.len()];
+    let mut slope = vec![0.0; x.len()];
+    loop {
+        scheme.draw_crumb(x, &mut crumb, rng);
+        scheme.draw_proposal(&mut proposal, rng);
+        let log_density = f(target, &proposal);
+        evaluations += 1;
+        if y < log_density {
+            x.copy_from_slice(&proposal);
+            return evaluations;
+        }
+        // The proposals have collapsed onto the current state (possible only if the target is
+        // not a deterministic function) or are no longer finite, so the current state is kept
+        if proposal == x || proposal.iter().any(|pi| pi.is_nan()) {
+            return evaluations;
+        }
+        if gradient(target, &proposal, &mut slope) {
+            scheme.rejected(&proposal, log_density, Some(&slope));
+        } else {
+            scheme.rejected(&proposal, log_density, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::{CorrelatedNormal, IllConditionedNormal};
+
+    #[test]
+    fn test_gaussian_crumbs() {
+        let mut target = IllConditionedNormal {
+            dimension: 3,
+            condition_number: 1.0,
+        };
+        let mut scheme = GaussianCrumbs::new(3.0, 0.7);
+        let mut rng = Some(fastrand::Rng::with_seed(227));
+        let mut x = vec![0.0; 3];
+        let mut sum = [0.0; 3];
+        let mut sum_of_squares = [0.0; 3];
+        let mut evaluations = 0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            evaluations += crumb_slice_sampler(&mut x, &mut target, &mut scheme, &mut rng);
+            for i in 0..3 {
+                sum[i] += x[i];
+                sum_of_squares[i] += x[i] * x[i];
+            }
+        }
+        let n = n_samples as f64;
+        for i in 0..3 {
+            assert!((sum[i] / n).abs() < 0.05);
+            assert!((sum_of_squares[i] / n - 1.0).abs() < 0.05);
+        }
+        assert!((evaluations as f64) / n < 10.0);
+    }
+
+    fn run<C: CrumbScheme>(scheme: &mut C, with_gradient: bool) -> (f64, f64) {
+        let mut target = CorrelatedNormal { correlation: 0.999 };
+        let mut rng = Some(fastrand::Rng::with_seed(233));
+        let mut x = vec![0.0; 2];
+        let mut sum_of_products = 0.0;
+        let mut sum_of_squared_jumps = 0.0;
+        let n_samples = 40_000;
+        for _ in 0..n_samples {
+            let previous = x[0];
+            if with_gradient {
+                crumb_slice_sampler_with_gradient(&mut x, &mut target, scheme, &mut rng);
+            } else {
+                crumb_slice_sampler(&mut x, &mut target, scheme, &mut rng);
+            }
+            sum_of_products += x[0] * x[1];
+            sum_of_squared_jumps += (x[0] - previous) * (x[0] - previous);
+        }
+        let n = n_samples as f64;
+        (sum_of_products / n, sum_of_squared_jumps / n)
+    }
+
+    #[test]
+    fn test_adaptive_gaussian_crumbs() {
+        // Mean squared jumps of a coordinate along the long axis of the slice
+        let (correlation, adaptive) = run(&mut AdaptiveGaussianCrumbs::new(2.0, 0.3), true);
+        let (_, isotropic) = run(&mut GaussianCrumbs::new(2.0, 0.3), false);
+        assert!((correlation - 0.999).abs() < 0.05);
+        assert!(adaptive > 10.0 * isotropic);
+    }
+
+    // Crumbs uniform on cubes centred at the current state, the side of each later cube halved
+    // whenever a proposal is rejected, so that proposals are uniform on the intersection of the
+    // cubes as functions of the state.
+    struct CubeCrumbs {
+        side: f64,
+        lower: Vec<f64>,
+        upper: Vec<f64>,
+    }
+
+    impl CrumbScheme for CubeCrumbs {
+        fn begin(&mut self, dimension: usize) {
+            self.side = 4.0;
+            self.lower = vec![f64::NEG_INFINITY; dimension];
+            self.upper = vec![f64::INFINITY; dimension];
+        }
+        fn draw_crumb(&mut self, x: &[f64], crumb: &mut [f64], rng: &mut fastrand::Rng) {
+            for (i, (ci, xi)) in crumb.iter_mut().zip(x).enumerate() {
+                *ci = xi + self.side * (rng.f64() - 0.5);
+                self.lower[i] = self.lower[i].max(*ci - 0.5 * self.side);
+                self.upper[i] = self.upper[i].min(*ci + 0.5 * self.side);
+            }
+        }
+        fn draw_proposal(&mut self, proposal: &mut [f64], rng: &mut fastrand::Rng) {
+            for (i, pi) in proposal.iter_mut().enumerate() {
+                *pi = self.lower[i] + rng.f64() * (self.upper[i] - self.lower[i]);
+            }
+        }
+        fn rejected(&mut self, _: &[f64], _: f64, _: Option<&[f64]>) {
+            self.side *= 0.5;
+        }
+    }
+
+    #[test]
+    fn test_custom_scheme() {
+        let mut target = IllConditionedNormal {
+            dimension: 2,
+            condition_number: 1.0,
+        };
+        let mut scheme = CubeCrumbs {
+            side: 0.0,
+            lower: Vec::new(),
+            upper: Vec::new(),
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(229));
+        let mut x = vec![0.0; 2];
+        let mut sum_of_squares = 0.0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            crumb_slice_sampler(&mut x, &mut target, &mut scheme, &mut rng);
+            sum_of_squares += x[1] * x[1];
+        }
+        assert!((sum_of_squares / (n_samples as f64) - 1.0).abs() < 0.05);
+    }
+}
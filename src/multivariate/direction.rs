@@ -0,0 +1,305 @@
+use crate::diagnostics::inverse_normal_cdf;
+use crate::math::default_rng;
+use crate::multivariate::elliptical::cholesky;
+use crate::target::MultivariateTarget;
+use crate::univariate::stepping_out::{
+    univariate_slice_sampler_stepping_out_and_shrinkage, TuningParameters,
+};
+
+// Linear map (row major, dimension by n_columns) taking standard normal vectors to the directions
+// of random_direction_slice_sampler. With the Cholesky factor of an estimate of the posterior
+// covariance, directions are drawn from N(0, covariance), so that the sampler moves along the
+// principal axes of the posterior at their scales rather than isotropically.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preconditioner {
+    dimension: usize,
+    n_columns: usize,
+    factor: Vec<f64>,
+}
+
+impl Preconditioner {
+    pub fn identity(dimension: usize) -> Self {
+        let mut factor = vec![0.0; dimension * dimension];
+        for i in 0..dimension {
+            factor[i * dimension + i] = 1.0;
+        }
+        Self {
+            dimension,
+            n_columns: dimension,
+            factor,
+        }
+    }
+    // From a lower-triangular factor (row major) with a positive diagonal, or None otherwise.
+    pub fn from_cholesky(dimension: usize, factor: Vec<f64>) -> Option<Self> {
+        if factor.len() != dimension * dimension {
+            return None;
+        }
+        for i in 0..dimension {
+            let diagonal = factor[i * dimension + i];
+            if diagonal <= 0.0
+                || diagonal.is_nan()
+                || factor[i * dimension + i + 1..(i + 1) * dimension]
+                    .iter()
+                    .any(|&value| value != 0.0)
+            {
+                return None;
+            }
+        }
+        Some(Self {
+            dimension,
+            n_columns: dimension,
+            factor,
+        })
+    }
+    // From a covariance matrix (row major), or None if it is not positive definite.
+    pub fn from_covariance(dimension: usize, covariance: &[f64]) -> Option<Self> {
+        if covariance.len() != dimension * dimension {
+            return None;
+        }
+        Self::from_cholesky(dimension, cholesky(covariance, dimension)?)
+    }
+    // Directions drawn as combinations of the given vectors with standard normal coefficients,
+    // or None if there are none or their lengths differ. Vectors that do not span the space
+    // confine the sampler to the affine subspace through its starting point.
+    pub fn from_basis(vectors: &[Vec<f64>]) -> Option<Self> {
+        let dimension = vectors.first()?.len();
+        if vectors.iter().any(|vector| vector.len() != dimension) {
+            return None;
+        }
+        let n_columns = vectors.len();
+        let mut factor = vec![0.0; dimension * n_columns];
+        for (j, vector) in vectors.iter().enumerate() {
+            for (i, &value) in vector.iter().enumerate() {
+                factor[i * n_columns + j] = value;
+            }
+        }
+        Some(Self {
+            dimension,
+            n_columns,
+            factor,
+        })
+    }
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+    fn direction(&self, direction: &mut [f64], z: &mut [f64], rng: &mut fastrand::Rng) {
+        for zj in z.iter_mut() {
+            *zj = inverse_normal_cdf(rng.f64());
+        }
+        for (i, di) in direction.iter_mut().enumerate() {
+            let row = &self.factor[i * self.n_columns..(i + 1) * self.n_columns];
+            *di = row.iter().zip(z.iter()).map(|(a, zj)| a * zj).sum();
+        }
+    }
+}
+
+// Running mean and covariance (by Welford's algorithm) of draws during warmup, from which the
+// preconditioner of random_direction_slice_sampler can be updated periodically. As with the
+// adaptation of widths, the preconditioner must be fixed after warmup for the chain to be valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CovarianceEstimator {
+    dimension: usize,
+    count: u64,
+    mean: Vec<f64>,
+    comoment: Vec<f64>,
+}
+
+impl CovarianceEstimator {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            count: 0,
+            mean: vec![0.0; dimension],
+            comoment: vec![0.0; dimension * dimension],
+        }
+    }
+    pub fn update(&mut self, x: &[f64]) {
+        let n = self.dimension;
+        self.count += 1;
+        let delta: Vec<f64> = x.iter().zip(&self.mean).map(|(xi, mi)| xi - mi).collect();
+        for (mi, di) in self.mean.iter_mut().zip(&delta) {
+            *mi += di / (self.count as f64);
+        }
+        for (i, (xi, mi)) in x.iter().zip(&self.mean).enumerate() {
+            let after = xi - mi;
+            for (j, dj) in delta.iter().enumerate() {
+                self.comoment[j * n + i] += dj * after;
+            }
+        }
+    }
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+    // Sample covariance matrix (row major).
+    pub fn covariance(&self) -> Vec<f64> {
+        let denominator = (self.count.max(2) - 1) as f64;
+        self.comoment.iter().map(|c| c / denominator).collect()
+    }
+    // Preconditioner from the sample covariance, shrunk towards a small multiple of the identity
+    // while there are few draws (as in Stan's warmup) so that it is positive definite, or None
+    // if there are fewer than two draws.
+    pub fn preconditioner(&self) -> Option<Preconditioner> {
+        if self.count < 2 {
+            return None;
+        }
+        let n = self.dimension;
+        let count = self.count as f64;
+        let weight = count / (count + 5.0);
+        let mut covariance = self.covariance();
+        for (k, value) in covariance.iter_mut().enumerate() {
+            *value *= weight;
+            if k % (n + 1) == 0 {
+                *value += 1e-3 * (1.0 - weight);
+            }
+        }
+        Preconditioner::from_covariance(n, &covariance)
+    }
+}
+
+// Slice sampler along a random line through the current state (hit-and-run), whose direction
+// is drawn from the preconditioner, updating the position along the line by stepping out and
+// shrinkage with the given tuning parameters (in units of the direction, so that a width of
+// about 2 suits a preconditioner matching the posterior covariance). Returns the number of
+// target evaluations.
+pub fn random_direction_slice_sampler<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    target: &mut T,
+    preconditioner: &Preconditioner,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    debug_assert_eq!(x.len(), preconditioner.dimension());
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let mut direction = vec![0.0; x.len()];
+    let mut z = vec![0.0; preconditioner.n_columns];
+    if let Some(generator) = rng.as_mut() {
+        preconditioner.direction(&mut direction, &mut z, generator);
+    }
+    let on_log_scale = target.on_log_scale();
+    let mut point = x.to_vec();
+    let (t, evaluations) = univariate_slice_sampler_stepping_out_and_shrinkage(
+        0.0,
+        |t| {
+            for ((pi, xi), di) in point.iter_mut().zip(x.iter()).zip(&direction) {
+                *pi = xi + t * di;
+            }
+            target.evaluate(&point)
+        },
+        on_log_scale,
+        tuning_parameters,
+        rng,
+    );
+    for (xi, di) in x.iter_mut().zip(&direction) {
+        *xi += t * di;
+    }
+    evaluations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::IllConditionedNormal;
+
+    // Mean squared distance between successive states, and the variance of the first coordinate
+    fn run(
+        target: &mut IllConditionedNormal,
+        preconditioner: &Preconditioner,
+        n_samples: usize,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> (f64, f64) {
+        let tuning_parameters = TuningParameters::builder().width(2.0).build().unwrap();
+        let mut x = vec![0.0; target.dimension];
+        let mut jumps = 0.0;
+        let mut sum_of_squares = 0.0;
+        for _ in 0..n_samples {
+            let previous = x.clone();
+            random_direction_slice_sampler(&mut x, target, preconditioner, &tuning_parameters, rng);
+            jumps += x
+                .iter()
+                .zip(&previous)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>();
+            sum_of_squares += x[0] * x[0];
+        }
+        let n = n_samples as f64;
+        (jumps / n, sum_of_squares / n)
+    }
+
+    #[test]
+    fn test_preconditioned_directions() {
+        let mut target = IllConditionedNormal {
+            dimension: 5,
+            condition_number: 1_000.0,
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(211));
+        // Warmup with isotropic directions, updating the preconditioner every 500 draws
+        let mut preconditioner = Preconditioner::identity(5);
+        let mut estimator = CovarianceEstimator::new(5);
+        let tuning_parameters = TuningParameters::builder().width(2.0).build().unwrap();
+        let mut x = vec![0.0; 5];
+        for i in 1..=5_000 {
+            random_direction_slice_sampler(
+                &mut x,
+                &mut target,
+                &preconditioner,
+                &tuning_parameters,
+                &mut rng,
+            );
+            estimator.update(&x);
+            if i % 500 == 0 {
+                preconditioner = estimator.preconditioner().unwrap();
+            }
+        }
+        let n_samples = 20_000;
+        let (isotropic_jump, _) = run(
+            &mut target,
+            &Preconditioner::identity(5),
+            n_samples,
+            &mut rng,
+        );
+        let (jump, variance) = run(&mut target, &preconditioner, n_samples, &mut rng);
+        assert!(jump > 5.0 * isotropic_jump);
+        // x = H z with H the reflection and z having independent coordinates
+        let h: f64 = 2.0 / 5.0;
+        let expected = (1.0 - h).powi(2) * target.variance(0)
+            + (1..5).map(|k| h * h * target.variance(k)).sum::<f64>();
+        assert!((variance / expected - 1.0).abs() < 0.15);
+    }
+
+    #[test]
+    fn test_constructors() {
+        assert!(Preconditioner::from_cholesky(2, vec![1.0, 0.0, 0.5, 1.0]).is_some());
+        assert!(Preconditioner::from_cholesky(2, vec![1.0, 0.5, 0.0, 1.0]).is_none());
+        assert!(Preconditioner::from_covariance(2, &[1.0, 2.0, 2.0, 1.0]).is_none());
+        let basis = Preconditioner::from_basis(&[vec![1.0, 1.0, 0.0]]).unwrap();
+        // Directions along a single vector keep the state on the line through the start
+        let mut target = IllConditionedNormal {
+            dimension: 3,
+            condition_number: 1.0,
+        };
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(223));
+        let mut x = vec![0.0, 0.0, 0.5];
+        for _ in 0..100 {
+            random_direction_slice_sampler(
+                &mut x,
+                &mut target,
+                &basis,
+                &tuning_parameters,
+                &mut rng,
+            );
+        }
+        assert_eq!(x[2], 0.5);
+        assert!((x[0] - x[1]).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::diagnostics::inverse_normal_cdf;
+use crate::math::default_rng;
+use crate::target::MultivariateTarget;
+use std::f64::consts::PI;
+
+// Murray, Adams & MacKay (2010) elliptical slice sampler for a latent vector x with a zero-mean
+// Gaussian prior, where target is the likelihood (not including the prior) and prior_draw is a
+// draw from the prior. Returns the number of likelihood evaluations.
+pub fn elliptical_slice_sampler<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    prior_draw: &[f64],
+    target: &mut T,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    let indices: Vec<usize> = (0..x.len()).collect();
+    let mean = vec![0.0; x.len()];
+    elliptical_slice_sampler_with_mean(x, &mean, &indices, prior_draw, target, rng)
+}
+
+// Like elliptical_slice_sampler, but for a Gaussian prior with the given mean and updating only
+// the coordinates of x at indices, e.g., one block of a large Gaussian process at a time. For
+// the update to leave the posterior invariant, mean and prior_draw must then be the mean and a
+// zero-mean draw from the prior of those coordinates given the others (so prior_draw has one
+// element per index), which for independent blocks is simply their marginal prior.
+pub fn elliptical_slice_sampler_with_mean<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    mean: &[f64],
+    indices: &[usize],
+    prior_draw: &[f64],
+    target: &mut T,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    assert_eq!(prior_draw.len(), indices.len());
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    shrink_ellipse(x, mean, indices, prior_draw, target, rng)
+}
+
+fn shrink_ellipse<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    mean: &[f64],
+    indices: &[usize],
+    prior_draw: &[f64],
+    target: &mut T,
+    rng: &mut fastrand::Rng,
+) -> u64 {
+    let on_log_scale = target.on_log_scale();
+    let mut log_likelihood = |x: &[f64]| {
+        let fx = target.evaluate(x);
+        if on_log_scale {
+            fx
+        } else {
+            fx.ln()
+        }
+    };
+    let offsets: Vec<f64> = indices.iter().map(|&i| x[i] - mean[i]).collect();
+    let mut proposal = x.to_vec();
+    let propose = |theta: f64, proposal: &mut [f64]| {
+        let (sin, cos) = theta.sin_cos();
+        for ((&i, offset), nu) in indices.iter().zip(&offsets).zip(prior_draw) {
+            proposal[i] = mean[i] + offset * cos + nu * sin;
+        }
+    };
+    // Step 1 (slice)
+    let y = log_likelihood(x) + rng.f64().ln();
+    let mut evaluations = 1;
+    // Step 2 (initial ellipse angle and bracket)
+    let mut theta = 2.0 * PI * rng.f64();
+    let mut theta_min = theta - 2.0 * PI;
+    let mut theta_max = theta;
+    // Step 3 (shrinkage of the bracket towards the current state at theta = 0)
+    loop {
+        propose(theta, &mut proposal);
+        evaluations += 1;
+        if y < log_likelihood(&proposal) {
+            x.copy_from_slice(&proposal);
+            return evaluations;
+        }
+        if theta < 0.0 {
+            theta_min = theta;
+        } else {
+            theta_max = theta;
+        }
+        theta = theta_min + rng.f64() * (theta_max - theta_min);
+        // The bracket has collapsed onto the current state (possible only if the likelihood is
+        // not a deterministic function), so the current state is kept
+        if theta == 0.0 || theta_max - theta_min <= f64::EPSILON {
+            return evaluations;
+        }
+    }
+}
+
+// Lower-triangular Cholesky factor (row major) of a symmetric positive definite n by n matrix,
+// or None if the matrix is not positive definite.
+pub(crate) fn cholesky(a: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| l[i * n + k] * l[j * n + k]).sum();
+            if i == j {
+                let d = a[i * n + i] - sum;
+                if d <= 0.0 || d.is_nan() {
+                    return None;
+                }
+                l[i * n + i] = d.sqrt();
+            } else {
+                l[i * n + j] = (a[i * n + j] - sum) / l[j * n + j];
+            }
+        }
+    }
+    Some(l)
+}
+
+fn standard_normal(rng: &mut fastrand::Rng) -> f64 {
+    inverse_normal_cdf(rng.f64())
+}
+
+// Marsaglia & Tsang (2000) draw from the gamma distribution with the given shape and unit rate,
+// using Gamma(shape) = Gamma(shape + 1) U^(1 / shape) for shapes less than one.
+fn standard_gamma(shape: f64, rng: &mut fastrand::Rng) -> f64 {
+    if shape < 1.0 {
+        return standard_gamma(shape + 1.0, rng) * rng.f64().powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let z = standard_normal(rng);
+        let v = (1.0 + c * z).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        if rng.f64().ln() < 0.5 * z * z + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+// Multivariate Student-t distribution with the given mean, scale matrix, and degrees of
+// freedom, used by generalized_elliptical_slice_sampler as a scale-mixture approximation to the
+// target. The scale matrix is stored through its lower-triangular Cholesky factor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudentT {
+    mean: Vec<f64>,
+    cholesky: Vec<f64>,
+    degrees_of_freedom: f64,
+}
+
+// Number of expectation-maximization iterations in StudentT::fit.
+const FIT_ITERATIONS: usize = 100;
+
+impl StudentT {
+    // The scale is an n by n symmetric positive definite matrix in row-major order, where n is
+    // the length of the mean. Returns None if the scale is not positive definite.
+    pub fn new(mean: Vec<f64>, scale: &[f64], degrees_of_freedom: f64) -> Option<Self> {
+        assert!(degrees_of_freedom > 0.0);
+        let n = mean.len();
+        assert_eq!(scale.len(), n * n);
+        Some(Self {
+            cholesky: cholesky(scale, n)?,
+            mean,
+            degrees_of_freedom,
+        })
+    }
+
+    // Maximum likelihood fit of the mean and scale to draws (e.g., the current states of a
+    // population of chains) for fixed degrees of freedom, by expectation maximization with the
+    // sample mean and covariance as the starting point. Returns None if the fitted scale is not
+    // positive definite, e.g., with fewer draws than dimensions.
+    pub fn fit(draws: &[Vec<f64>], degrees_of_freedom: f64) -> Option<Self> {
+        assert!(!draws.is_empty());
+        let n = draws[0].len();
+        let mut weights = vec![1.0; draws.len()];
+        let mut fitted = None;
+        for _ in 0..FIT_ITERATIONS {
+            let total: f64 = weights.iter().sum();
+            let mut mean = vec![0.0; n];
+            for (draw, w) in draws.iter().zip(&weights) {
+                for (m, xi) in mean.iter_mut().zip(draw) {
+                    *m += w * xi / total;
+                }
+            }
+            let mut scale = vec![0.0; n * n];
+            for (draw, w) in draws.iter().zip(&weights) {
+                for i in 0..n {
+                    for j in 0..=i {
+                        scale[i * n + j] +=
+                            w * (draw[i] - mean[i]) * (draw[j] - mean[j]) / (draws.len() as f64);
+                    }
+                }
+            }
+            for i in 0..n {
+                for j in 0..i {
+                    scale[j * n + i] = scale[i * n + j];
+                }
+            }
+            let t = Self::new(mean, &scale, degrees_of_freedom)?;
+            for (draw, w) in draws.iter().zip(weights.iter_mut()) {
+                *w = (degrees_of_freedom + n as f64)
+                    / (degrees_of_freedom + t.squared_distance(draw));
+            }
+            fitted = Some(t);
+        }
+        fitted
+    }
+
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    pub fn degrees_of_freedom(&self) -> f64 {
+        self.degrees_of_freedom
+    }
+
+    // Squared Mahalanobis distance (x - mean)^T scale^-1 (x - mean), by forward substitution.
+    fn squared_distance(&self, x: &[f64]) -> f64 {
+        let n = self.mean.len();
+        let mut z = vec![0.0; n];
+        let mut sum = 0.0;
+        for i in 0..n {
+            let partial: f64 = (0..i).map(|k| self.cholesky[i * n + k] * z[k]).sum();
+            z[i] = (x[i] - self.mean[i] - partial) / self.cholesky[i * n + i];
+            sum += z[i] * z[i];
+        }
+        sum
+    }
+
+    // Log density up to an additive constant.
+    fn log_density(&self, x: &[f64]) -> f64 {
+        let n = self.mean.len() as f64;
+        let nu = self.degrees_of_freedom;
+        -0.5 * (nu + n) * (self.squared_distance(x) / nu).ln_1p()
+    }
+}
+
+// Target divided by its Student-t approximation, the likelihood of the elliptical slice
+// sampling step in generalized_elliptical_slice_sampler.
+struct Residual<'a, T: ?Sized> {
+    target: &'a mut T,
+    approximation: &'a StudentT,
+}
+
+impl<T: MultivariateTarget + ?Sized> MultivariateTarget for Residual<'_, T> {
+    fn dimension(&self) -> usize {
+        self.target.dimension()
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let fx = self.target.evaluate(x);
+        let log_density = if self.target.on_log_scale() {
+            fx
+        } else {
+            fx.ln()
+        };
+        log_density - self.approximation.log_density(x)
+    }
+}
+
+// Nishihara, Murray & Adams (2014) generalized elliptical slice sampler for a target density
+// (including any prior) with a Student-t approximation, e.g., from StudentT::fit. Writing the
+// approximation as a scale mixture of Gaussians, the update draws the scale s from its inverse
+// gamma conditional given x and then performs an elliptical slice sampling step with prior
+// N(mean, s scale) and likelihood target / approximation. Returns the number of target
+// evaluations.
+pub fn generalized_elliptical_slice_sampler<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    approximation: &StudentT,
+    target: &mut T,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    let n = x.len();
+    assert_eq!(approximation.mean.len(), n);
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let nu = approximation.degrees_of_freedom;
+    let shape = 0.5 * (nu + n as f64);
+    let rate = 0.5 * (nu + approximation.squared_distance(x));
+    let s = rate / standard_gamma(shape, rng);
+    let z: Vec<f64> = (0..n).map(|_| standard_normal(rng)).collect();
+    let prior_draw: Vec<f64> = (0..n)
+        .map(|i| {
+            let lz: f64 = (0..=i)
+                .map(|k| approximation.cholesky[i * n + k] * z[k])
+                .sum();
+            s.sqrt() * lz
+        })
+        .collect();
+    let indices: Vec<usize> = (0..n).collect();
+    let mut residual = Residual {
+        target,
+        approximation,
+    };
+    shrink_ellipse(
+        x,
+        &approximation.mean,
+        &indices,
+        &prior_draw,
+        &mut residual,
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::Banana;
+
+    // Gaussian likelihood centered at the observations with unit variance.
+    struct Observations(Vec<f64>);
+
+    impl MultivariateTarget for Observations {
+        fn dimension(&self) -> usize {
+            self.0.len()
+        }
+        fn evaluate(&mut self, x: &[f64]) -> f64 {
+            x.iter()
+                .zip(&self.0)
+                .map(|(xi, yi)| -0.5 * (xi - yi).powi(2))
+                .sum()
+        }
+    }
+
+    #[test]
+    fn test_conjugate_normal() {
+        // With a N(mean, 1) prior and a N(x, 1) likelihood, the posterior is
+        // N((mean + y) / 2, 1 / 2)
+        let mean = [3.0, -1.0];
+        let mut target = Observations(vec![1.0, 1.0]);
+        let mut rng = Some(fastrand::Rng::with_seed(83));
+        let mut x = [0.0, 0.0];
+        let mut sums = [0.0; 2];
+        let mut sums_of_squares = [0.0; 2];
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            let generator = rng.as_mut().unwrap();
+            let prior_draw = [standard_normal(generator), standard_normal(generator)];
+            elliptical_slice_sampler_with_mean(
+                &mut x,
+                &mean,
+                &[0, 1],
+                &prior_draw,
+                &mut target,
+                &mut rng,
+            );
+            for i in 0..2 {
+                sums[i] += x[i];
+                sums_of_squares[i] += x[i] * x[i];
+            }
+        }
+        for i in 0..2 {
+            let m = sums[i] / (n_samples as f64);
+            let variance = sums_of_squares[i] / (n_samples as f64) - m * m;
+            assert!((m - (mean[i] + 1.0) / 2.0).abs() < 0.03);
+            assert!((variance - 0.5).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn test_partial_update() {
+        let mut target = Observations(vec![1.0, 1.0, 1.0]);
+        let mut rng = Some(fastrand::Rng::with_seed(89));
+        let mut x = [5.0, 0.0, 5.0];
+        for _ in 0..100 {
+            let prior_draw = [standard_normal(rng.as_mut().unwrap())];
+            elliptical_slice_sampler_with_mean(
+                &mut x,
+                &[0.0; 3],
+                &[1],
+                &prior_draw,
+                &mut target,
+                &mut rng,
+            );
+            assert_eq!((x[0], x[2]), (5.0, 5.0));
+        }
+        assert_ne!(x[1], 0.0);
+    }
+
+    #[test]
+    fn test_standard_gamma() {
+        let mut rng = fastrand::Rng::with_seed(97);
+        for shape in [0.5, 3.0] {
+            let n_samples = 50_000;
+            let mean = (0..n_samples)
+                .map(|_| standard_gamma(shape, &mut rng))
+                .sum::<f64>()
+                / (n_samples as f64);
+            assert!((mean - shape).abs() < 0.05 * shape.max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_student_t_fit() {
+        let mut rng = fastrand::Rng::with_seed(101);
+        let draws: Vec<Vec<f64>> = (0..2_000)
+            .map(|_| {
+                let z = standard_normal(&mut rng);
+                vec![1.0 + 2.0 * z, -1.0 + z + standard_normal(&mut rng)]
+            })
+            .collect();
+        let t = StudentT::fit(&draws, 1000.0).unwrap();
+        assert!((t.mean()[0] - 1.0).abs() < 0.15);
+        assert!((t.mean()[1] + 1.0).abs() < 0.15);
+        assert!((t.squared_distance(&[3.0, 0.0]) - 1.0).abs() < 0.15);
+        assert!(StudentT::new(vec![0.0, 0.0], &[1.0, 2.0, 2.0, 1.0], 3.0).is_none());
+    }
+
+    #[test]
+    fn test_generalized_banana() {
+        // The banana's second coordinate has mean 0 and variance 1 + 2 curvature^2 scale^4
+        let mut target = Banana {
+            scale: 1.0,
+            curvature: 0.5,
+        };
+        let approximation = StudentT::new(vec![0.0, 0.0], &[1.0, 0.0, 0.0, 1.5], 5.0).unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(103));
+        let mut x = [0.0, 0.0];
+        let mut sums = [0.0; 2];
+        let mut sums_of_squares = [0.0; 2];
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            generalized_elliptical_slice_sampler(&mut x, &approximation, &mut target, &mut rng);
+            for i in 0..2 {
+                sums[i] += x[i];
+                sums_of_squares[i] += x[i] * x[i];
+            }
+        }
+        for (i, variance) in [1.0, 1.5].into_iter().enumerate() {
+            let m = sums[i] / (n_samples as f64);
+            assert!(m.abs() < 0.05);
+            assert!((sums_of_squares[i] / (n_samples as f64) - m * m - variance).abs() < 0.1);
+        }
+    }
+}
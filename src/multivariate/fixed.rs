@@ -0,0 +1,127 @@
+use crate::error::{check_width, TuningError};
+use crate::math::default_rng;
+use crate::target::MultivariateTarget;
+
+// Slice sampler for a target of fixed dimension N using Neal's (2003, Section 5.1)
+// hyperrectangle procedure, as in bivariate_slice_sampler, with the state and the hyperrectangle
+// on the stack so that a draw performs no allocation: a hyperrectangle with the given widths is
+// positioned uniformly at random around the current point, and proposals are drawn uniformly
+// from it, shrinking each side towards the current point after each rejection. Returns the new
+// state and the number of target evaluations, or an error if a width is not finite and positive.
+pub fn multivariate_slice_sampler<const N: usize, T: MultivariateTarget + ?Sized>(
+    x: [f64; N],
+    target: &mut T,
+    widths: &[f64; N],
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<([f64; N], u64), TuningError> {
+    debug_assert_eq!(target.dimension(), N);
+    for &w in widths {
+        check_width(w)?;
+    }
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let mut f = |x: &[f64; N]| {
+        let fx = target.evaluate(x);
+        if on_log_scale {
+            fx
+        } else {
+            fx.ln()
+        }
+    };
+    // Step 1 (slice)
+    let y = f(&x) + rng.f64().ln();
+    let mut evaluations = 1;
+    // Step 2 (hyperrectangle)
+    let mut l = [0.0; N];
+    let mut r = [0.0; N];
+    for i in 0..N {
+        l[i] = x[i] - widths[i] * rng.f64();
+        r[i] = l[i] + widths[i];
+    }
+    // Step 3 (shrinkage)
+    let mut x1 = [0.0; N];
+    loop {
+        for i in 0..N {
+            x1[i] = l[i] + rng.f64() * (r[i] - l[i]);
+        }
+        evaluations += 1;
+        if y < f(&x1) {
+            return Ok((x1, evaluations));
+        }
+        // The hyperrectangle has collapsed onto the current state (possible only if the target
+        // is not a deterministic function) or is no longer finite, so the current state is
+        // returned
+        if x1 == x || x1.iter().any(|xi| xi.is_nan()) {
+            return Ok((x, evaluations));
+        }
+        for i in 0..N {
+            if x1[i] < x[i] {
+                l[i] = x1[i];
+            } else {
+                r[i] = x1[i];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::{CorrelatedNormal, IllConditionedNormal};
+
+    #[test]
+    fn test_correlated_normal() {
+        let mut target = CorrelatedNormal { correlation: 0.5 };
+        let mut rng = Some(fastrand::Rng::with_seed(131));
+        let mut x = [0.0; 2];
+        let (mut sum, mut sum_of_products) = (0.0, 0.0);
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (x, _) = multivariate_slice_sampler(x, &mut target, &[2.0, 2.0], &mut rng).unwrap();
+            sum += x[0];
+            sum_of_products += x[0] * x[1];
+        }
+        assert!((sum / (n_samples as f64)).abs() < 0.05);
+        assert!((sum_of_products / (n_samples as f64) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_isotropic_normal() {
+        // With condition number one the covariance is the identity
+        let mut target = IllConditionedNormal {
+            dimension: 4,
+            condition_number: 1.0,
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(137));
+        let mut x = [0.0; 4];
+        let mut sums_of_squares = [0.0; 4];
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            (x, _) = multivariate_slice_sampler(x, &mut target, &[3.0; 4], &mut rng).unwrap();
+            for (s, xi) in sums_of_squares.iter_mut().zip(x) {
+                *s += xi * xi;
+            }
+        }
+        for s in sums_of_squares {
+            assert!((s / (n_samples as f64) - 1.0).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_invalid_widths() {
+        let mut target = CorrelatedNormal { correlation: 0.5 };
+        for w in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                multivariate_slice_sampler([0.0; 2], &mut target, &[1.0, w], &mut None),
+                Err(TuningError::InvalidWidth(w))
+            );
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::multivariate::gibbs::TuningParametersVec;
+use crate::univariate::stepping_out::univariate_slice_sampler_stepping_out_and_shrinkage;
+
+// Updates the location mu and log scale log_sigma of group-level effects theta_i ~ N(mu, sigma^2)
+// by interweaving (Yu & Meng, 2011): each is first updated given the effects (the centered
+// parameterization), and then given the standardized effects z_i = (theta_i - mu) / sigma (the
+// non-centered one), moving the effects with them. The centered updates mix well when the data
+// are informative about the effects and the non-centered ones when they are not, so alternating
+// avoids getting stuck in the neck of the funnel either way. log_prior is the joint log prior of
+// (mu, log_sigma), including any Jacobian for the log scale, and log_likelihood the log density
+// of the data given the effects. tuning_parameters has the stepping out tuning parameters of
+// mu and then log_sigma, e.g., from the prior scales. Updating the effects given mu, log_sigma,
+// and the data is left to the caller. Returns the number of evaluations of the four updates.
+pub fn interweaving_funnel_sweep<P, L>(
+    location: &mut f64,
+    log_scale: &mut f64,
+    effects: &mut [f64],
+    mut log_prior: P,
+    mut log_likelihood: L,
+    tuning_parameters: &TuningParametersVec,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64
+where
+    P: FnMut(f64, f64) -> f64,
+    L: FnMut(&[f64]) -> f64,
+{
+    let n = effects.len() as f64;
+    let mut evaluations = 0;
+    // Centered updates, which do not involve the data
+    let (mu, count) = univariate_slice_sampler_stepping_out_and_shrinkage(
+        *location,
+        |mu| {
+            let precision = (-2.0 * *log_scale).exp();
+            let sum_of_squares: f64 = effects.iter().map(|t| (t - mu) * (t - mu)).sum();
+            log_prior(mu, *log_scale) - 0.5 * precision * sum_of_squares
+        },
+        true,
+        tuning_parameters.get(0),
+        rng,
+    );
+    *location = mu;
+    evaluations += count;
+    let sum_of_squares: f64 = effects.iter().map(|t| (t - mu) * (t - mu)).sum();
+    let (log_sigma, count) = univariate_slice_sampler_stepping_out_and_shrinkage(
+        *log_scale,
+        |log_sigma| {
+            log_prior(mu, log_sigma)
+                - n * log_sigma
+                - 0.5 * (-2.0 * log_sigma).exp() * sum_of_squares
+        },
+        true,
+        tuning_parameters.get(1),
+        rng,
+    );
+    *log_scale = log_sigma;
+    evaluations += count;
+    // Non-centered updates, holding the standardized effects fixed
+    let sigma = log_sigma.exp();
+    let z: Vec<f64> = effects.iter().map(|t| (t - mu) / sigma).collect();
+    let moved = |mu: f64, log_sigma: f64, effects: &mut [f64]| {
+        let sigma = log_sigma.exp();
+        for (t, zi) in effects.iter_mut().zip(&z) {
+            *t = mu + sigma * zi;
+        }
+    };
+    let (mu, count) = univariate_slice_sampler_stepping_out_and_shrinkage(
+        *location,
+        |mu| {
+            moved(mu, *log_scale, effects);
+            log_prior(mu, *log_scale) + log_likelihood(effects)
+        },
+        true,
+        tuning_parameters.get(0),
+        rng,
+    );
+    *location = mu;
+    evaluations += count;
+    let (log_sigma, count) = univariate_slice_sampler_stepping_out_and_shrinkage(
+        *log_scale,
+        |log_sigma| {
+            moved(mu, log_sigma, effects);
+            log_prior(mu, log_sigma) + log_likelihood(effects)
+        },
+        true,
+        tuning_parameters.get(1),
+        rng,
+    );
+    *log_scale = log_sigma;
+    evaluations += count;
+    moved(mu, log_sigma, effects);
+    evaluations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::inverse_normal_cdf;
+
+    #[test]
+    fn test_interweaving_funnel_sweep() {
+        // Eight effects observed with unit noise, under independent standard normal priors on
+        // mu and log_sigma. Without data the posterior is the funnel, whose log scale margin is
+        // the prior; with data the mean of mu is compared with its exact value.
+        let mut rng = Some(fastrand::Rng::with_seed(241));
+        let tuning_parameters = TuningParametersVec::new(2);
+        let log_prior = |mu: f64, log_sigma: f64| -0.5 * (mu * mu + log_sigma * log_sigma);
+        for data in [None, Some([2.0; 8])] {
+            let mut mu = 0.0;
+            let mut log_sigma = 0.0;
+            let mut effects = vec![0.0; 8];
+            let mut sum = 0.0;
+            let mut sum_of_squares = 0.0;
+            let mut mu_sum = 0.0;
+            let n_samples = 20_000;
+            for _ in 0..n_samples {
+                let log_likelihood = |theta: &[f64]| match data {
+                    None => 0.0,
+                    Some(y) => theta
+                        .iter()
+                        .zip(y)
+                        .map(|(t, yi)| -0.5 * (t - yi) * (t - yi))
+                        .sum(),
+                };
+                interweaving_funnel_sweep(
+                    &mut mu,
+                    &mut log_sigma,
+                    &mut effects,
+                    log_prior,
+                    log_likelihood,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+                // Conjugate update of the effects
+                let sigma2 = (2.0 * log_sigma).exp();
+                for (i, t) in effects.iter_mut().enumerate() {
+                    let (mean, variance) = match data {
+                        None => (mu, sigma2),
+                        Some(y) => {
+                            let variance = 1.0 / (1.0 + 1.0 / sigma2);
+                            (variance * (y[i] + mu / sigma2), variance)
+                        }
+                    };
+                    let z = inverse_normal_cdf(rng.as_mut().unwrap().f64());
+                    *t = mean + variance.sqrt() * z;
+                }
+                sum += log_sigma;
+                sum_of_squares += log_sigma * log_sigma;
+                mu_sum += mu;
+            }
+            let n = n_samples as f64;
+            let mean = sum / n;
+            match data {
+                None => {
+                    assert!(mean.abs() < 0.05);
+                    assert!((sum_of_squares / n - mean * mean - 1.0).abs() < 0.1);
+                }
+                // All y_i = 2, so given sigma the mean of mu is 16 / (9 + sigma^2) and the
+                // marginal likelihood is proportional to (1 + sigma^2)^(-7 / 2) N(2 | 0, 1 +
+                // (1 + sigma^2) / 8), which quadrature over log_sigma averages
+                Some(_) => {
+                    let mut weight_sum = 0.0;
+                    let mut mean_sum = 0.0;
+                    for k in 0..=1200 {
+                        let log_sigma = -6.0 + 0.01 * (k as f64);
+                        let sigma2 = (2.0 * log_sigma).exp();
+                        let variance = 1.0 + (1.0 + sigma2) / 8.0;
+                        let weight = (-0.5 * log_sigma * log_sigma - 2.0 / variance).exp()
+                            * (1.0 + sigma2).powf(-3.5)
+                            / variance.sqrt();
+                        weight_sum += weight;
+                        mean_sum += weight * 16.0 / (9.0 + sigma2);
+                    }
+                    assert!((mu_sum / n - mean_sum / weight_sum).abs() < 0.03);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::adaptation::WarmupAdapter;
+use crate::univariate::stepping_out::{
+    self, stepping_out_and_shrinkage_with_interval,
+    univariate_slice_sampler_stepping_out_and_shrinkage,
+};
+use crate::univariate::Phases;
+
+type Term<'a> = Box<dyn FnMut(&[f64]) -> f64 + 'a>;
+
+// Log density that is a sum of terms, each declared with the coordinates it depends on, so that
+// a coordinate-wise update only evaluates the terms involving that coordinate. The values of the
+// terms at the current state are cached, giving the full log density without re-evaluation.
+pub struct AdditiveTarget<'a> {
+    terms: Vec<Term<'a>>,
+    terms_of_coordinate: Vec<Vec<usize>>,
+    values: Vec<f64>,
+}
+
+impl<'a> AdditiveTarget<'a> {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            terms: Vec::new(),
+            terms_of_coordinate: vec![Vec::new(); dimension],
+            values: Vec::new(),
+        }
+    }
+    pub fn dimension(&self) -> usize {
+        self.terms_of_coordinate.len()
+    }
+    // Adds a term on the log scale depending only on the given coordinates of the state.
+    pub fn add_term<T: FnMut(&[f64]) -> f64 + 'a>(&mut self, coordinates: &[usize], term: T) {
+        let t = self.terms.len();
+        for &i in coordinates {
+            if !self.terms_of_coordinate[i].contains(&t) {
+                self.terms_of_coordinate[i].push(t);
+            }
+        }
+        self.terms.push(Box::new(term));
+        self.values.push(f64::NAN);
+    }
+    // Evaluates all terms at x, refreshing the cache, and returns the log density.
+    pub fn evaluate(&mut self, x: &[f64]) -> f64 {
+        for (value, term) in self.values.iter_mut().zip(self.terms.iter_mut()) {
+            *value = term(x);
+        }
+        self.log_density()
+    }
+    // Log density at the state of the last evaluation or sweep, from the cached term values.
+    pub fn log_density(&self) -> f64 {
+        self.values.iter().sum()
+    }
+}
+
+// Stepping out tuning parameters for each coordinate of a multivariate state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningParametersVec {
+    tuning_parameters: Vec<stepping_out::TuningParameters>,
+}
+
+impl TuningParametersVec {
+    pub fn new(dimension: usize) -> Self {
+        Self::repeat(&stepping_out::TuningParameters::new(), dimension)
+    }
+    pub fn repeat(tuning_parameters: &stepping_out::TuningParameters, dimension: usize) -> Self {
+        Self {
+            tuning_parameters: vec![tuning_parameters.clone(); dimension],
+        }
+    }
+    pub fn from_vec(tuning_parameters: Vec<stepping_out::TuningParameters>) -> Self {
+        Self { tuning_parameters }
+    }
+    pub fn dimension(&self) -> usize {
+        self.tuning_parameters.len()
+    }
+    pub fn get(&self, i: usize) -> &stepping_out::TuningParameters {
+        &self.tuning_parameters[i]
+    }
+    pub fn set(&mut self, i: usize, tuning_parameters: stepping_out::TuningParameters) {
+        self.tuning_parameters[i] = tuning_parameters;
+    }
+}
+
+// Updates each coordinate of x in turn using the stepping out and shrinkage procedures with
+// that coordinate's tuning parameters, where the conditional log density of a coordinate only
+// involves the terms depending on it. Returns the number of term evaluations.
+pub fn gibbs_sweep_stepping_out_and_shrinkage(
+    x: &mut [f64],
+    target: &mut AdditiveTarget,
+    tuning_parameters: &TuningParametersVec,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    sweep(x, target, |i, xi, f| {
+        univariate_slice_sampler_stepping_out_and_shrinkage(
+            xi,
+            f,
+            true,
+            tuning_parameters.get(i),
+            rng,
+        )
+    })
+}
+
+// Like gibbs_sweep_stepping_out_and_shrinkage, but the width of each coordinate is learned
+// independently by its own warmup adapter.
+pub fn adaptive_gibbs_sweep_stepping_out_and_shrinkage(
+    x: &mut [f64],
+    target: &mut AdditiveTarget,
+    tuning_parameters: &TuningParametersVec,
+    adapters: &mut [WarmupAdapter],
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    sweep(x, target, |i, xi, f| {
+        adapters[i].stepping_out_and_shrinkage(xi, f, true, tuning_parameters.get(i), rng)
+    })
+}
+
+// Final interval of each coordinate's latest update, from which the next sweep warm-starts the
+// initial width of that coordinate at safety_factor times the width of the interval, so that
+// little stepping out is needed while the conditional scales change slowly. Since the width then
+// depends on the history of the chain, the intervals are only remembered over the first
+// n_warmup sweeps, after which the widths are frozen so that the remaining sweeps form a valid
+// Markov chain. Used by gibbs_sweep_with_interval_memory and by the blocks of a Model added with
+// add_scalar_with_interval_memory or add_block_with_interval_memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalMemory {
+    safety_factor: f64,
+    widths: Vec<Option<f64>>,
+    n_warmup: u32,
+    n_sweeps: u32,
+}
+
+impl IntervalMemory {
+    pub fn new(dimension: usize, safety_factor: f64, n_warmup: u32) -> Self {
+        Self {
+            safety_factor,
+            widths: vec![None; dimension],
+            n_warmup,
+            n_sweeps: 0,
+        }
+    }
+    // Initial width for coordinate i, if an interval of it has been remembered.
+    pub fn width(&self, i: usize) -> Option<f64> {
+        self.widths[i]
+    }
+    pub fn is_frozen(&self) -> bool {
+        self.n_sweeps >= self.n_warmup
+    }
+    pub(crate) fn record(&mut self, i: usize, interval_width: f64) {
+        let width = self.safety_factor * interval_width;
+        if !self.is_frozen() && width.is_finite() && width > 0.0 {
+            self.widths[i] = Some(width);
+        }
+    }
+    pub(crate) fn end_sweep(&mut self) {
+        self.n_sweeps = self.n_sweeps.saturating_add(1);
+    }
+}
+
+// Like gibbs_sweep_stepping_out_and_shrinkage, but the initial width of each coordinate is the
+// one remembered from its previous update (or that of its tuning parameters before there is one).
+pub fn gibbs_sweep_with_interval_memory(
+    x: &mut [f64],
+    target: &mut AdditiveTarget,
+    tuning_parameters: &TuningParametersVec,
+    memory: &mut IntervalMemory,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    let term_evaluations = sweep(x, target, |i, xi, f| {
+        let tuning_parameters = tuning_parameters.get(i);
+        let w = memory.width(i).unwrap_or(tuning_parameters.width());
+        let (x1, evaluation_counter, (l, r)) = stepping_out_and_shrinkage_with_interval(
+            xi,
+            f,
+            true,
+            w,
+            tuning_parameters,
+            &mut Phases::default(),
+            rng,
+        );
+        memory.record(i, r - l);
+        (x1, evaluation_counter)
+    });
+    memory.end_sweep();
+    term_evaluations
+}
+
+fn sweep<U>(x: &mut [f64], target: &mut AdditiveTarget, mut update: U) -> u64
+where
+    U: FnMut(usize, f64, &mut dyn FnMut(f64) -> f64) -> (f64, u64),
+{
+    let mut term_evaluations = 0;
+    let mut last = Vec::new();
+    for i in 0..target.dimension() {
+        let indices = &target.terms_of_coordinate[i];
+        if indices.is_empty() {
+            continue;
+        }
+        let terms = &mut target.terms;
+        let xi = x[i];
+        let mut last_point = f64::NAN;
+        last.resize(indices.len(), 0.0);
+        let mut f = |v: f64| {
+            x[i] = v;
+            last_point = v;
+            let mut sum = 0.0;
+            for (value, &t) in last.iter_mut().zip(indices.iter()) {
+                *value = terms[t](x);
+                sum += *value;
+            }
+            sum
+        };
+        let (xi, evaluation_counter) = update(i, xi, &mut f);
+        term_evaluations += evaluation_counter * (indices.len() as u64);
+        x[i] = xi;
+        for (j, &t) in indices.iter().enumerate() {
+            target.values[t] = if last_point == xi {
+                last[j]
+            } else {
+                term_evaluations += 1;
+                target.terms[t](x)
+            };
+        }
+    }
+    term_evaluations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_of_normals() {
+        // Random walk prior x[i] ~ N(x[i-1], 1) with x[0] ~ N(0, 1), so Var(x[i]) = i + 1
+        let dimension = 10;
+        let mut target = AdditiveTarget::new(dimension);
+        target.add_term(&[0], |x: &[f64]| -0.5 * x[0] * x[0]);
+        for i in 1..dimension {
+            target.add_term(&[i - 1, i], move |x: &[f64]| {
+                -0.5 * (x[i] - x[i - 1]) * (x[i] - x[i - 1])
+            });
+        }
+        let tuning_parameters = TuningParametersVec::repeat(
+            &stepping_out::TuningParameters::builder()
+                .width(2.)
+                .build()
+                .unwrap(),
+            dimension,
+        );
+        let mut rng = Some(fastrand::Rng::with_seed(17));
+        let mut x = vec![0.0; dimension];
+        target.evaluate(&x);
+        let n_samples = 20_000;
+        let mut sum_of_squares = 0.0;
+        let mut term_evaluations = 0;
+        for _ in 0..n_samples {
+            term_evaluations += gibbs_sweep_stepping_out_and_shrinkage(
+                &mut x,
+                &mut target,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum_of_squares += x[2] * x[2];
+        }
+        let cached = target.log_density();
+        assert!((cached - target.evaluate(&x)).abs() < 1e-12);
+        assert!((sum_of_squares / (n_samples as f64) - 3.0).abs() < 0.3);
+        // Each update touches at most two of the ten terms, so a sweep of about six evaluations
+        // per coordinate costs about 110 term evaluations rather than the 600 of full evaluations
+        let per_sweep = (term_evaluations as f64) / (n_samples as f64);
+        assert!((90.0..140.0).contains(&per_sweep));
+    }
+
+    #[test]
+    fn test_adaptive_widths_per_coordinate() {
+        // Independent normals with very different scales
+        let scales = [0.01, 1.0, 100.0];
+        let mut target = AdditiveTarget::new(scales.len());
+        for (i, &scale) in scales.iter().enumerate() {
+            target.add_term(&[i], move |x: &[f64]| -0.5 * (x[i] / scale).powi(2));
+        }
+        let tuning_parameters = TuningParametersVec::new(scales.len());
+        let mut adapters = vec![WarmupAdapter::new(1.0, 2_000).unwrap(); scales.len()];
+        let mut rng = Some(fastrand::Rng::with_seed(23));
+        let mut x = vec![0.0; scales.len()];
+        for _ in 0..2_000 {
+            adaptive_gibbs_sweep_stepping_out_and_shrinkage(
+                &mut x,
+                &mut target,
+                &tuning_parameters,
+                &mut adapters,
+                &mut rng,
+            );
+        }
+        for (adapter, &scale) in adapters.iter().zip(scales.iter()) {
+            assert!(adapter.is_frozen());
+            assert!(adapter.width() > 0.1 * scale && adapter.width() < 10.0 * scale);
+        }
+    }
+
+    #[test]
+    fn test_interval_memory() {
+        // Independent normals with a scale far from the initial width of 1
+        let dimension = 3;
+        let mut target = AdditiveTarget::new(dimension);
+        for i in 0..dimension {
+            target.add_term(&[i], move |x: &[f64]| -0.5 * (x[i] / 100.0).powi(2));
+        }
+        let tuning_parameters = TuningParametersVec::new(dimension);
+        let mut rng = Some(fastrand::Rng::with_seed(199));
+        let mut x = vec![0.0; dimension];
+        let mut cold = 0;
+        let mut memory = IntervalMemory::new(dimension, 2.0, 500);
+        let mut warm = 0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 5_000;
+        for _ in 0..n_samples {
+            cold += gibbs_sweep_stepping_out_and_shrinkage(
+                &mut x.clone(),
+                &mut target,
+                &tuning_parameters,
+                &mut rng,
+            );
+            warm += gibbs_sweep_with_interval_memory(
+                &mut x,
+                &mut target,
+                &tuning_parameters,
+                &mut memory,
+                &mut rng,
+            );
+            sum_of_squares += x[0] * x[0];
+        }
+        assert!(memory.is_frozen());
+        assert!(memory.width(0).is_some_and(|w| w > 10.0 && w < 10_000.0));
+        assert!((sum_of_squares / (n_samples as f64) / 10_000.0 - 1.0).abs() < 0.1);
+        assert!(10 * warm < cold);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod bivariate;
+pub mod crumbs;
+pub mod direction;
+pub mod elliptical;
+pub mod fixed;
+pub mod funnel;
+pub mod gibbs;
+pub mod model;
+pub mod reflective;
+pub mod simplex;
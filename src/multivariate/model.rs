@@ -0,0 +1,564 @@
+use crate::multivariate::gibbs::IntervalMemory;
+use crate::target::UnivariateTarget;
+use crate::transforms::VectorTransform;
+use crate::univariate::stepping_out::{self, stepping_out_and_shrinkage_with_interval};
+use crate::univariate::variant::{SliceSampler, SliceVariant, UnivariateSampler};
+use crate::univariate::Phases;
+
+type ConditionalLogDensity<'a, S> = Box<dyn FnMut(&S) -> f64 + 'a>;
+type ConditionalDraw<'a, S> = Box<dyn FnMut(&S, &mut fastrand::Rng) -> Vec<f64> + 'a>;
+
+// Location of a parameter block within the shared state.
+enum Access<S> {
+    Scalar(fn(&mut S) -> &mut f64),
+    Block(fn(&mut S) -> &mut [f64]),
+}
+
+impl<S> Access<S> {
+    fn get<'s>(&self, state: &'s mut S) -> &'s mut [f64] {
+        match self {
+            Access::Scalar(access) => core::slice::from_mut(access(state)),
+            Access::Block(access) => access(state),
+        }
+    }
+}
+
+// Update of a block: coordinate-wise slice sampling from the log density of its full
+// conditional (in the unconstrained coordinates of a transform, if any), coordinate-wise
+// stepping out and shrinkage warm-started from the remembered intervals of the previous sweep, or
+// a direct draw from a known full conditional (e.g., in the conjugate case).
+enum Update<'a, S> {
+    Slice {
+        sampler: Box<dyn UnivariateSampler + 'a>,
+        log_density: ConditionalLogDensity<'a, S>,
+        transform: Option<Box<dyn VectorTransform + 'a>>,
+    },
+    Remembered {
+        tuning_parameters: stepping_out::TuningParameters,
+        memory: IntervalMemory,
+        rng: Option<fastrand::Rng>,
+        log_density: ConditionalLogDensity<'a, S>,
+    },
+    Direct(ConditionalDraw<'a, S>),
+}
+
+// Named scalar or vector parameter with its update and the values recorded after each sweep.
+struct ParameterBlock<'a, S> {
+    name: String,
+    access: Access<S>,
+    update: Update<'a, S>,
+    draws: Vec<Vec<f64>>,
+}
+
+// Full conditional of one coordinate of a block, evaluated by writing the coordinate into the
+// shared state or, for a transformed block, into the unconstrained coordinates and their
+// constrained value into the shared state, adding the log Jacobian.
+struct Conditional<'s, 'a, S> {
+    state: &'s mut S,
+    access: &'s Access<S>,
+    index: usize,
+    log_density: &'s mut ConditionalLogDensity<'a, S>,
+    transform: Option<(&'s dyn VectorTransform, &'s mut [f64])>,
+}
+
+impl<S> UnivariateTarget for Conditional<'_, '_, S> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        match &mut self.transform {
+            None => {
+                self.access.get(self.state)[self.index] = x;
+                (self.log_density)(self.state)
+            }
+            Some((transform, y)) => {
+                y[self.index] = x;
+                let log_jacobian = transform.constrain(y, self.access.get(self.state));
+                let log_density = (self.log_density)(self.state);
+                if log_density == f64::NEG_INFINITY {
+                    log_density
+                } else {
+                    log_density + log_jacobian
+                }
+            }
+        }
+    }
+}
+
+// Gibbs sampler over a user-defined state struct, where each registered parameter block is
+// updated coordinate-wise by its own slice sampler using the log density of its full conditional
+// or, for semi-conjugate models, by a user-supplied draw from its full conditional. Slice
+// samplers are seeded from the model's random number generator, which is also passed to direct
+// draws, and the values of every block are recorded after each sweep.
+pub struct Model<'a, S> {
+    state: S,
+    blocks: Vec<ParameterBlock<'a, S>>,
+    rng: fastrand::Rng,
+}
+
+impl<'a, S> Model<'a, S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            blocks: Vec::new(),
+            rng: fastrand::Rng::new(),
+        }
+    }
+    pub fn with_seed(state: S, seed: u64) -> Self {
+        Self {
+            state,
+            blocks: Vec::new(),
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+    // Registers a scalar parameter, e.g., with access |s: &mut State| &mut s.mu.
+    pub fn add_scalar<L: FnMut(&S) -> f64 + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut f64,
+        variant: SliceVariant,
+        log_density: L,
+    ) {
+        let update = self.slice_update(variant, Box::new(log_density), None);
+        self.add(name, Access::Scalar(access), update);
+    }
+    // Registers a vector parameter whose coordinates are updated in turn, e.g., with access
+    // |s: &mut State| &mut s.theta[..].
+    pub fn add_block<L: FnMut(&S) -> f64 + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut [f64],
+        variant: SliceVariant,
+        log_density: L,
+    ) {
+        let update = self.slice_update(variant, Box::new(log_density), None);
+        self.add(name, Access::Block(access), update);
+    }
+    // Registers a vector parameter whose value in the state is constrained by the transform (e.g.,
+    // a covariance matrix or an ordered vector), which is updated by slice sampling each of its
+    // unconstrained coordinates in turn. The log density is that of the constrained value, to
+    // which the log Jacobian of the transform is added.
+    pub fn add_transformed_block<R, L>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut [f64],
+        transform: R,
+        variant: SliceVariant,
+        log_density: L,
+    ) where
+        R: VectorTransform + 'a,
+        L: FnMut(&S) -> f64 + 'a,
+    {
+        let transform: Box<dyn VectorTransform + 'a> = Box::new(transform);
+        let update = self.slice_update(variant, Box::new(log_density), Some(transform));
+        self.add(name, Access::Block(access), update);
+    }
+    // Registers a scalar parameter updated by stepping out and shrinkage whose initial width is
+    // warm-started from its interval in the previous sweep, as in gibbs_sweep_with_interval_memory,
+    // over the first sweeps given by the memory (of dimension one).
+    pub fn add_scalar_with_interval_memory<L: FnMut(&S) -> f64 + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut f64,
+        tuning_parameters: stepping_out::TuningParameters,
+        memory: IntervalMemory,
+        log_density: L,
+    ) {
+        let update = self.remembered_update(tuning_parameters, memory, Box::new(log_density));
+        self.add(name, Access::Scalar(access), update);
+    }
+    // Like add_scalar_with_interval_memory, but for a vector parameter whose coordinates are
+    // updated in turn, with a memory of the same dimension.
+    pub fn add_block_with_interval_memory<L: FnMut(&S) -> f64 + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut [f64],
+        tuning_parameters: stepping_out::TuningParameters,
+        memory: IntervalMemory,
+        log_density: L,
+    ) {
+        let update = self.remembered_update(tuning_parameters, memory, Box::new(log_density));
+        self.add(name, Access::Block(access), update);
+    }
+    // Registers a scalar parameter updated by drawing from its full conditional given the state.
+    pub fn add_direct_scalar<D: FnMut(&S, &mut fastrand::Rng) -> f64 + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut f64,
+        mut draw: D,
+    ) {
+        let update = Update::Direct(Box::new(move |state, rng| vec![draw(state, rng)]));
+        self.add(name, Access::Scalar(access), update);
+    }
+    // Registers a vector parameter updated by drawing all of its coordinates from their joint full
+    // conditional given the state.
+    pub fn add_direct_block<D: FnMut(&S, &mut fastrand::Rng) -> Vec<f64> + 'a>(
+        &mut self,
+        name: &str,
+        access: fn(&mut S) -> &mut [f64],
+        draw: D,
+    ) {
+        self.add(name, Access::Block(access), Update::Direct(Box::new(draw)));
+    }
+    fn slice_update(
+        &mut self,
+        variant: SliceVariant,
+        log_density: ConditionalLogDensity<'a, S>,
+        transform: Option<Box<dyn VectorTransform + 'a>>,
+    ) -> Update<'a, S> {
+        Update::Slice {
+            sampler: Box::new(SliceSampler::with_seed(variant, self.rng.u64(..))),
+            log_density,
+            transform,
+        }
+    }
+    fn remembered_update(
+        &mut self,
+        tuning_parameters: stepping_out::TuningParameters,
+        memory: IntervalMemory,
+        log_density: ConditionalLogDensity<'a, S>,
+    ) -> Update<'a, S> {
+        Update::Remembered {
+            tuning_parameters,
+            memory,
+            rng: Some(fastrand::Rng::with_seed(self.rng.u64(..))),
+            log_density,
+        }
+    }
+    fn add(&mut self, name: &str, access: Access<S>, update: Update<'a, S>) {
+        self.blocks.push(ParameterBlock {
+            name: name.to_string(),
+            access,
+            update,
+            draws: Vec::new(),
+        });
+    }
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+    pub fn into_state(self) -> S {
+        self.state
+    }
+    pub fn names(&self) -> Vec<&str> {
+        self.blocks
+            .iter()
+            .map(|block| block.name.as_str())
+            .collect()
+    }
+    // Values of the named parameter after each sweep.
+    pub fn draws(&self, name: &str) -> Option<&[Vec<f64>]> {
+        self.blocks
+            .iter()
+            .find(|block| block.name == name)
+            .map(|block| block.draws.as_slice())
+    }
+    // Updates every block in the order registered and records their values.
+    pub fn sweep(&mut self) {
+        for block in self.blocks.iter_mut() {
+            match &mut block.update {
+                Update::Slice {
+                    sampler,
+                    log_density,
+                    transform,
+                } => {
+                    let mut y = transform.as_ref().map(|transform| {
+                        let mut y = vec![0.0; transform.unconstrained_dimension()];
+                        transform.unconstrain(block.access.get(&mut self.state), &mut y);
+                        y
+                    });
+                    let n = match &y {
+                        Some(y) => y.len(),
+                        None => block.access.get(&mut self.state).len(),
+                    };
+                    for index in 0..n {
+                        let x = match &y {
+                            Some(y) => y[index],
+                            None => block.access.get(&mut self.state)[index],
+                        };
+                        let mut target = Conditional {
+                            state: &mut self.state,
+                            access: &block.access,
+                            index,
+                            log_density,
+                            transform: transform.as_deref().zip(y.as_deref_mut()),
+                        };
+                        let x1 = sampler.draw(x, &mut target);
+                        match (transform.as_deref(), y.as_deref_mut()) {
+                            (Some(transform), Some(y)) => {
+                                y[index] = x1;
+                                transform.constrain(y, block.access.get(&mut self.state));
+                            }
+                            _ => block.access.get(&mut self.state)[index] = x1,
+                        }
+                    }
+                }
+                Update::Remembered {
+                    tuning_parameters,
+                    memory,
+                    rng,
+                    log_density,
+                } => {
+                    let n = block.access.get(&mut self.state).len();
+                    for index in 0..n {
+                        let x = block.access.get(&mut self.state)[index];
+                        let mut target = Conditional {
+                            state: &mut self.state,
+                            access: &block.access,
+                            index,
+                            log_density,
+                            transform: None,
+                        };
+                        let w = memory.width(index).unwrap_or(tuning_parameters.width());
+                        let (x1, _, (l, r)) = stepping_out_and_shrinkage_with_interval(
+                            x,
+                            |x| target.evaluate(x),
+                            true,
+                            w,
+                            tuning_parameters,
+                            &mut Phases::default(),
+                            rng,
+                        );
+                        memory.record(index, r - l);
+                        block.access.get(&mut self.state)[index] = x1;
+                    }
+                    memory.end_sweep();
+                }
+                Update::Direct(draw) => {
+                    let values = draw(&self.state, &mut self.rng);
+                    block.access.get(&mut self.state).copy_from_slice(&values);
+                }
+            }
+            block.draws.push(block.access.get(&mut self.state).to_vec());
+        }
+    }
+    pub fn run(&mut self, n_sweeps: usize) {
+        for _ in 0..n_sweeps {
+            self.sweep();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    struct State {
+        mu: f64,
+        theta: Vec<f64>,
+    }
+
+    #[test]
+    fn test_hierarchical_normal() {
+        // mu ~ N(0, 1) and theta_i | mu ~ N(mu, 1), so mu has variance 1 and theta_i has
+        // variance 2 with covariance 1 between coordinates
+        let variant = SliceVariant::SteppingOut(TuningParameters::new());
+        let state = State {
+            mu: 0.0,
+            theta: vec![0.0; 2],
+        };
+        let mut model = Model::with_seed(state, 59);
+        model.add_scalar(
+            "mu",
+            |s: &mut State| &mut s.mu,
+            variant.clone(),
+            |s: &State| {
+                -0.5 * s.mu * s.mu
+                    - 0.5 * s.theta.iter().map(|t| (t - s.mu) * (t - s.mu)).sum::<f64>()
+            },
+        );
+        model.add_block(
+            "theta",
+            |s: &mut State| &mut s.theta[..],
+            variant,
+            |s: &State| -0.5 * s.theta.iter().map(|t| (t - s.mu) * (t - s.mu)).sum::<f64>(),
+        );
+        let n_sweeps = 50_000;
+        model.run(n_sweeps);
+        assert_eq!(model.names(), ["mu", "theta"]);
+        let moment = |draws: &[Vec<f64>], f: &dyn Fn(&[f64]) -> f64| {
+            draws.iter().map(|x| f(x)).sum::<f64>() / (n_sweeps as f64)
+        };
+        let mu = model.draws("mu").unwrap();
+        let theta = model.draws("theta").unwrap();
+        assert_eq!(theta.len(), n_sweeps);
+        assert!(moment(mu, &|x| x[0]).abs() < 0.05);
+        assert!((moment(mu, &|x| x[0] * x[0]) - 1.0).abs() < 0.1);
+        assert!((moment(theta, &|x| x[1] * x[1]) - 2.0).abs() < 0.2);
+        assert!((moment(theta, &|x| x[0] * x[1]) - 1.0).abs() < 0.2);
+        assert!(model.draws("sigma").is_none());
+    }
+
+    #[test]
+    fn test_interval_memory() {
+        // mu ~ N(0, 100^2) and independent theta_i ~ N(0, 100^2), far from the initial width of 1
+        use core::cell::Cell;
+        let n_sweeps = 5_000;
+        let mut evaluations = [0, 0];
+        let mut second_moments = [0.0, 0.0];
+        for (remembered, evaluations) in [false, true].into_iter().zip(evaluations.iter_mut()) {
+            let counter = Cell::new(0);
+            let state = State {
+                mu: 0.0,
+                theta: vec![0.0; 2],
+            };
+            let mut model = Model::with_seed(state, 61);
+            let mu_density = |s: &State| {
+                counter.set(counter.get() + 1);
+                -0.5 * (s.mu / 100.0).powi(2)
+            };
+            let theta_density = |s: &State| {
+                counter.set(counter.get() + 1);
+                -0.5 * s.theta.iter().map(|t| (t / 100.0).powi(2)).sum::<f64>()
+            };
+            if remembered {
+                model.add_scalar_with_interval_memory(
+                    "mu",
+                    |s: &mut State| &mut s.mu,
+                    TuningParameters::new(),
+                    IntervalMemory::new(1, 2.0, 500),
+                    mu_density,
+                );
+                model.add_block_with_interval_memory(
+                    "theta",
+                    |s: &mut State| &mut s.theta[..],
+                    TuningParameters::new(),
+                    IntervalMemory::new(2, 2.0, 500),
+                    theta_density,
+                );
+            } else {
+                let variant = SliceVariant::SteppingOut(TuningParameters::new());
+                model.add_scalar("mu", |s: &mut State| &mut s.mu, variant.clone(), mu_density);
+                model.add_block(
+                    "theta",
+                    |s: &mut State| &mut s.theta[..],
+                    variant,
+                    theta_density,
+                );
+            }
+            model.run(n_sweeps);
+            *evaluations = counter.get();
+            for name in ["mu", "theta"] {
+                for draw in &model.draws(name).unwrap()[500..] {
+                    second_moments[usize::from(remembered)] +=
+                        draw.iter().map(|x| x * x).sum::<f64>();
+                }
+            }
+        }
+        let n = (3 * (n_sweeps - 500)) as f64;
+        for second_moment in second_moments {
+            assert!((second_moment / n / 10_000.0 - 1.0).abs() < 0.1);
+        }
+        assert!(10 * evaluations[1] < evaluations[0]);
+    }
+
+    #[test]
+    fn test_semi_conjugate_normal() {
+        // y_i ~ N(mu, 1/tau) with mu ~ N(0, 1) updated directly and tau ~ Gamma(2, 1) by slice
+        // sampling, checked against a direct Gibbs sampler's posterior mean of mu
+        struct Data {
+            y: Vec<f64>,
+            mu: f64,
+            tau: f64,
+        }
+        let y = vec![1.2, 0.8, 1.9, 1.4, 0.6, 1.1];
+        let n = y.len() as f64;
+        let sum: f64 = y.iter().sum();
+        let state = Data {
+            y,
+            mu: 0.0,
+            tau: 1.0,
+        };
+        let mut model = Model::with_seed(state, 61);
+        model.add_direct_scalar(
+            "mu",
+            |s: &mut Data| &mut s.mu,
+            move |s: &Data, rng: &mut fastrand::Rng| {
+                let precision = 1.0 + n * s.tau;
+                let mean = s.tau * sum / precision;
+                let z = crate::diagnostics::inverse_normal_cdf(rng.f64());
+                mean + z / precision.sqrt()
+            },
+        );
+        model.add_scalar(
+            "tau",
+            |s: &mut Data| &mut s.tau,
+            SliceVariant::SteppingOut(TuningParameters::new()),
+            |s: &Data| {
+                if s.tau <= 0.0 {
+                    return f64::NEG_INFINITY;
+                }
+                let ss: f64 = s.y.iter().map(|y| (y - s.mu) * (y - s.mu)).sum();
+                (1.0 + 0.5 * s.y.len() as f64) * s.tau.ln() - s.tau - 0.5 * s.tau * ss
+            },
+        );
+        let n_sweeps = 50_000;
+        model.run(n_sweeps);
+        let mu = model.draws("mu").unwrap();
+        let mean = mu.iter().map(|x| x[0]).sum::<f64>() / (n_sweeps as f64);
+        let tau = model.draws("tau").unwrap();
+        assert!(tau.iter().all(|x| x[0] > 0.0));
+        assert!((mean - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_covariance_block() {
+        // Wishart(5, I) on 2 x 2 covariance matrices, with mean 5 I
+        let mut model = Model::with_seed(vec![1.0, 0.0, 0.0, 1.0], 97);
+        model.add_transformed_block(
+            "sigma",
+            |s: &mut Vec<f64>| &mut s[..],
+            crate::transforms::CholeskyCovariance { dimension: 2 },
+            SliceVariant::SteppingOut(TuningParameters::new()),
+            |s: &Vec<f64>| {
+                let determinant = s[0] * s[3] - s[1] * s[2];
+                0.5 * (5.0 - 2.0 - 1.0) * determinant.ln() - 0.5 * (s[0] + s[3])
+            },
+        );
+        let n_sweeps = 50_000;
+        model.run(n_sweeps);
+        let sigma = model.draws("sigma").unwrap();
+        let mean = |k: usize| sigma.iter().map(|x| x[k]).sum::<f64>() / (n_sweeps as f64);
+        assert!((mean(0) - 5.0).abs() < 0.25);
+        assert!((mean(3) - 5.0).abs() < 0.25);
+        assert!(mean(1).abs() < 0.25);
+        assert!(sigma
+            .iter()
+            .all(|x| x[1] == x[2] && x[0] * x[3] > x[1] * x[2]));
+    }
+
+    #[test]
+    fn test_ordered_mixture_means() {
+        // Equal-weight mixture of N(mu_1, 1) and N(mu_2, 1) with mu_1 < mu_2, which avoids label
+        // switching between the components
+        let data: Vec<f64> = (0..40)
+            .map(|i| if i % 2 == 0 { -2.0 } else { 2.0 } + 0.1 * ((i % 7) as f64 - 3.0))
+            .collect();
+        let mut model = Model::with_seed(vec![-0.1, 0.1], 101);
+        model.add_transformed_block(
+            "mu",
+            |s: &mut Vec<f64>| &mut s[..],
+            crate::transforms::Ordered { dimension: 2 },
+            SliceVariant::SteppingOut(TuningParameters::new()),
+            move |mu: &Vec<f64>| {
+                let prior = -0.5 * (mu[0] * mu[0] + mu[1] * mu[1]) / 100.0;
+                prior
+                    + data
+                        .iter()
+                        .map(|y| {
+                            let a = -0.5 * (y - mu[0]) * (y - mu[0]);
+                            let b = -0.5 * (y - mu[1]) * (y - mu[1]);
+                            a.max(b) + (-(a - b).abs()).exp().ln_1p()
+                        })
+                        .sum::<f64>()
+            },
+        );
+        let n_sweeps = 20_000;
+        model.run(n_sweeps);
+        let mu = model.draws("mu").unwrap();
+        assert!(mu.iter().all(|x| x[0] < x[1]));
+        let mean = |k: usize| mu.iter().map(|x| x[k]).sum::<f64>() / (n_sweeps as f64);
+        assert!((mean(0) + 2.0).abs() < 0.2);
+        assert!((mean(1) - 2.0).abs() < 0.2);
+    }
+}
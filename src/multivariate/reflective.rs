@@ -0,0 +1,133 @@
+use crate::diagnostics::inverse_normal_cdf;
+use crate::error::TuningError;
+use crate::math::default_rng;
+use crate::target::MultivariateTargetWithGradient;
+
+// Neal's (2003, Section 7) reflective slice sampler with outside reflection. From the current
+// state x, a momentum drawn from the standard normal distribution moves the position in
+// n_steps steps of size step_size. Whenever a step lands outside the slice, the momentum is
+// reflected off the contour of the log density using its gradient at that point, and the
+// trajectory continues from there. The endpoint is accepted if it is inside the slice and
+// otherwise x is kept. Returns the number of evaluations of the target and of its gradient, or an
+// error if the step size is not finite and positive.
+pub fn reflective_slice_sampler<T: MultivariateTargetWithGradient + ?Sized>(
+    x: &mut [f64],
+    target: &mut T,
+    step_size: f64,
+    n_steps: usize,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<u64, TuningError> {
+    if !(step_size.is_finite() && step_size > 0.0) {
+        return Err(TuningError::InvalidStepSize(step_size));
+    }
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let log_density = |target: &mut T, x: &[f64]| {
+        let fx = target.evaluate(x);
+        if on_log_scale {
+            fx
+        } else {
+            fx.ln()
+        }
+    };
+    // Step 1 (slice)
+    let y = log_density(target, x) + rng.f64().ln();
+    let mut evaluations = 1;
+    // Step 2 (trajectory with reflections)
+    let mut momentum: Vec<f64> = x.iter().map(|_| inverse_normal_cdf(rng.f64())).collect();
+    let mut position = x.to_vec();
+    let mut gradient = vec![0.0; x.len()];
+    let mut inside = true;
+    for _ in 0..n_steps {
+        for (z, p) in position.iter_mut().zip(&momentum) {
+            *z += step_size * p;
+        }
+        evaluations += 1;
+        let fz = log_density(target, &position);
+        inside = y < fz;
+        if !inside {
+            target.gradient(&position, &mut gradient);
+            evaluations += 1;
+            let squared_norm: f64 = gradient.iter().map(|g| g * g).sum();
+            if squared_norm > 0.0 && squared_norm.is_finite() {
+                let scale = 2.0
+                    * momentum
+                        .iter()
+                        .zip(&gradient)
+                        .map(|(p, g)| p * g)
+                        .sum::<f64>()
+                    / squared_norm;
+                for (p, g) in momentum.iter_mut().zip(&gradient) {
+                    *p -= scale * g;
+                }
+            }
+        }
+    }
+    // Step 3 (acceptance of the endpoint)
+    if inside && position.iter().all(|z| z.is_finite()) {
+        x.copy_from_slice(&position);
+    }
+    Ok(evaluations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::MultivariateTarget;
+    use crate::targets::CorrelatedNormal;
+
+    #[test]
+    fn test_correlated_normal() {
+        let mut target = CorrelatedNormal { correlation: 0.9 };
+        let mut rng = Some(fastrand::Rng::with_seed(139));
+        let mut x = [0.0; 2];
+        let (mut sum, mut sum_of_squares, mut sum_of_products) = (0.0, 0.0, 0.0);
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            reflective_slice_sampler(&mut x, &mut target, 0.2, 10, &mut rng).unwrap();
+            sum += x[1];
+            sum_of_squares += x[1] * x[1];
+            sum_of_products += x[0] * x[1];
+        }
+        let n = n_samples as f64;
+        assert!((sum / n).abs() < 0.05);
+        assert!((sum_of_squares / n - 1.0).abs() < 0.05);
+        assert!((sum_of_products / n - 0.9).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let mut target = CorrelatedNormal { correlation: 0.5 };
+        let x = [0.3, -1.2];
+        let mut gradient = [0.0; 2];
+        target.gradient(&x, &mut gradient);
+        let h = 1e-6;
+        for i in 0..2 {
+            let mut up = x;
+            let mut down = x;
+            up[i] += h;
+            down[i] -= h;
+            let numerical = (target.evaluate(&up) - target.evaluate(&down)) / (2.0 * h);
+            assert!((gradient[i] - numerical).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_invalid_step_size() {
+        let mut target = CorrelatedNormal { correlation: 0.5 };
+        let mut x = [0.0; 2];
+        for step_size in [0.0, -0.1, f64::INFINITY] {
+            assert_eq!(
+                reflective_slice_sampler(&mut x, &mut target, step_size, 10, &mut None),
+                Err(TuningError::InvalidStepSize(step_size))
+            );
+        }
+    }
+}
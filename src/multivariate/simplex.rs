@@ -0,0 +1,129 @@
+use crate::diagnostics::inverse_normal_cdf;
+use crate::math::default_rng;
+use crate::target::MultivariateTarget;
+use crate::transforms::{Simplex, VectorTransform};
+use crate::univariate::stepping_out::{self, univariate_slice_sampler_stepping_out_and_shrinkage};
+
+// How the unconstrained (stick-breaking) coordinates of a probability vector are updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimplexUpdate {
+    // Each stick in turn, holding the others fixed.
+    OneStickAtATime,
+    // All sticks at once along a direction drawn uniformly from the unit sphere.
+    RandomDirection,
+}
+
+// Updates the probability vector x (nonnegative and summing to one) for a target density on the
+// simplex by slice sampling its stick-breaking coordinates, including the Jacobian of the
+// transform, with stepping out and shrinkage. Returns the number of target evaluations.
+pub fn simplex_slice_sampler<T: MultivariateTarget + ?Sized>(
+    x: &mut [f64],
+    target: &mut T,
+    update: SimplexUpdate,
+    tuning_parameters: &stepping_out::TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> u64 {
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let transform = Simplex { dimension: x.len() };
+    let on_log_scale = target.on_log_scale();
+    let mut y = vec![0.0; transform.unconstrained_dimension()];
+    transform.unconstrain(x, &mut y);
+    let mut z = y.clone();
+    let mut log_density = |z: &[f64], x: &mut [f64]| {
+        let log_jacobian = transform.constrain(z, x);
+        let fx = target.evaluate(x);
+        let log_density = if on_log_scale { fx } else { fx.ln() };
+        if log_density == f64::NEG_INFINITY {
+            log_density
+        } else {
+            log_density + log_jacobian
+        }
+    };
+    let mut evaluations = 0;
+    match update {
+        SimplexUpdate::OneStickAtATime => {
+            for i in 0..y.len() {
+                z.copy_from_slice(&y);
+                let evaluation_counter;
+                (y[i], evaluation_counter) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                    y[i],
+                    |yi| {
+                        z[i] = yi;
+                        log_density(&z, x)
+                    },
+                    true,
+                    tuning_parameters,
+                    rng,
+                );
+                evaluations += evaluation_counter;
+            }
+        }
+        SimplexUpdate::RandomDirection => {
+            let generator = rng.get_or_insert_with(default_rng);
+            let mut direction: Vec<f64> = y
+                .iter()
+                .map(|_| inverse_normal_cdf(generator.f64()))
+                .collect();
+            let norm = direction.iter().map(|d| d * d).sum::<f64>().sqrt();
+            direction.iter_mut().for_each(|d| *d /= norm);
+            let (t, evaluation_counter) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                0.0,
+                |t| {
+                    for ((zi, yi), di) in z.iter_mut().zip(&y).zip(&direction) {
+                        *zi = yi + t * di;
+                    }
+                    log_density(&z, x)
+                },
+                true,
+                tuning_parameters,
+                rng,
+            );
+            for (yi, di) in y.iter_mut().zip(&direction) {
+                *yi += t * di;
+            }
+            evaluations += evaluation_counter;
+        }
+    }
+    transform.constrain(&y, x);
+    evaluations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::Dirichlet;
+
+    #[test]
+    fn test_dirichlet() {
+        let tuning_parameters = stepping_out::TuningParameters::new();
+        for update in [
+            SimplexUpdate::OneStickAtATime,
+            SimplexUpdate::RandomDirection,
+        ] {
+            let mut target = Dirichlet {
+                alpha: vec![1.0, 2.0, 3.0, 4.0],
+            };
+            let mut rng = Some(fastrand::Rng::with_seed(89));
+            let mut x = vec![0.25; 4];
+            let mut sums = [0.0; 4];
+            let n_samples = 50_000;
+            for _ in 0..n_samples {
+                simplex_slice_sampler(&mut x, &mut target, update, &tuning_parameters, &mut rng);
+                assert!((x.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+                for (sum, xi) in sums.iter_mut().zip(&x) {
+                    *sum += xi;
+                }
+            }
+            for (sum, mean) in sums.iter().zip([0.1, 0.2, 0.3, 0.4]) {
+                assert!((sum / (n_samples as f64) - mean).abs() < 0.01);
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::chain::Chain;
+use std::io::{self, Write};
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 400.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+// Lines with more points are drawn through every k-th point to keep the files small.
+const MAX_POINTS: usize = 2_000;
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+// Line plot of several series of (x, y) points with a shared pair of axes, rendered as SVG.
+struct Plot<'a> {
+    title: String,
+    x_label: &'a str,
+    y_label: &'a str,
+    series: Vec<Vec<(f64, f64)>>,
+}
+
+impl Plot<'_> {
+    fn range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+        let (min, max) = values
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), v| {
+                (a.min(v), b.max(v))
+            });
+        if min > max {
+            (0.0, 1.0)
+        } else if min == max {
+            (min - 0.5, max + 0.5)
+        } else {
+            (min, max)
+        }
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let points = || self.series.iter().flatten();
+        let (x_min, x_max) = Self::range(points().map(|p| p.0));
+        let (y_min, y_max) = Self::range(points().map(|p| p.1));
+        let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        let sx = |x: f64| MARGIN_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+        let sy = |y: f64| MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#
+        )?;
+        writeln!(writer, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+        writeln!(
+            writer,
+            r#"<text x="{}" y="24" text-anchor="middle" font-size="15">{}</text>"#,
+            WIDTH / 2.0,
+            escape(&self.title)
+        )?;
+        writeln!(
+            writer,
+            r#"<rect x="{MARGIN_LEFT}" y="{MARGIN_TOP}" width="{plot_width}" height="{plot_height}" fill="none" stroke="black"/>"#
+        )?;
+        for k in 0..=4 {
+            let fraction = (k as f64) / 4.0;
+            let x = x_min + fraction * (x_max - x_min);
+            let y = y_min + fraction * (y_max - y_min);
+            writeln!(
+                writer,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                sx(x),
+                HEIGHT - MARGIN_BOTTOM + 16.0,
+                tick_label(x)
+            )?;
+            writeln!(
+                writer,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#,
+                MARGIN_LEFT - 6.0,
+                sy(y) + 4.0,
+                tick_label(y)
+            )?;
+        }
+        writeln!(
+            writer,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            MARGIN_LEFT + plot_width / 2.0,
+            HEIGHT - 12.0,
+            escape(self.x_label)
+        )?;
+        writeln!(
+            writer,
+            r#"<text x="16" y="{0}" text-anchor="middle" transform="rotate(-90 16 {0})">{1}</text>"#,
+            MARGIN_TOP + plot_height / 2.0,
+            escape(self.y_label)
+        )?;
+        for (i, series) in self.series.iter().enumerate() {
+            let stride = series.len().div_ceil(MAX_POINTS).max(1);
+            write!(
+                writer,
+                r#"<polyline fill="none" stroke-width="1" stroke="{}" points=""#,
+                COLORS[i % COLORS.len()]
+            )?;
+            for &(x, y) in series.iter().step_by(stride).filter(|p| p.1.is_finite()) {
+                write!(writer, "{:.1},{:.1} ", sx(x), sy(y))?;
+            }
+            writeln!(writer, r#""/>"#)?;
+        }
+        writeln!(writer, "</svg>")?;
+        writer.flush()
+    }
+}
+
+fn tick_label(value: f64) -> String {
+    if value != 0.0 && (value.abs() >= 1e4 || value.abs() < 1e-2) {
+        format!("{:.2e}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Writes a trace plot (draws against their index, one line per chain) of the named parameter as
+// SVG.
+pub fn write_trace_svg<W: Write>(writer: W, name: &str, chains: &[Chain]) -> io::Result<()> {
+    let series = chains
+        .iter()
+        .map(|chain| {
+            chain
+                .draws
+                .iter()
+                .enumerate()
+                .map(|(t, &x)| (t as f64, x))
+                .collect()
+        })
+        .collect();
+    Plot {
+        title: format!("Trace of {}", name),
+        x_label: "draw",
+        y_label: name,
+        series,
+    }
+    .write(writer)
+}
+
+// Writes the running mean of the named parameter in each chain against the draw index as SVG,
+// which should settle to a common value if the chains have converged.
+pub fn write_running_mean_svg<W: Write>(writer: W, name: &str, chains: &[Chain]) -> io::Result<()> {
+    let series = chains
+        .iter()
+        .map(|chain| {
+            let mut sum = 0.0;
+            chain
+                .draws
+                .iter()
+                .enumerate()
+                .map(|(t, &x)| {
+                    sum += x;
+                    (t as f64, sum / ((t + 1) as f64))
+                })
+                .collect()
+        })
+        .collect();
+    Plot {
+        title: format!("Running mean of {}", name),
+        x_label: "draw",
+        y_label: "mean",
+        series,
+    }
+    .write(writer)
+}
+
+// Writes the kernel density estimate of the marginal of the named parameter in each chain (see
+// Chain::density) as SVG, on n_grid points spanning the pooled draws.
+pub fn write_density_svg<W: Write>(
+    writer: W,
+    name: &str,
+    chains: &[Chain],
+    n_grid: usize,
+) -> io::Result<()> {
+    let (min, max) = Plot::range(chains.iter().flat_map(|chain| chain.draws.iter().copied()));
+    let step = (max - min) / ((n_grid.max(2) - 1) as f64);
+    let grid: Vec<f64> = (0..n_grid.max(2))
+        .map(|k| min + (k as f64) * step)
+        .collect();
+    let series = chains
+        .iter()
+        .filter(|chain| chain.len() >= 2)
+        .map(|chain| {
+            grid.iter()
+                .copied()
+                .zip(chain.density(&grid))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Plot {
+        title: format!("Density of {}", name),
+        x_label: name,
+        y_label: "density",
+        series,
+    }
+    .write(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_plots() {
+        let mut rng = fastrand::Rng::with_seed(43);
+        let chains: Vec<Chain> = (0..2)
+            .map(|_| Chain {
+                draws: (0..5_000).map(|_| rng.f64()).collect(),
+                ..Default::default()
+            })
+            .collect();
+        let mut trace = Vec::new();
+        write_trace_svg(&mut trace, "x<1>", &chains).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.starts_with("<svg") && trace.trim_end().ends_with("</svg>"));
+        assert_eq!(trace.matches("<polyline").count(), 2);
+        assert!(trace.contains("Trace of x&lt;1&gt;"));
+        // Each line is drawn through at most MAX_POINTS points
+        let points = trace
+            .split("points=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        assert!(points.split_whitespace().count() <= MAX_POINTS);
+        let mut running_mean = Vec::new();
+        write_running_mean_svg(&mut running_mean, "x", &chains).unwrap();
+        let mut density = Vec::new();
+        write_density_svg(&mut density, "x", &chains, 100).unwrap();
+        let density = String::from_utf8(density).unwrap();
+        let points = density
+            .split("points=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        assert_eq!(points.split_whitespace().count(), 100);
+        let mut empty = Vec::new();
+        write_density_svg(&mut empty, "x", &[Chain::default()], 100).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+use crate::adaptation::WarmupAdapter;
+use crate::checkpoint::Checkpoint;
+use crate::efficiency::EfficiencyReport;
+use crate::univariate::doubling::{self, doubling_and_shrinkage_with_interval};
+use crate::univariate::shrinkage::shrinkage_with_interval;
+use crate::univariate::stepping_out::{self, stepping_out_and_shrinkage_with_interval};
+use crate::univariate::Phases;
+
+// Cost and geometry of one update: its target evaluations, the width of the interval from which
+// the accepted point was drawn (after expansion and shrinkage), and the slice level, which is on
+// the scale of the target (log or natural).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpdateMetadata {
+    pub evaluations: u64,
+    pub width: f64,
+    pub slice_level: f64,
+}
+
+// Seed of the given stream derived from a seed (by the SplitMix64 finalizer), so that the streams
+// of one seed are effectively independent of each other, e.g., one per chain, or one per draw so
+// that draw t of two runs uses the same uniforms however many the earlier draws consumed.
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Stateful sampler owning its random number generator, so that a run can be reproduced from
+// a seed and the generator state can be saved and later restored to resume a run exactly. The
+// sampler also counts its updates, remembers the latest state, and optionally adapts the width
+// of the stepping out and doubling procedures during warmup; all of this is captured by a
+// checkpoint. The cost of the draws, broken down by phase, is accumulated in an efficiency
+// report, which is not part of a checkpoint.
+#[derive(Debug)]
+pub struct Sampler {
+    rng: Option<fastrand::Rng>,
+    iteration: u64,
+    state: Option<f64>,
+    adapter: Option<WarmupAdapter>,
+    report: EfficiencyReport,
+    last_update: Option<UpdateMetadata>,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: Some(fastrand::Rng::with_seed(seed)),
+            ..Default::default()
+        }
+    }
+    // Sampler on the given stream of the seed (see stream_seed), e.g., for the chains of a run.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        Self::with_seed(stream_seed(seed, stream))
+    }
+    pub fn with_warmup_adapter(self, adapter: WarmupAdapter) -> Self {
+        Self {
+            adapter: Some(adapter),
+            ..self
+        }
+    }
+    pub fn rng_state(&self) -> u64 {
+        self.rng.as_ref().map_or(0, |rng| rng.get_seed())
+    }
+    pub fn restore_rng_state(&mut self, state: u64) {
+        self.rng = Some(fastrand::Rng::with_seed(state));
+    }
+    pub fn rng(&mut self) -> &mut fastrand::Rng {
+        self.rng.get_or_insert_with(fastrand::Rng::new)
+    }
+    pub fn iteration(&self) -> u64 {
+        self.iteration
+    }
+    pub fn state(&self) -> Option<f64> {
+        self.state
+    }
+    pub fn adapter(&self) -> Option<&WarmupAdapter> {
+        self.adapter.as_ref()
+    }
+    pub fn efficiency_report(&self) -> &EfficiencyReport {
+        &self.report
+    }
+    // Metadata of the latest update, if any, which is not part of a checkpoint.
+    pub fn last_update(&self) -> Option<UpdateMetadata> {
+        self.last_update
+    }
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            rng_state: self.rng_state(),
+            iteration: self.iteration,
+            state: self.state,
+            adapter: self.adapter.clone(),
+        }
+    }
+    pub fn resume(checkpoint: Checkpoint) -> Self {
+        Self {
+            rng: Some(fastrand::Rng::with_seed(checkpoint.rng_state)),
+            iteration: checkpoint.iteration,
+            state: checkpoint.state,
+            adapter: checkpoint.adapter,
+            report: EfficiencyReport::new(),
+            last_update: None,
+        }
+    }
+    fn record(&mut self, draw: (f64, u64), phases: Phases) -> (f64, u64) {
+        self.iteration += 1;
+        self.state = Some(draw.0);
+        self.last_update = Some(UpdateMetadata {
+            evaluations: draw.1,
+            width: phases.final_interval.1 - phases.final_interval.0,
+            slice_level: phases.slice_level,
+        });
+        self.report.evaluations.record(draw.1);
+        self.report
+            .expansion_evaluations
+            .record(phases.expansion_evaluations);
+        self.report
+            .shrinkage_rejections
+            .record(phases.shrinkage_rejections);
+        draw
+    }
+    pub fn stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &stepping_out::TuningParameters,
+    ) -> (f64, u64) {
+        let mut phases = Phases::default();
+        let draw = match &mut self.adapter {
+            Some(adapter) => adapter.stepping_out_and_shrinkage_with_phases(
+                x,
+                f,
+                on_log_scale,
+                tuning_parameters,
+                &mut phases,
+                &mut self.rng,
+            ),
+            None => {
+                let (x1, evaluation_counter, _) = stepping_out_and_shrinkage_with_interval(
+                    x,
+                    f,
+                    on_log_scale,
+                    tuning_parameters.width(),
+                    tuning_parameters,
+                    &mut phases,
+                    &mut self.rng,
+                );
+                (x1, evaluation_counter)
+            }
+        };
+        self.record(draw, phases)
+    }
+    pub fn doubling_and_shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        tuning_parameters: &doubling::TuningParameters,
+    ) -> (f64, u64) {
+        let mut phases = Phases::default();
+        let draw = match &mut self.adapter {
+            Some(adapter) => adapter.doubling_and_shrinkage_with_phases(
+                x,
+                f,
+                on_log_scale,
+                tuning_parameters,
+                &mut phases,
+                &mut self.rng,
+            ),
+            None => {
+                let (x1, evaluation_counter, _) = doubling_and_shrinkage_with_interval(
+                    x,
+                    f,
+                    on_log_scale,
+                    tuning_parameters.width(),
+                    tuning_parameters,
+                    &mut phases,
+                    &mut self.rng,
+                );
+                (x1, evaluation_counter)
+            }
+        };
+        self.record(draw, phases)
+    }
+    pub fn shrinkage<S: FnMut(f64) -> f64>(
+        &mut self,
+        x: f64,
+        f: S,
+        on_log_scale: bool,
+        left: f64,
+        right: f64,
+    ) -> (f64, u64) {
+        let mut phases = Phases::default();
+        let (x1, evaluation_counter, _) =
+            shrinkage_with_interval(x, f, on_log_scale, left, right, &mut phases, &mut self.rng);
+        self.record((x1, evaluation_counter), phases)
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            rng: Some(fastrand::Rng::new()),
+            iteration: 0,
+            state: None,
+            adapter: None,
+            report: EfficiencyReport::new(),
+            last_update: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normal(x: f64) -> f64 {
+        -0.5 * x * x
+    }
+
+    #[test]
+    fn test_seed_reproducibility() {
+        let tuning_parameters = stepping_out::TuningParameters::builder()
+            .width(1.)
+            .build()
+            .unwrap();
+        let mut sampler1 = Sampler::with_seed(42);
+        let mut sampler2 = Sampler::with_seed(42);
+        let mut x1 = 0.0;
+        let mut x2 = 0.0;
+        for _ in 0..1_000 {
+            (x1, _) = sampler1.stepping_out_and_shrinkage(x1, normal, true, &tuning_parameters);
+            (x2, _) = sampler2.stepping_out_and_shrinkage(x2, normal, true, &tuning_parameters);
+            assert_eq!(x1, x2);
+        }
+    }
+
+    #[test]
+    fn test_streams() {
+        let streams: Vec<u64> = (0..1_000).map(|stream| stream_seed(42, stream)).collect();
+        let mut distinct = streams.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), streams.len());
+        assert_ne!(stream_seed(42, 0), stream_seed(43, 0));
+        assert_eq!(
+            Sampler::with_stream(42, 3).rng_state(),
+            Sampler::with_seed(stream_seed(42, 3)).rng_state()
+        );
+    }
+
+    #[test]
+    fn test_restore_rng_state() {
+        let tuning_parameters = doubling::TuningParameters::builder()
+            .width(1.)
+            .build()
+            .unwrap();
+        let mut sampler = Sampler::with_seed(7);
+        let mut x = 0.0;
+        for _ in 0..100 {
+            (x, _) = sampler.doubling_and_shrinkage(x, normal, true, &tuning_parameters);
+        }
+        let state = sampler.rng_state();
+        let checkpoint = x;
+        let mut draws = Vec::new();
+        for _ in 0..100 {
+            (x, _) = sampler.doubling_and_shrinkage(x, normal, true, &tuning_parameters);
+            draws.push(x);
+        }
+        let mut resumed = Sampler::new();
+        resumed.restore_rng_state(state);
+        let mut x = checkpoint;
+        for draw in draws {
+            (x, _) = resumed.doubling_and_shrinkage(x, normal, true, &tuning_parameters);
+            assert_eq!(x, draw);
+        }
+    }
+
+    #[test]
+    fn test_efficiency_report() {
+        // A narrow width makes stepping out dominate, and a wide one makes shrinkage dominate
+        let mut x = 0.0;
+        let mut expansion = Vec::new();
+        let mut rejections = Vec::new();
+        for width in [0.01, 100.0] {
+            let tuning_parameters = stepping_out::TuningParameters::builder()
+                .width(width)
+                .build()
+                .unwrap();
+            let mut sampler = Sampler::with_seed(61);
+            for _ in 0..1_000 {
+                (x, _) = sampler.stepping_out_and_shrinkage(x, normal, true, &tuning_parameters);
+            }
+            let report = sampler.efficiency_report();
+            assert_eq!(report.n_draws(), 1_000);
+            // Each draw evaluates the target once for the slice level and once when accepting
+            assert_eq!(
+                report.evaluations.total(),
+                2_000 + report.expansion_evaluations.total() + report.shrinkage_rejections.total()
+            );
+            expansion.push(report.expansion_evaluations.mean());
+            rejections.push(report.shrinkage_rejections.mean());
+        }
+        assert!(expansion[0] > 10.0 * expansion[1]);
+        assert!(rejections[1] > 10.0 * rejections[0]);
+    }
+}
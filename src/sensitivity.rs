@@ -0,0 +1,63 @@
+use crate::chain::{replay_chain, Chain};
+use crate::sampler::Sampler;
+
+// Change in posterior summaries induced by perturbing the target, where the perturbed chain
+// reuses the uniform stream of the baseline chain (common random numbers).
+#[derive(Debug, Clone)]
+pub struct SensitivityReport {
+    pub baseline_mean: f64,
+    pub perturbed_mean: f64,
+    pub baseline_standard_deviation: f64,
+    pub perturbed_standard_deviation: f64,
+    pub perturbed_chain: Chain,
+}
+
+impl SensitivityReport {
+    pub fn mean_change(&self) -> f64 {
+        self.perturbed_mean - self.baseline_mean
+    }
+    pub fn standard_deviation_change(&self) -> f64 {
+        self.perturbed_standard_deviation - self.baseline_standard_deviation
+    }
+}
+
+// Replays a stored chain under a kernel for the perturbed target (e.g., with a different prior)
+// and reports the change in the mean and standard deviation relative to the stored chain.
+pub fn sensitivity_analysis<K: FnMut(&mut Sampler, f64) -> (f64, u64)>(
+    chain: &Chain,
+    perturbed_kernel: K,
+) -> SensitivityReport {
+    let perturbed_chain = replay_chain(chain, perturbed_kernel);
+    SensitivityReport {
+        baseline_mean: chain.mean(),
+        perturbed_mean: perturbed_chain.mean(),
+        baseline_standard_deviation: chain.standard_deviation(),
+        perturbed_standard_deviation: perturbed_chain.standard_deviation(),
+        perturbed_chain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::run_chain;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_shifted_prior_mean() {
+        let tuning_parameters = TuningParameters::builder().width(2.).build().unwrap();
+        let kernel = |shift: f64| {
+            let tuning_parameters = &tuning_parameters;
+            move |sampler: &mut Sampler, x: f64| {
+                let f = |x: f64| -0.5 * (x - shift) * (x - shift);
+                sampler.stepping_out_and_shrinkage(x, f, true, tuning_parameters)
+            }
+        };
+        let chain = run_chain(0.0, 10_000, kernel(0.0), &mut Sampler::with_seed(11));
+        let report = sensitivity_analysis(&chain, kernel(0.0));
+        assert_eq!(report.mean_change(), 0.0);
+        let report = sensitivity_analysis(&chain, kernel(0.1));
+        assert!((report.mean_change() - 0.1).abs() < 0.02);
+        assert!(report.standard_deviation_change().abs() < 0.05);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::ais::check_schedule;
+use crate::error::TuningError;
+use crate::math::default_rng;
+use crate::target::LogDensity;
+use crate::univariate::variant::{sample, SliceVariant};
+
+// Particles are resampled when the effective sample size of their weights falls below this
+// fraction of the number of particles.
+const RESAMPLING_THRESHOLD: f64 = 0.5;
+
+// Weighted particles approximating the posterior from sequential Monte Carlo, with the estimate
+// of the log marginal likelihood, the number of times the particles were resampled, and the
+// total number of evaluations of the tempered targets and of the likelihood for the weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmcEstimate {
+    pub particles: Vec<f64>,
+    pub log_weights: Vec<f64>,
+    pub log_marginal_likelihood: f64,
+    pub n_resamplings: usize,
+    pub evaluations: u64,
+}
+
+impl SmcEstimate {
+    // Weights of the particles normalized to sum to one.
+    pub fn weights(&self) -> Vec<f64> {
+        normalize(&self.log_weights)
+    }
+
+    // Weighted mean of g over the particles.
+    pub fn expectation<G: FnMut(f64) -> f64>(&self, mut g: G) -> f64 {
+        self.weights()
+            .iter()
+            .zip(&self.particles)
+            .map(|(w, &x)| w * g(x))
+            .sum()
+    }
+}
+
+fn normalize(log_weights: &[f64]) -> Vec<f64> {
+    let max = log_weights
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+}
+
+// Systematic resampling: the indices of the particles selected by a single uniform offset.
+fn systematic_resample(weights: &[f64], rng: &mut fastrand::Rng) -> Vec<usize> {
+    let n = weights.len();
+    let offset = rng.f64();
+    let mut indices = Vec::with_capacity(n);
+    let mut cumulative = weights[0];
+    let mut j = 0;
+    for i in 0..n {
+        let u = (i as f64 + offset) / (n as f64);
+        while cumulative <= u && j < n - 1 {
+            j += 1;
+            cumulative += weights[j];
+        }
+        indices.push(j);
+    }
+    indices
+}
+
+// Sequential Monte Carlo sampler (Del Moral, Doucet & Jasra, 2006) moving n_particles prior
+// draws through the distributions proportional to prior(x) * likelihood(x)^lambda for the
+// schedule of lambda values, which must increase from 0 to 1. At each lambda the particles are
+// reweighted by the likelihood raised to the increment, resampled systematically when their
+// effective sample size is low, and then rejuvenated by n_updates updates of the slice variant,
+// which leave the current tempered distribution invariant. The prior must be normalized for the
+// marginal likelihood estimate.
+#[allow(clippy::too_many_arguments)]
+pub fn sequential_monte_carlo<P, L, D>(
+    schedule: &[f64],
+    n_particles: usize,
+    n_updates: usize,
+    mut log_prior: P,
+    mut log_likelihood: L,
+    mut draw_prior: D,
+    variant: &SliceVariant,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<SmcEstimate, TuningError>
+where
+    P: FnMut(f64) -> f64,
+    L: FnMut(f64) -> f64,
+    D: FnMut(&mut fastrand::Rng) -> f64,
+{
+    check_schedule(schedule)?;
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let mut particles: Vec<f64> = (0..n_particles)
+        .map(|_| draw_prior(rng.get_or_insert_with(default_rng)))
+        .collect();
+    let mut log_weights = vec![0.0; n_particles];
+    let mut log_marginal_likelihood = 0.0;
+    let mut n_resamplings = 0;
+    let mut evaluations = 0;
+    for pair in schedule.windows(2) {
+        let (previous, lambda) = (pair[0], pair[1]);
+        // Reweighting, where the increment of the log marginal likelihood is the log of the
+        // weighted mean of the incremental weights
+        let weights = normalize(&log_weights);
+        let mut increments = Vec::with_capacity(n_particles);
+        for (log_weight, &x) in log_weights.iter_mut().zip(&particles) {
+            let increment = (lambda - previous) * log_likelihood(x);
+            *log_weight += increment;
+            increments.push(increment);
+        }
+        evaluations += n_particles as u64;
+        let max = increments.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mean: f64 = weights
+            .iter()
+            .zip(&increments)
+            .map(|(w, increment)| w * (increment - max).exp())
+            .sum();
+        log_marginal_likelihood += max + mean.ln();
+        // Resampling
+        let weights = normalize(&log_weights);
+        let effective_sample_size = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+        if effective_sample_size < RESAMPLING_THRESHOLD * (n_particles as f64) {
+            let indices = systematic_resample(&weights, rng.get_or_insert_with(default_rng));
+            particles = indices.iter().map(|&i| particles[i]).collect();
+            log_weights.iter_mut().for_each(|w| *w = 0.0);
+            n_resamplings += 1;
+        }
+        // Rejuvenation
+        let mut target = LogDensity(|x| {
+            let lp = log_prior(x);
+            if lp == f64::NEG_INFINITY {
+                lp
+            } else {
+                lp + lambda * log_likelihood(x)
+            }
+        });
+        for x in particles.iter_mut() {
+            for _ in 0..n_updates {
+                let evaluation_counter;
+                (*x, evaluation_counter) = sample(variant, *x, &mut target, rng);
+                evaluations += evaluation_counter;
+            }
+        }
+    }
+    Ok(SmcEstimate {
+        particles,
+        log_weights,
+        log_marginal_likelihood,
+        n_resamplings,
+        evaluations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::inverse_normal_cdf;
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_normal_marginal_likelihood() {
+        // x ~ N(0, 1) and y | x ~ N(x, 1) with y = 1, so that y ~ N(0, 2) and the posterior is
+        // N(1 / 2, 1 / 2)
+        let schedule: Vec<f64> = (0..=20).map(|i| (i as f64) / 20.0).collect();
+        let mut rng = Some(fastrand::Rng::with_seed(107));
+        let estimate = sequential_monte_carlo(
+            &schedule,
+            2_000,
+            2,
+            |x| -0.5 * x * x - 0.5 * (2.0 * std::f64::consts::PI).ln(),
+            |x| -0.5 * (1.0 - x) * (1.0 - x) - 0.5 * (2.0 * std::f64::consts::PI).ln(),
+            |rng| inverse_normal_cdf(rng.f64()),
+            &SliceVariant::SteppingOut(TuningParameters::new()),
+            &mut rng,
+        )
+        .unwrap();
+        let truth = -0.5 * (4.0 * std::f64::consts::PI).ln() - 0.25;
+        assert_eq!(estimate.particles.len(), 2_000);
+        assert!((estimate.log_marginal_likelihood - truth).abs() < 0.05);
+        let mean = estimate.expectation(|x| x);
+        assert!((mean - 0.5).abs() < 0.05);
+        assert!((estimate.expectation(|x| x * x) - mean * mean - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_systematic_resample() {
+        let mut rng = fastrand::Rng::with_seed(109);
+        let indices = systematic_resample(&[0.0, 0.5, 0.0, 0.5], &mut rng);
+        assert_eq!(indices, vec![1, 1, 3, 3]);
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let result = sequential_monte_carlo(
+            &[0.0, 0.5],
+            1,
+            1,
+            |x| -x * x,
+            |x| -x * x,
+            |rng| rng.f64(),
+            &SliceVariant::SteppingOut(TuningParameters::new()),
+            &mut None,
+        );
+        assert_eq!(result, Err(TuningError::InvalidSchedule(0.5)));
+    }
+}
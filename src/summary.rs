@@ -0,0 +1,354 @@
+// Streaming estimate of a quantile by the P-squared algorithm of Jain and Chlamtac (1985), which
+// tracks five markers instead of storing the draws. Non-finite draws are ignored.
+#[derive(Debug, Clone)]
+pub struct P2Quantile {
+    probability: f64,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+    count: usize,
+}
+
+impl P2Quantile {
+    pub fn new(probability: f64) -> Self {
+        let p = probability;
+        Self {
+            probability,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+            count: 0,
+        }
+    }
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+    pub fn update(&mut self, x: f64) {
+        if !x.is_finite() {
+            return;
+        }
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+        let h = &mut self.heights;
+        let k = if x < h[0] {
+            h[0] = x;
+            0
+        } else if x >= h[4] {
+            h[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < h[i + 1]).unwrap()
+        };
+        for position in &mut self.positions[k + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments.iter()) {
+            *desired += increment;
+        }
+        for i in 1..4 {
+            let n = &self.positions;
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = h[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]));
+                h[i] = if h[i - 1] < parabolic && parabolic < h[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    h[i] + d * (h[j] - h[i]) / (n[j] - n[i])
+                };
+                self.positions[i] += d;
+            }
+        }
+    }
+    pub fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        let mut sorted = self.heights[..self.count].to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let index = (self.probability * ((self.count - 1) as f64)).round() as usize;
+        sorted[index]
+    }
+}
+
+// Summary statistics of a chain accumulated draw by draw without storing the chain: Welford's
+// mean and variance, the minimum and maximum, and P-squared estimates of the given quantiles.
+// Non-finite draws are counted separately and left out of the statistics.
+#[derive(Debug, Clone)]
+pub struct OnlineSummary {
+    count: u64,
+    non_finite_count: u64,
+    mean: f64,
+    sum_of_squares: f64,
+    min: f64,
+    max: f64,
+    quantiles: Vec<P2Quantile>,
+}
+
+impl OnlineSummary {
+    pub fn new(probabilities: &[f64]) -> Self {
+        Self {
+            count: 0,
+            non_finite_count: 0,
+            mean: 0.0,
+            sum_of_squares: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            quantiles: probabilities.iter().map(|&p| P2Quantile::new(p)).collect(),
+        }
+    }
+    pub fn update(&mut self, x: f64) {
+        if !x.is_finite() {
+            self.non_finite_count += 1;
+            return;
+        }
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / (self.count as f64);
+        self.sum_of_squares += delta * (x - self.mean);
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        for quantile in &mut self.quantiles {
+            quantile.update(x);
+        }
+    }
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    pub fn non_finite_count(&self) -> u64 {
+        self.non_finite_count
+    }
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+    // Sample variance, NaN with fewer than two draws.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        self.sum_of_squares / ((self.count - 1) as f64)
+    }
+    pub fn standard_deviation(&self) -> f64 {
+        self.variance().sqrt()
+    }
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+    pub fn quantiles(&self) -> Vec<f64> {
+        self.quantiles.iter().map(|q| q.estimate()).collect()
+    }
+}
+
+// Bandwidth of a Gaussian kernel density estimate: Silverman's rule of thumb, 0.9 min(sd, IQR /
+// 1.34) n^(-1/5), which oversmooths multimodal densities; the two-stage direct plug-in estimate
+// of Sheather and Jones (1991) as given by Wand and Jones (1995), which estimates the curvature
+// of the density from the draws (on a binned approximation, so that its cost does not grow with
+// the square of the number of draws); or a fixed value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bandwidth {
+    Silverman,
+    SheatherJones,
+    Fixed(f64),
+}
+
+impl Bandwidth {
+    // Bandwidth for the given draws, NaN if there are fewer than two.
+    pub fn value(&self, draws: &[f64]) -> f64 {
+        if let Bandwidth::Fixed(h) = self {
+            return *h;
+        }
+        let n = draws.len();
+        if n < 2 {
+            return f64::NAN;
+        }
+        let mut sorted = draws.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let quantile = |p: f64| {
+            let h = p * ((n - 1) as f64);
+            let lower = h.floor() as usize;
+            let upper = (lower + 1).min(n - 1);
+            sorted[lower] + (h - (lower as f64)) * (sorted[upper] - sorted[lower])
+        };
+        let mean = draws.iter().sum::<f64>() / (n as f64);
+        let sd = (draws.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ((n - 1) as f64)).sqrt();
+        let iqr = quantile(0.75) - quantile(0.25);
+        let scale = if iqr > 0.0 { sd.min(iqr / 1.349) } else { sd };
+        let n = n as f64;
+        match self {
+            Bandwidth::Silverman => 0.9 * scale * n.powf(-0.2),
+            _ => {
+                let (grid_step, counts) = linear_binning(&sorted, 401);
+                let root_pi = std::f64::consts::PI.sqrt();
+                let psi8 = 105.0 / (32.0 * root_pi * scale.powi(9));
+                let phi0 = 1.0 / (2.0 * std::f64::consts::PI).sqrt();
+                let g1 = (30.0 * phi0 / (psi8 * n)).powf(1.0 / 9.0);
+                let psi6 = binned_functional(&counts, grid_step, g1, n, 6);
+                let g2 = (-6.0 * phi0 / (psi6 * n)).powf(1.0 / 7.0);
+                let psi4 = binned_functional(&counts, grid_step, g2, n, 4);
+                (1.0 / (2.0 * root_pi * psi4 * n)).powf(0.2)
+            }
+        }
+    }
+}
+
+// Counts of the sorted draws linearly binned onto m equally spaced grid points spanning them,
+// with the spacing of the grid.
+fn linear_binning(sorted: &[f64], m: usize) -> (f64, Vec<f64>) {
+    let (a, b) = (sorted[0], sorted[sorted.len() - 1]);
+    let step = (b - a) / ((m - 1) as f64);
+    let mut counts = vec![0.0; m];
+    if step == 0.0 {
+        counts[0] = sorted.len() as f64;
+        return (step, counts);
+    }
+    for x in sorted {
+        let position = ((x - a) / step).min((m - 1) as f64);
+        let k = (position.floor() as usize).min(m - 2);
+        let fraction = position - (k as f64);
+        counts[k] += 1.0 - fraction;
+        counts[k + 1] += fraction;
+    }
+    (step, counts)
+}
+
+// Estimate of the density functional psi_r (the mean of the r-th derivative of the density at the
+// draws) for r = 4 or 6, with a Gaussian kernel of bandwidth g on binned counts.
+fn binned_functional(counts: &[f64], step: f64, g: f64, n: f64, r: i32) -> f64 {
+    let phi = |z: f64| (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    let derivative = |z: f64| {
+        let z2 = z * z;
+        let hermite = if r == 4 {
+            z2 * z2 - 6.0 * z2 + 3.0
+        } else {
+            z2 * z2 * z2 - 15.0 * z2 * z2 + 45.0 * z2 - 15.0
+        };
+        hermite * phi(z)
+    };
+    // The kernel depends only on the difference of the bin indices
+    let m = counts.len();
+    let kernel: Vec<f64> = (0..m).map(|d| derivative((d as f64) * step / g)).collect();
+    let mut sum = 0.0;
+    for (k, ck) in counts.iter().enumerate() {
+        if *ck == 0.0 {
+            continue;
+        }
+        for (l, cl) in counts.iter().enumerate() {
+            sum += ck * cl * kernel[k.abs_diff(l)];
+        }
+    }
+    sum / (n * n * g.powi(r + 1))
+}
+
+// Gaussian kernel density estimate from the draws at each point of the grid.
+pub fn kernel_density(draws: &[f64], grid: &[f64], bandwidth: Bandwidth) -> Vec<f64> {
+    let h = bandwidth.value(draws);
+    let normalizer = 1.0 / ((draws.len() as f64) * h * (2.0 * std::f64::consts::PI).sqrt());
+    grid.iter()
+        .map(|t| {
+            draws
+                .iter()
+                .map(|x| {
+                    let z = (t - x) / h;
+                    (-0.5 * z * z).exp()
+                })
+                .sum::<f64>()
+                * normalizer
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bandwidth() {
+        // For normal draws the Sheather-Jones bandwidth is close to the asymptotically optimal
+        // (4 / 3)^(1/5) n^(-1/5), and for well separated modes it is much smaller than
+        // Silverman's, which the overall spread inflates
+        let mut rng = fastrand::Rng::with_seed(37);
+        let n = 10_000;
+        let normal: Vec<f64> = (0..n)
+            .map(|_| crate::diagnostics::inverse_normal_cdf(rng.f64()))
+            .collect();
+        let optimal = (4.0f64 / 3.0).powf(0.2) * (n as f64).powf(-0.2);
+        let h = Bandwidth::SheatherJones.value(&normal);
+        assert!((h / optimal - 1.0).abs() < 0.15);
+        let bimodal: Vec<f64> = normal
+            .iter()
+            .enumerate()
+            .map(|(i, x)| if i % 2 == 0 { x - 5.0 } else { x + 5.0 })
+            .collect();
+        let h = Bandwidth::SheatherJones.value(&bimodal);
+        assert!(h < 0.5 * Bandwidth::Silverman.value(&bimodal));
+        assert!((h / optimal - 1.0).abs() < 0.3);
+        assert_eq!(Bandwidth::Fixed(0.3).value(&normal), 0.3);
+        assert!(Bandwidth::Silverman.value(&[1.0]).is_nan());
+    }
+
+    #[test]
+    fn test_online_summary_uniform() {
+        let mut rng = fastrand::Rng::with_seed(31);
+        let mut summary = OnlineSummary::new(&[0.05, 0.5, 0.9]);
+        for _ in 0..100_000 {
+            summary.update(rng.f64());
+        }
+        assert_eq!(summary.count(), 100_000);
+        assert!((summary.mean() - 0.5).abs() < 0.01);
+        assert!((summary.variance() - 1.0 / 12.0).abs() < 0.002);
+        assert!(summary.min() >= 0.0 && summary.min() < 0.001);
+        assert!(summary.max() < 1.0 && summary.max() > 0.999);
+        for (estimate, p) in summary.quantiles().iter().zip([0.05, 0.5, 0.9]) {
+            assert!((estimate - p).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_few_draws() {
+        let mut summary = OnlineSummary::new(&[0.5]);
+        assert!(summary.variance().is_nan());
+        summary.update(3.0);
+        assert!(summary.variance().is_nan());
+        assert!(summary.standard_deviation().is_nan());
+        for x in [1.0, 2.0] {
+            summary.update(x);
+        }
+        assert_eq!(summary.mean(), 2.0);
+        assert_eq!(summary.variance(), 1.0);
+        assert_eq!(summary.quantiles(), vec![2.0]);
+    }
+
+    #[test]
+    fn test_non_finite_draws() {
+        let mut summary = OnlineSummary::new(&[0.5]);
+        for i in 0..10 {
+            summary.update(i as f64);
+            summary.update(f64::NAN);
+        }
+        summary.update(f64::INFINITY);
+        assert_eq!(summary.count(), 10);
+        assert_eq!(summary.non_finite_count(), 11);
+        assert_eq!(summary.mean(), 4.5);
+        assert_eq!(summary.max(), 9.0);
+        assert!(summary.quantiles()[0].is_finite());
+    }
+}
@@ -0,0 +1,395 @@
+use crate::math::ln;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+// Unnormalized density of a univariate target, which is evaluated on the log scale unless
+// on_log_scale returns false. Targets are taken by mutable reference and need not be Send or
+// Sync, since the samplers never move them to other threads. To run chains on several threads
+// for one immutable target, give each chain a clone of a SharedTarget.
+pub trait UnivariateTarget {
+    fn evaluate(&mut self, x: f64) -> f64;
+    // Evaluates the target at each point of xs, writing the values to out, which has the same
+    // length. Targets that evaluate many points more cheaply at once than one at a time, e.g.,
+    // with SIMD or on a GPU, can override this.
+    fn evaluate_batch(&mut self, xs: &[f64], out: &mut [f64]) {
+        for (x, fx) in xs.iter().zip(out) {
+            *fx = self.evaluate(*x);
+        }
+    }
+    fn on_log_scale(&self) -> bool {
+        true
+    }
+}
+
+// Unnormalized density of a multivariate target, which is evaluated on the log scale unless
+// on_log_scale returns false.
+pub trait MultivariateTarget {
+    fn dimension(&self) -> usize;
+    fn evaluate(&mut self, x: &[f64]) -> f64;
+    fn on_log_scale(&self) -> bool {
+        true
+    }
+}
+
+// Univariate target that also provides the derivative of its log density (whether or not it is
+// evaluated on the log scale), for samplers that use gradient information.
+pub trait UnivariateTargetWithGradient: UnivariateTarget {
+    fn gradient(&mut self, x: f64) -> f64;
+}
+
+// Univariate target that also computes n_statistics statistics (functionals whose posterior
+// expectations are wanted) at points it has evaluated, writing those at x to out, so that they can
+// share work with the density (e.g., by caching it at the latest points evaluated) rather than be
+// recomputed from stored draws.
+pub trait UnivariateTargetWithStatistics: UnivariateTarget {
+    fn n_statistics(&self) -> usize;
+    fn statistics(&mut self, x: f64, out: &mut [f64]);
+}
+
+// Multivariate target that also provides the gradient of its log density (whether or not it is
+// evaluated on the log scale), writing it to gradient, which has the length of x.
+pub trait MultivariateTargetWithGradient: MultivariateTarget {
+    fn gradient(&mut self, x: &[f64], gradient: &mut [f64]);
+}
+
+// Target whose log density can be evaluated to increasing precision, e.g., a likelihood over a
+// large data set bounded from a subset of its terms. At each level from 0 (the coarsest) to
+// max_level, bounds returns an interval containing the exact log density at x, and the bounds
+// must shrink as the level grows and be exact at max_level. Implementations should cache work
+// across levels at the same point, since samplers request increasing levels one at a time.
+pub trait RefinableTarget {
+    fn bounds(&mut self, x: f64, level: u32) -> (f64, f64);
+    fn max_level(&self) -> u32;
+}
+
+// Unnormalized density of a target over pairs, e.g., a location and a scale, which is evaluated
+// on the log scale unless on_log_scale returns false.
+pub trait BivariateTarget {
+    fn evaluate(&mut self, x: (f64, f64)) -> f64;
+    fn on_log_scale(&self) -> bool {
+        true
+    }
+}
+
+// Adapter turning a log density closure (e.g., |x| distribution.ln_pdf(x) for a statrs or
+// rand_distr distribution) into a univariate target.
+#[derive(Debug, Clone, Copy)]
+pub struct LogDensity<F>(pub F);
+
+impl<F: FnMut(f64) -> f64> UnivariateTarget for LogDensity<F> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        (self.0)(x)
+    }
+}
+
+// Adapter turning a density closure on the natural scale into a univariate target.
+#[derive(Debug, Clone, Copy)]
+pub struct Density<F>(pub F);
+
+impl<F: FnMut(f64) -> f64> UnivariateTarget for Density<F> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        (self.0)(x)
+    }
+    fn on_log_scale(&self) -> bool {
+        false
+    }
+}
+
+// Target on the log scale whatever the scale of the wrapped target, taking the logarithm of a
+// density on the natural scale and passing a log density through unchanged.
+#[derive(Debug, Clone, Copy)]
+pub struct LogScale<T>(pub T);
+
+impl<T: UnivariateTarget> UnivariateTarget for LogScale<T> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        let fx = self.0.evaluate(x);
+        if self.0.on_log_scale() {
+            fx
+        } else {
+            ln(fx)
+        }
+    }
+}
+
+impl<T: MultivariateTarget> MultivariateTarget for LogScale<T> {
+    fn dimension(&self) -> usize {
+        self.0.dimension()
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let fx = self.0.evaluate(x);
+        if self.0.on_log_scale() {
+            fx
+        } else {
+            ln(fx)
+        }
+    }
+}
+
+// Target on the natural scale whatever the scale of the wrapped target, exponentiating a log
+// density (which may underflow to zero far in the tails, where LogScale is safer).
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct NaturalScale<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T: UnivariateTarget> UnivariateTarget for NaturalScale<T> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        let fx = self.0.evaluate(x);
+        if self.0.on_log_scale() {
+            fx.exp()
+        } else {
+            fx
+        }
+    }
+    fn on_log_scale(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: MultivariateTarget> MultivariateTarget for NaturalScale<T> {
+    fn dimension(&self) -> usize {
+        self.0.dimension()
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let fx = self.0.evaluate(x);
+        if self.0.on_log_scale() {
+            fx.exp()
+        } else {
+            fx
+        }
+    }
+    fn on_log_scale(&self) -> bool {
+        false
+    }
+}
+
+// Density closure shared across threads through an Arc, e.g., a posterior holding a large data
+// set that several chains update in parallel under rayon or tokio. Clones are cheap and evaluate
+// the same closure, which takes the point by shared reference and so must be Sync to be used
+// from several threads at once.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct SharedTarget<F: ?Sized> {
+    on_log_scale: bool,
+    density: Arc<F>,
+}
+
+#[cfg(feature = "std")]
+impl<F> SharedTarget<F> {
+    pub fn log_density(f: F) -> Self {
+        Self::from_arc(Arc::new(f), true)
+    }
+    pub fn density(f: F) -> Self {
+        Self::from_arc(Arc::new(f), false)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: ?Sized> SharedTarget<F> {
+    pub fn from_arc(density: Arc<F>, on_log_scale: bool) -> Self {
+        Self {
+            on_log_scale,
+            density,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: ?Sized> Clone for SharedTarget<F> {
+    fn clone(&self) -> Self {
+        Self::from_arc(Arc::clone(&self.density), self.on_log_scale)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: Fn(f64) -> f64 + ?Sized> UnivariateTarget for SharedTarget<F> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        (self.density)(x)
+    }
+    fn on_log_scale(&self) -> bool {
+        self.on_log_scale
+    }
+}
+
+// Target that serves repeated evaluations at identical points (compared by bit pattern) from a
+// small cache of the most recently used points, e.g., for the acceptance test of the doubling
+// procedure, which revisits points evaluated while doubling. The wrapped target must be
+// deterministic. Lookups scan the cache, so it should stay small.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct MemoizedTarget<T> {
+    target: T,
+    capacity: usize,
+    // Bit pattern of x, value, and time of last use
+    entries: Vec<(u64, f64, u64)>,
+    time: u64,
+    hits: u64,
+    misses: u64,
+}
+
+#[cfg(feature = "std")]
+impl<T> MemoizedTarget<T> {
+    pub fn new(target: T, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            target,
+            capacity,
+            entries: Vec::with_capacity(capacity),
+            time: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    // Evaluations served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+    // Evaluations passed to the wrapped target.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    pub fn into_inner(self) -> T {
+        self.target
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: UnivariateTarget> UnivariateTarget for MemoizedTarget<T> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        self.time += 1;
+        let bits = x.to_bits();
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.0 == bits) {
+            entry.2 = self.time;
+            self.hits += 1;
+            return entry.1;
+        }
+        self.misses += 1;
+        let fx = self.target.evaluate(x);
+        if self.entries.len() < self.capacity {
+            self.entries.push((bits, fx, self.time));
+        } else if let Some(oldest) = self.entries.iter_mut().min_by_key(|entry| entry.2) {
+            *oldest = (bits, fx, self.time);
+        }
+        fx
+    }
+    fn on_log_scale(&self) -> bool {
+        self.target.on_log_scale()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closure_adapters() {
+        let mut log_density = LogDensity(|x: f64| -0.5 * x * x);
+        let mut density = Density(|x: f64| (-0.5 * x * x).exp());
+        assert!(log_density.on_log_scale());
+        assert!(!density.on_log_scale());
+        assert_eq!(log_density.evaluate(2.0), -2.0);
+        assert_eq!(density.evaluate(2.0), (-2.0f64).exp());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_scale_wrappers() {
+        use crate::univariate::stepping_out::TuningParameters;
+        use crate::univariate::variant::{SliceSampler, SliceVariant};
+        let mut log_scale = LogScale(Density(|x: f64| (-0.5 * x * x).exp()));
+        let mut natural_scale = NaturalScale(LogDensity(|x: f64| -0.5 * x * x));
+        assert!(log_scale.on_log_scale());
+        assert!(!natural_scale.on_log_scale());
+        assert!((log_scale.evaluate(2.0) + 2.0).abs() < 1e-12);
+        assert!((natural_scale.evaluate(2.0) - (-2.0f64).exp()).abs() < 1e-12);
+        // Either wrapper gives the same draws as the wrapped target on its own scale
+        let variant = SliceVariant::SteppingOut(TuningParameters::new());
+        let mut draws = [vec![0.0; 100], vec![0.0; 100], vec![0.0; 100]];
+        SliceSampler::with_seed(variant.clone(), 193).sample_n(
+            0.0,
+            &mut LogDensity(|x: f64| -0.5 * x * x),
+            &mut draws[0],
+        );
+        SliceSampler::with_seed(variant.clone(), 193).sample_n(0.0, &mut log_scale, &mut draws[1]);
+        SliceSampler::with_seed(variant, 193).sample_n(0.0, &mut natural_scale, &mut draws[2]);
+        for ((a, b), c) in draws[0].iter().zip(&draws[1]).zip(&draws[2]) {
+            assert!((a - b).abs() < 1e-9 && (a - c).abs() < 1e-9);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_memoized_target() {
+        use crate::univariate::doubling::TuningParameters;
+        use crate::univariate::variant::{SliceSampler, SliceVariant};
+        let mut calls = 0;
+        let mut target = MemoizedTarget::new(
+            LogDensity(|x: f64| {
+                calls += 1;
+                -0.5 * x * x
+            }),
+            2,
+        );
+        assert_eq!(target.evaluate(1.0), -0.5);
+        assert_eq!(target.evaluate(2.0), -2.0);
+        assert_eq!(target.evaluate(1.0), -0.5);
+        // Evicts 2.0, the least recently used
+        assert_eq!(target.evaluate(3.0), -4.5);
+        assert_eq!(target.evaluate(1.0), -0.5);
+        assert_eq!(target.evaluate(2.0), -2.0);
+        assert_eq!((target.hits(), target.misses()), (2, 4));
+        drop(target);
+        assert_eq!(calls, 4);
+        // The doubling procedure revisits points in its acceptance test
+        let variant =
+            SliceVariant::Doubling(TuningParameters::builder().width(0.1).build().unwrap());
+        let mut sampler = SliceSampler::with_seed(variant, 181);
+        let mut target = MemoizedTarget::new(LogDensity(|x: f64| -0.5 * x * x), 16);
+        let mut draws = vec![0.0; 1_000];
+        sampler.sample_n(0.0, &mut target, &mut draws);
+        assert!(target.hits() > 0);
+        assert_eq!(target.hits() + target.misses(), sampler.evaluations());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shared_target_across_threads() {
+        use crate::chain::Chain;
+        use crate::sampler::Sampler;
+        use crate::univariate::stepping_out::TuningParameters;
+        use crate::univariate::variant::{SliceSampler, SliceVariant};
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedTarget<fn(f64) -> f64>>();
+        assert_send_sync::<SliceSampler>();
+        assert_send_sync::<Sampler>();
+        assert_send_sync::<Chain>();
+        let data: Vec<f64> = (0..100).map(|i| (i as f64) / 100.0).collect();
+        // Normal likelihood with unit variance and a flat prior, so that the posterior mean of
+        // the location is the sample mean
+        let target = SharedTarget::log_density(move |mu: f64| {
+            data.iter().map(|y| -0.5 * (y - mu) * (y - mu)).sum::<f64>()
+        });
+        let means: Vec<f64> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|seed| {
+                    let mut target = target.clone();
+                    scope.spawn(move || {
+                        let variant = SliceVariant::SteppingOut(TuningParameters::new());
+                        let mut sampler = SliceSampler::with_seed(variant, seed);
+                        let mut draws = vec![0.0; 5_000];
+                        sampler.sample_n(0.0, &mut target, &mut draws);
+                        draws.iter().sum::<f64>() / (draws.len() as f64)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for mean in means {
+            assert!((mean - 0.495).abs() < 0.02);
+        }
+    }
+}
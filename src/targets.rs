@@ -0,0 +1,392 @@
+use crate::target::{
+    MultivariateTarget, MultivariateTargetWithGradient, UnivariateTarget,
+    UnivariateTargetWithGradient,
+};
+use std::f64::consts::PI;
+
+// Natural logarithm of the gamma function for x > 0 (Lanczos approximation, g = 7, n = 9).
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut a = COEFFICIENTS[0];
+    let t = x + 7.5;
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        a += c / (x + (i as f64));
+    }
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+fn normal_log_density(x: f64, mean: f64, standard_deviation: f64) -> f64 {
+    let z = (x - mean) / standard_deviation;
+    -0.5 * z * z - standard_deviation.ln() - 0.5 * (2.0 * PI).ln()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Normal {
+    pub mean: f64,
+    pub standard_deviation: f64,
+}
+
+impl UnivariateTarget for Normal {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        normal_log_density(x, self.mean, self.standard_deviation)
+    }
+}
+
+impl UnivariateTargetWithGradient for Normal {
+    fn gradient(&mut self, x: f64) -> f64 {
+        -(x - self.mean) / (self.standard_deviation * self.standard_deviation)
+    }
+}
+
+// Gamma distribution with the given shape and rate (mean shape / rate).
+#[derive(Debug, Clone, Copy)]
+pub struct Gamma {
+    pub shape: f64,
+    pub rate: f64,
+}
+
+impl UnivariateTarget for Gamma {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        if x <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        self.shape * self.rate.ln() - ln_gamma(self.shape) + (self.shape - 1.0) * x.ln()
+            - self.rate * x
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Beta {
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl UnivariateTarget for Beta {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        if x <= 0.0 || x >= 1.0 {
+            return f64::NEG_INFINITY;
+        }
+        ln_gamma(self.alpha + self.beta) - ln_gamma(self.alpha) - ln_gamma(self.beta)
+            + (self.alpha - 1.0) * x.ln()
+            + (self.beta - 1.0) * (1.0 - x).ln()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StudentT {
+    pub degrees_of_freedom: f64,
+    pub location: f64,
+    pub scale: f64,
+}
+
+impl UnivariateTarget for StudentT {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        let nu = self.degrees_of_freedom;
+        let z = (x - self.location) / self.scale;
+        ln_gamma(0.5 * (nu + 1.0))
+            - ln_gamma(0.5 * nu)
+            - 0.5 * (nu * PI).ln()
+            - self.scale.ln()
+            - 0.5 * (nu + 1.0) * (z * z / nu).ln_1p()
+    }
+}
+
+// Log-normal distribution, where the log of the variable has the given mean and standard
+// deviation.
+#[derive(Debug, Clone, Copy)]
+pub struct LogNormal {
+    pub mean_log: f64,
+    pub standard_deviation_log: f64,
+}
+
+impl UnivariateTarget for LogNormal {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        if x <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        normal_log_density(x.ln(), self.mean_log, self.standard_deviation_log) - x.ln()
+    }
+}
+
+// Mixture of two normal distributions, where the first component has probability weight.
+#[derive(Debug, Clone, Copy)]
+pub struct NormalMixture {
+    pub weight: f64,
+    pub first: Normal,
+    pub second: Normal,
+}
+
+impl UnivariateTarget for NormalMixture {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        let a = self.weight.ln() + self.first.evaluate(x);
+        let b = (1.0 - self.weight).ln() + self.second.evaluate(x);
+        let m = a.max(b);
+        if m == f64::NEG_INFINITY {
+            return m;
+        }
+        m + ((a - m).exp() + (b - m).exp()).ln()
+    }
+}
+
+// Neal's funnel: v ~ N(0, 3^2) and, given v, the remaining dimension - 1 coordinates are
+// independent N(0, exp(v)), where v is the first coordinate of the state.
+#[derive(Debug, Clone, Copy)]
+pub struct Funnel {
+    pub dimension: usize,
+}
+
+impl MultivariateTarget for Funnel {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let v = x[0];
+        let mut sum = normal_log_density(v, 0.0, 3.0);
+        let standard_deviation = (0.5 * v).exp();
+        for &xi in &x[1..] {
+            sum += normal_log_density(xi, 0.0, standard_deviation);
+        }
+        sum
+    }
+}
+
+// Banana-shaped distribution on two coordinates: x[0] ~ N(0, scale^2) and, given x[0],
+// x[1] ~ N(curvature * (x[0]^2 - scale^2), 1).
+#[derive(Debug, Clone, Copy)]
+pub struct Banana {
+    pub scale: f64,
+    pub curvature: f64,
+}
+
+impl MultivariateTarget for Banana {
+    fn dimension(&self) -> usize {
+        2
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let mean = self.curvature * (x[0] * x[0] - self.scale * self.scale);
+        normal_log_density(x[0], 0.0, self.scale) + normal_log_density(x[1], mean, 1.0)
+    }
+}
+
+// Dirichlet distribution with the given concentration parameters, whose state is a
+// probability vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dirichlet {
+    pub alpha: Vec<f64>,
+}
+
+impl MultivariateTarget for Dirichlet {
+    fn dimension(&self) -> usize {
+        self.alpha.len()
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let mut sum = ln_gamma(self.alpha.iter().sum());
+        for (&a, &xi) in self.alpha.iter().zip(x) {
+            if xi < 0.0 {
+                return f64::NEG_INFINITY;
+            }
+            sum += (a - 1.0) * xi.ln() - ln_gamma(a);
+        }
+        sum
+    }
+}
+
+// Bivariate normal distribution with standard normal margins and the given correlation.
+#[derive(Debug, Clone, Copy)]
+pub struct CorrelatedNormal {
+    pub correlation: f64,
+}
+
+impl MultivariateTarget for CorrelatedNormal {
+    fn dimension(&self) -> usize {
+        2
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let rho = self.correlation;
+        let q = (x[0] * x[0] - 2.0 * rho * x[0] * x[1] + x[1] * x[1]) / (1.0 - rho * rho);
+        -0.5 * q - 0.5 * (1.0 - rho * rho).ln() - (2.0 * PI).ln()
+    }
+}
+
+impl MultivariateTargetWithGradient for CorrelatedNormal {
+    fn gradient(&mut self, x: &[f64], gradient: &mut [f64]) {
+        let rho = self.correlation;
+        gradient[0] = -(x[0] - rho * x[1]) / (1.0 - rho * rho);
+        gradient[1] = -(x[1] - rho * x[0]) / (1.0 - rho * rho);
+    }
+}
+
+// Zero-mean normal distribution whose covariance has eigenvalues spaced geometrically from 1 to
+// condition_number, with eigenvectors rotated away from the coordinate axes by the Householder
+// reflection through the vector of ones, so that the coordinates are strongly correlated.
+#[derive(Debug, Clone, Copy)]
+pub struct IllConditionedNormal {
+    pub dimension: usize,
+    pub condition_number: f64,
+}
+
+impl IllConditionedNormal {
+    // Variance along the ith eigenvector.
+    pub fn variance(&self, i: usize) -> f64 {
+        if self.dimension < 2 {
+            return 1.0;
+        }
+        self.condition_number
+            .powf((i as f64) / ((self.dimension - 1) as f64))
+    }
+}
+
+impl MultivariateTarget for IllConditionedNormal {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        // The reflection I - 2 v v' / (v' v) with v the vector of ones is its own inverse
+        let shift = 2.0 * x.iter().sum::<f64>() / (x.len() as f64);
+        let mut sum = 0.0;
+        for (i, &xi) in x.iter().enumerate() {
+            let variance = self.variance(i);
+            let z = xi - shift;
+            sum -= 0.5 * (z * z / variance + (2.0 * PI * variance).ln());
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out::{
+        univariate_slice_sampler_stepping_out_and_shrinkage, TuningParameters,
+    };
+
+    fn sample_mean<T: UnivariateTarget>(mut target: T, x: f64) -> f64 {
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(29));
+        let mut x = x;
+        let mut sum = 0.0;
+        let n_samples = 100_000;
+        let on_log_scale = target.on_log_scale();
+        for _ in 0..n_samples {
+            (x, _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                x,
+                |x| target.evaluate(x),
+                on_log_scale,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum += x;
+        }
+        sum / (n_samples as f64)
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert!(ln_gamma(1.0).abs() < 1e-12);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - PI.sqrt().ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_univariate_means() {
+        let normal = Normal {
+            mean: 1.0,
+            standard_deviation: 2.0,
+        };
+        assert!((sample_mean(normal, 0.0) - 1.0).abs() < 0.05);
+        let gamma = Gamma {
+            shape: 3.0,
+            rate: 2.0,
+        };
+        assert!((sample_mean(gamma, 1.0) - 1.5).abs() < 0.03);
+        let beta = Beta {
+            alpha: 2.0,
+            beta: 5.0,
+        };
+        assert!((sample_mean(beta, 0.5) - 2.0 / 7.0).abs() < 0.01);
+        let log_normal = LogNormal {
+            mean_log: 0.0,
+            standard_deviation_log: 0.5,
+        };
+        assert!((sample_mean(log_normal, 1.0) - 0.125f64.exp()).abs() < 0.02);
+        let mixture = NormalMixture {
+            weight: 0.3,
+            first: Normal {
+                mean: -1.0,
+                standard_deviation: 1.0,
+            },
+            second: Normal {
+                mean: 2.0,
+                standard_deviation: 1.0,
+            },
+        };
+        assert!((sample_mean(mixture, 0.0) - 1.1).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_normalization() {
+        // Trapezoidal rule over a grid covering essentially all of the mass
+        let integrate = |target: &mut dyn UnivariateTarget, a: f64, b: f64| {
+            let n = 200_000;
+            let h = (b - a) / (n as f64);
+            (1..n)
+                .map(|i| target.evaluate(a + (i as f64) * h).exp() * h)
+                .sum::<f64>()
+        };
+        let mut student_t = StudentT {
+            degrees_of_freedom: 5.0,
+            location: 1.0,
+            scale: 2.0,
+        };
+        assert!((integrate(&mut student_t, -400.0, 400.0) - 1.0).abs() < 1e-3);
+        let mut gamma = Gamma {
+            shape: 2.5,
+            rate: 0.5,
+        };
+        assert!((integrate(&mut gamma, 0.0, 100.0) - 1.0).abs() < 1e-3);
+        let mut banana = Banana {
+            scale: 1.0,
+            curvature: 0.5,
+        };
+        assert!(
+            (banana.evaluate(&[0.0, -0.5]) - 2.0 * normal_log_density(0.0, 0.0, 1.0)).abs() < 1e-12
+        );
+        let mut funnel = Funnel { dimension: 3 };
+        assert_eq!(funnel.dimension(), 3);
+        assert!(funnel.evaluate(&[0.0, 0.0, 0.0]).is_finite());
+    }
+
+    #[test]
+    fn test_gaussian_benchmarks() {
+        // With no correlation the bivariate normal is a product of standard normals
+        let mut independent = CorrelatedNormal { correlation: 0.0 };
+        let expected = 2.0 * normal_log_density(0.5, 0.0, 1.0);
+        assert!((independent.evaluate(&[0.5, -0.5]) - expected).abs() < 1e-12);
+        // The reflection maps the first eigenvector, of variance 1, to e1 - 2 / n * ones
+        let mut ill_conditioned = IllConditionedNormal {
+            dimension: 4,
+            condition_number: 1000.0,
+        };
+        assert_eq!(ill_conditioned.variance(3), 1000.0);
+        let x = [0.5, -0.5, -0.5, -0.5];
+        let expected = normal_log_density(1.0, 0.0, 1.0)
+            + (1..4)
+                .map(|i| normal_log_density(0.0, 0.0, ill_conditioned.variance(i).sqrt()))
+                .sum::<f64>();
+        assert!((ill_conditioned.evaluate(&x) - expected).abs() < 1e-12);
+    }
+}
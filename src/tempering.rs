@@ -0,0 +1,228 @@
+use crate::error::TuningError;
+use crate::math::default_rng;
+use crate::target::UnivariateTarget;
+use crate::univariate::variant::{sample, SliceVariant};
+
+// Target whose log density is the log density of the wrapped target multiplied by lambda, i.e.,
+// tempered by the temperature 1 / lambda.
+pub struct Tempered<'t, T: UnivariateTarget + ?Sized> {
+    target: &'t mut T,
+    lambda: f64,
+}
+
+impl<'t, T: UnivariateTarget + ?Sized> Tempered<'t, T> {
+    pub fn new(target: &'t mut T, lambda: f64) -> Self {
+        Self { target, lambda }
+    }
+    pub fn lambda(&self) -> f64 {
+        self.lambda
+    }
+    // Log density of the wrapped (untempered) target.
+    pub fn log_density(&mut self, x: f64) -> f64 {
+        let fx = self.target.evaluate(x);
+        if self.target.on_log_scale() {
+            fx
+        } else {
+            fx.ln()
+        }
+    }
+}
+
+impl<T: UnivariateTarget + ?Sized> UnivariateTarget for Tempered<'_, T> {
+    fn evaluate(&mut self, x: f64) -> f64 {
+        let log_density = self.log_density(x);
+        if log_density == f64::NEG_INFINITY {
+            log_density
+        } else {
+            self.lambda * log_density
+        }
+    }
+}
+
+pub(crate) fn check_temperature(temperature: f64) -> Result<f64, TuningError> {
+    if temperature.is_finite() && temperature > 0.0 {
+        Ok(temperature)
+    } else {
+        Err(TuningError::InvalidTemperature(temperature))
+    }
+}
+
+// Results of parallel tempering: the draws of the cold chain, the number of target evaluations
+// at each temperature, the acceptance rate of swaps between each pair of adjacent temperatures,
+// and the final state at each temperature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelTempering {
+    pub draws: Vec<f64>,
+    pub evaluations: Vec<u64>,
+    pub swap_acceptance_rates: Vec<f64>,
+    pub states: Vec<f64>,
+}
+
+// Parallel tempering from x with a chain at each temperature (the first of which should be 1,
+// the cold chain), where each of n_draws sweeps updates every chain with the slice variant on
+// its tempered target and then proposes Metropolis swaps between adjacent temperatures.
+pub fn parallel_tempering<T: UnivariateTarget + ?Sized>(
+    x: f64,
+    n_draws: usize,
+    temperatures: &[f64],
+    target: &mut T,
+    variant: &SliceVariant,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<ParallelTempering, TuningError> {
+    for &temperature in temperatures {
+        check_temperature(temperature)?;
+    }
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let k = temperatures.len();
+    let mut states = vec![x; k];
+    let mut log_densities = vec![0.0; k];
+    let mut evaluations = vec![0; k];
+    let mut n_accepted = vec![0; k.saturating_sub(1)];
+    let mut draws = Vec::with_capacity(n_draws);
+    for _ in 0..n_draws {
+        for i in 0..k {
+            let mut tempered = Tempered::new(&mut *target, 1.0 / temperatures[i]);
+            let evaluation_counter;
+            (states[i], evaluation_counter) = sample(variant, states[i], &mut tempered, rng);
+            log_densities[i] = tempered.log_density(states[i]);
+            evaluations[i] += evaluation_counter + 1;
+        }
+        for i in 0..k.saturating_sub(1) {
+            let log_ratio = (1.0 / temperatures[i] - 1.0 / temperatures[i + 1])
+                * (log_densities[i + 1] - log_densities[i]);
+            let u = rng.get_or_insert_with(default_rng).f64();
+            if log_ratio >= 0.0 || u.ln() < log_ratio {
+                states.swap(i, i + 1);
+                log_densities.swap(i, i + 1);
+                n_accepted[i] += 1;
+            }
+        }
+        if k > 0 {
+            draws.push(states[0]);
+        }
+    }
+    Ok(ParallelTempering {
+        draws,
+        evaluations,
+        swap_acceptance_rates: n_accepted
+            .iter()
+            .map(|&n| (n as f64) / (n_draws as f64))
+            .collect(),
+        states,
+    })
+}
+
+// Results of annealing: the state at the end of each stage of the schedule and the number of
+// target evaluations in each stage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annealing {
+    pub draws: Vec<f64>,
+    pub evaluations: Vec<u64>,
+}
+
+// Anneals from x through a schedule of lambda values (e.g., increasing to find a mode), applying
+// n_updates updates of the slice variant to the target with log density lambda * log f(x) at
+// each stage. Each lambda must be finite and positive, i.e., correspond to a valid temperature.
+pub fn anneal<T: UnivariateTarget + ?Sized>(
+    x: f64,
+    schedule: &[f64],
+    n_updates: usize,
+    target: &mut T,
+    variant: &SliceVariant,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<Annealing, TuningError> {
+    for &lambda in schedule {
+        check_temperature(1.0 / lambda)?;
+    }
+    let mut maybe;
+    let rng = match rng {
+        Some(_) => rng,
+        None => {
+            maybe = Some(default_rng());
+            &mut maybe
+        }
+    };
+    let mut x = x;
+    let mut draws = Vec::with_capacity(schedule.len());
+    let mut evaluations = Vec::with_capacity(schedule.len());
+    for &lambda in schedule {
+        let mut tempered = Tempered::new(&mut *target, lambda);
+        let mut evaluation_counter = 0;
+        for _ in 0..n_updates {
+            let evaluations;
+            (x, evaluations) = sample(variant, x, &mut tempered, rng);
+            evaluation_counter += evaluations;
+        }
+        draws.push(x);
+        evaluations.push(evaluation_counter);
+    }
+    Ok(Annealing { draws, evaluations })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::{Normal, NormalMixture};
+    use crate::univariate::stepping_out::TuningParameters;
+
+    #[test]
+    fn test_parallel_tempering_bimodal() {
+        let mut target = NormalMixture {
+            weight: 0.5,
+            first: Normal {
+                mean: -5.0,
+                standard_deviation: 1.0,
+            },
+            second: Normal {
+                mean: 5.0,
+                standard_deviation: 1.0,
+            },
+        };
+        let variant = SliceVariant::SteppingOut(TuningParameters::new());
+        let mut rng = Some(fastrand::Rng::with_seed(67));
+        let n_draws = 20_000;
+        let result = parallel_tempering(
+            -5.0,
+            n_draws,
+            &[1.0, 3.0, 9.0, 27.0],
+            &mut target,
+            &variant,
+            &mut rng,
+        )
+        .unwrap();
+        let positive = result.draws.iter().filter(|&&x| x > 0.0).count() as f64;
+        assert!((positive / (n_draws as f64) - 0.5).abs() < 0.05);
+        assert_eq!(result.swap_acceptance_rates.len(), 3);
+        assert!(result.swap_acceptance_rates.iter().all(|&rate| rate > 0.1));
+        assert_eq!(
+            parallel_tempering(0.0, 1, &[1.0, 0.0], &mut target, &variant, &mut rng),
+            Err(TuningError::InvalidTemperature(0.0))
+        );
+    }
+
+    #[test]
+    fn test_annealing_finds_mode() {
+        let mut target = Normal {
+            mean: 3.0,
+            standard_deviation: 1.0,
+        };
+        let variant = SliceVariant::SteppingOut(TuningParameters::new());
+        let mut rng = Some(fastrand::Rng::with_seed(71));
+        let schedule: Vec<f64> = (0..20).map(|i| 2.0f64.powi(i)).collect();
+        let result = anneal(-10.0, &schedule, 10, &mut target, &variant, &mut rng).unwrap();
+        assert_eq!(result.evaluations.len(), schedule.len());
+        assert!(result.evaluations.iter().all(|&n| n > 0));
+        assert!((result.draws.last().unwrap() - 3.0).abs() < 0.01);
+        assert_eq!(
+            anneal(0.0, &[0.0], 1, &mut target, &variant, &mut rng),
+            Err(TuningError::InvalidTemperature(f64::INFINITY))
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::diagnostics::{effective_sample_size, monte_carlo_standard_error};
+use crate::targets::ln_gamma;
+
+// Number of Monte Carlo standard errors within which the assertions require agreement, which
+// keeps false alarms rare enough for test suites with many such checks.
+const Z: f64 = 4.0;
+
+fn mean(draws: &[f64]) -> f64 {
+    draws.iter().sum::<f64>() / (draws.len() as f64)
+}
+
+// Asserts that the mean of the (possibly autocorrelated) draws agrees with the analytic mean
+// within Monte Carlo error.
+pub fn assert_mean(draws: &[f64], expected: f64) {
+    let estimate = mean(draws);
+    let standard_error = monte_carlo_standard_error(draws);
+    assert!(
+        (estimate - expected).abs() <= Z * standard_error,
+        "mean {} differs from {} by more than {} standard errors of {}",
+        estimate,
+        expected,
+        Z,
+        standard_error
+    );
+}
+
+// Asserts that the variance of the draws agrees with the analytic variance within Monte Carlo
+// error, treating the variance as the mean of the squared deviations.
+pub fn assert_variance(draws: &[f64], expected: f64) {
+    let m = mean(draws);
+    let squared_deviations: Vec<f64> = draws.iter().map(|x| (x - m).powi(2)).collect();
+    let estimate = mean(&squared_deviations);
+    let standard_error = monte_carlo_standard_error(&squared_deviations);
+    assert!(
+        (estimate - expected).abs() <= Z * standard_error,
+        "variance {} differs from {} by more than {} standard errors of {}",
+        estimate,
+        expected,
+        Z,
+        standard_error
+    );
+}
+
+pub fn assert_moments(draws: &[f64], expected_mean: f64, expected_variance: f64) {
+    assert_mean(draws, expected_mean);
+    assert_variance(draws, expected_variance);
+}
+
+// Asserts that the proportion of draws at or below the analytic quantile for the probability
+// agrees with the probability within Monte Carlo error.
+pub fn assert_quantile(draws: &[f64], probability: f64, quantile: f64) {
+    let indicators: Vec<f64> = draws
+        .iter()
+        .map(|&x| if x <= quantile { 1.0 } else { 0.0 })
+        .collect();
+    let proportion = mean(&indicators);
+    let standard_error = (probability * (1.0 - probability) / effective_sample_size(draws)).sqrt();
+    assert!(
+        (proportion - probability).abs() <= Z * standard_error,
+        "{} of the draws are at or below {}, which differs from {} by more than {} standard \
+         errors of {}",
+        proportion,
+        quantile,
+        probability,
+        Z,
+        standard_error
+    );
+}
+
+// Result of a chi-square goodness-of-fit test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+}
+
+// Chi-square goodness-of-fit test of the draws against the analytic probabilities of the bins
+// (-inf, edges[0]], (edges[0], edges[1]], ..., (edges[k - 1], inf), so probabilities has one
+// more element than edges. Since the draws of a chain are autocorrelated, the statistic is
+// scaled by the effective sample size over the number of draws.
+pub fn chi_square_test(draws: &[f64], edges: &[f64], probabilities: &[f64]) -> ChiSquare {
+    assert_eq!(probabilities.len(), edges.len() + 1);
+    let mut counts = vec![0.0; probabilities.len()];
+    for &x in draws {
+        counts[edges.partition_point(|&edge| edge < x)] += 1.0;
+    }
+    let n = draws.len() as f64;
+    let statistic = counts
+        .iter()
+        .zip(probabilities)
+        .map(|(count, p)| (count - n * p).powi(2) / (n * p))
+        .sum::<f64>()
+        * effective_sample_size(draws).min(n)
+        / n;
+    let degrees_of_freedom = probabilities.len() - 1;
+    ChiSquare {
+        statistic,
+        degrees_of_freedom,
+        p_value: chi_square_survival(statistic, degrees_of_freedom as f64),
+    }
+}
+
+// Probability that a chi-square variable with k degrees of freedom exceeds x.
+pub fn chi_square_survival(x: f64, k: f64) -> f64 {
+    if x <= 0.0 {
+        1.0
+    } else {
+        1.0 - regularized_lower_gamma(k / 2.0, x / 2.0)
+    }
+}
+
+// Regularized lower incomplete gamma function P(a, x), by its series for x < a + 1 and by the
+// continued fraction for its complement otherwise.
+fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        while term.abs() > sum.abs() * 1e-15 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+        }
+        sum * log_prefactor.exp()
+    } else {
+        // Modified Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        let mut i = 1.0;
+        loop {
+            let an = -i * (i - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+            i += 1.0;
+        }
+        1.0 - log_prefactor.exp() * h
+    }
+}
+
+// Result of a Kolmogorov-Smirnov test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KolmogorovSmirnov {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+// Kolmogorov-Smirnov test of the draws against the analytic cumulative distribution function,
+// with the p-value from the asymptotic distribution of the statistic (with Stephens' correction)
+// at the effective sample size, since the draws of a chain are autocorrelated.
+pub fn kolmogorov_smirnov_test<C: Fn(f64) -> f64>(draws: &[f64], cdf: C) -> KolmogorovSmirnov {
+    let mut sorted = draws.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len() as f64;
+    let statistic = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let p = cdf(x);
+            (p - (i as f64) / n).max((i as f64 + 1.0) / n - p)
+        })
+        .fold(0.0, f64::max);
+    let sqrt_n = effective_sample_size(draws).min(n).sqrt();
+    KolmogorovSmirnov {
+        statistic,
+        p_value: kolmogorov_survival((sqrt_n + 0.12 + 0.11 / sqrt_n) * statistic),
+    }
+}
+
+// Probability that a Kolmogorov distributed variable exceeds lambda.
+fn kolmogorov_survival(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100 {
+        let term = (-2.0 * (k as f64).powi(2) * lambda * lambda).exp();
+        sum += sign * term;
+        if term < 1e-16 {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+// Runs n updates of a chain from x with the kernel (e.g., |x, rng| a sampler's update of x
+// using rng) and a generator seeded with seed, and tests the draws against the analytic
+// cumulative distribution function of the target.
+pub fn validate_against_cdf<K, C>(
+    x: f64,
+    mut kernel: K,
+    cdf: C,
+    n: usize,
+    seed: u64,
+) -> KolmogorovSmirnov
+where
+    K: FnMut(f64, &mut Option<fastrand::Rng>) -> f64,
+    C: Fn(f64) -> f64,
+{
+    let mut rng = Some(fastrand::Rng::with_seed(seed));
+    let mut x = x;
+    let draws: Vec<f64> = (0..n)
+        .map(|_| {
+            x = kernel(x, &mut rng);
+            x
+        })
+        .collect();
+    kolmogorov_smirnov_test(&draws, cdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out::{
+        univariate_slice_sampler_stepping_out_and_shrinkage, TuningParameters,
+    };
+
+    #[test]
+    fn test_chi_square_survival() {
+        // Upper 5% points of the chi-square distribution
+        assert!((chi_square_survival(3.841459, 1.0) - 0.05).abs() < 1e-6);
+        assert!((chi_square_survival(18.307038, 10.0) - 0.05).abs() < 1e-6);
+        assert!((chi_square_survival(2.0, 2.0) - (-1.0f64).exp()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_standard_normal() {
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(71));
+        let mut x = 0.0;
+        let draws: Vec<f64> = (0..20_000)
+            .map(|_| {
+                (x, _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                    x,
+                    |x| -0.5 * x * x,
+                    true,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+                x
+            })
+            .collect();
+        assert_moments(&draws, 0.0, 1.0);
+        assert_quantile(&draws, 0.975, 1.959964);
+        let edges = [-1.0, 0.0, 1.0];
+        let probabilities = [0.158655, 0.341345, 0.341345, 0.158655];
+        let test = chi_square_test(&draws, &edges, &probabilities);
+        assert_eq!(test.degrees_of_freedom, 3);
+        assert!(test.p_value > 0.001);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_wrong_mean() {
+        let draws: Vec<f64> = (0..10_000).map(|i| (i % 100) as f64 / 100.0).collect();
+        assert_mean(&draws, 0.6);
+    }
+
+    #[test]
+    fn test_validate_against_cdf() {
+        // Standard exponential, and a deliberately wrong rate
+        let tuning_parameters = TuningParameters::new();
+        let kernel = |x: f64, rng: &mut Option<fastrand::Rng>| {
+            let f = |x: f64| if x < 0.0 { f64::NEG_INFINITY } else { -x };
+            univariate_slice_sampler_stepping_out_and_shrinkage(x, f, true, &tuning_parameters, rng)
+                .0
+        };
+        let test = validate_against_cdf(1.0, kernel, |x| 1.0 - (-x).exp(), 20_000, 73);
+        assert!(test.p_value > 0.001);
+        let test = validate_against_cdf(1.0, kernel, |x| 1.0 - (-1.2 * x).exp(), 20_000, 73);
+        assert!(test.p_value < 0.001);
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceRecord {
+    pub draw: f64,
+    pub evaluations: u64,
+    pub slice_level: Option<f64>,
+}
+
+const RECORD_SIZE: usize = 24;
+
+impl TraceRecord {
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[..8].copy_from_slice(&self.draw.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.evaluations.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.slice_level.unwrap_or(f64::NAN).to_le_bytes());
+        bytes
+    }
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let slice_level = f64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        Self {
+            draw: f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            evaluations: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            slice_level: if slice_level.is_nan() {
+                None
+            } else {
+                Some(slice_level)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Spill {
+    file: File,
+    chunk_size: usize,
+    n_spilled: usize,
+}
+
+// Record of the draws of a run, with the number of target evaluations and (optionally) the
+// slice level of each draw. Records are kept in memory unless the trace spills to a file, in
+// which case they are appended to the file in chunks of chunk_size records so that runs with
+// millions of iterations need only one chunk in memory.
+#[derive(Debug, Default)]
+pub struct Trace {
+    buffer: Vec<TraceRecord>,
+    spill: Option<Spill>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn with_spill<P: AsRef<Path>>(path: P, chunk_size: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            buffer: Vec::with_capacity(chunk_size),
+            spill: Some(Spill {
+                file,
+                chunk_size: chunk_size.max(1),
+                n_spilled: 0,
+            }),
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.n_spilled() + self.buffer.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn n_spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.n_spilled)
+    }
+    pub fn push(&mut self, record: TraceRecord) -> io::Result<()> {
+        self.buffer.push(record);
+        match &self.spill {
+            Some(spill) if self.buffer.len() >= spill.chunk_size => self.flush(),
+            _ => Ok(()),
+        }
+    }
+    // Writes the buffered records to the file, if the trace spills to a file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(spill) = &mut self.spill {
+            let mut bytes = Vec::with_capacity(self.buffer.len() * RECORD_SIZE);
+            for record in &self.buffer {
+                bytes.extend_from_slice(&record.to_bytes());
+            }
+            spill.file.seek(SeekFrom::End(0))?;
+            spill.file.write_all(&bytes)?;
+            spill.n_spilled += self.buffer.len();
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+    // Records with indices start..end (clipped to the length of the trace).
+    pub fn read_range(&mut self, start: usize, end: usize) -> io::Result<Vec<TraceRecord>> {
+        let end = end.min(self.len());
+        let start = start.min(end);
+        let n_spilled = self.n_spilled();
+        let mut records = Vec::with_capacity(end - start);
+        if let Some(spill) = &mut self.spill {
+            if start < n_spilled {
+                let stop = end.min(n_spilled);
+                let mut bytes = vec![0; (stop - start) * RECORD_SIZE];
+                spill
+                    .file
+                    .seek(SeekFrom::Start((start * RECORD_SIZE) as u64))?;
+                spill.file.read_exact(&mut bytes)?;
+                records.extend(bytes.chunks_exact(RECORD_SIZE).map(TraceRecord::from_bytes));
+            }
+        }
+        if end > n_spilled {
+            records
+                .extend_from_slice(&self.buffer[start.max(n_spilled) - n_spilled..end - n_spilled]);
+        }
+        Ok(records)
+    }
+    pub fn draws(&mut self) -> io::Result<Vec<f64>> {
+        let mut draws = Vec::with_capacity(self.len());
+        let chunk_size = self
+            .spill
+            .as_ref()
+            .map_or(self.len(), |spill| spill.chunk_size);
+        let mut start = 0;
+        while start < self.len() {
+            let records = self.read_range(start, start + chunk_size.max(1))?;
+            draws.extend(records.iter().map(|record| record.draw));
+            start += records.len();
+        }
+        Ok(draws)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(i: usize) -> TraceRecord {
+        TraceRecord {
+            draw: i as f64,
+            evaluations: (i % 7) as u64,
+            slice_level: if i.is_multiple_of(2) {
+                Some(-(i as f64))
+            } else {
+                None
+            },
+        }
+    }
+
+    #[test]
+    fn test_spilled_trace_matches_in_memory_trace() {
+        let path =
+            std::env::temp_dir().join(format!("slice_sampler_trace_{}.bin", std::process::id()));
+        let mut spilled = Trace::with_spill(&path, 16).unwrap();
+        let mut in_memory = Trace::new();
+        for i in 0..100 {
+            spilled.push(record(i)).unwrap();
+            in_memory.push(record(i)).unwrap();
+        }
+        assert_eq!(spilled.len(), 100);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            (96 * RECORD_SIZE) as u64
+        );
+        assert_eq!(
+            spilled.read_range(90, 98).unwrap(),
+            in_memory.read_range(90, 98).unwrap()
+        );
+        assert_eq!(
+            spilled.read_range(0, 100).unwrap(),
+            (0..100).map(record).collect::<Vec<_>>()
+        );
+        assert_eq!(spilled.draws().unwrap(), in_memory.draws().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,482 @@
+use crate::target::{MultivariateTarget, UnivariateTarget};
+
+// ln(1 + exp(t)) without overflow.
+fn softplus(t: f64) -> f64 {
+    t.max(0.0) + (-t.abs()).exp().ln_1p()
+}
+
+fn logistic(t: f64) -> f64 {
+    1.0 / (1.0 + (-t).exp())
+}
+
+fn logit(p: f64) -> f64 {
+    (p / (1.0 - p)).ln()
+}
+
+// Bijection from the real line onto a constrained set, with the log absolute derivative of the
+// map from the unconstrained to the constrained value.
+pub trait ScalarTransform {
+    fn constrain(&self, y: f64) -> f64;
+    fn unconstrain(&self, x: f64) -> f64;
+    fn log_jacobian(&self, y: f64) -> f64;
+}
+
+// Bijection from an unconstrained vector onto a constrained set of vectors, whose dimensions
+// may differ (e.g., a simplex of dimension k has k - 1 unconstrained coordinates).
+pub trait VectorTransform {
+    fn constrained_dimension(&self) -> usize;
+    fn unconstrained_dimension(&self) -> usize;
+    // Writes the constrained value of y to x, returning the log absolute determinant of the
+    // Jacobian of the map.
+    fn constrain(&self, y: &[f64], x: &mut [f64]) -> f64;
+    fn unconstrain(&self, x: &[f64], y: &mut [f64]);
+}
+
+// Positive values through x = exp(y).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Positive;
+
+impl ScalarTransform for Positive {
+    fn constrain(&self, y: f64) -> f64 {
+        y.exp()
+    }
+    fn unconstrain(&self, x: f64) -> f64 {
+        x.ln()
+    }
+    fn log_jacobian(&self, y: f64) -> f64 {
+        y
+    }
+}
+
+// Values in the open interval (a, b) through x = a + (b - a) logistic(y).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval(pub f64, pub f64);
+
+impl ScalarTransform for Interval {
+    fn constrain(&self, y: f64) -> f64 {
+        self.0 + (self.1 - self.0) * logistic(y)
+    }
+    fn unconstrain(&self, x: f64) -> f64 {
+        logit((x - self.0) / (self.1 - self.0))
+    }
+    fn log_jacobian(&self, y: f64) -> f64 {
+        (self.1 - self.0).ln() - softplus(-y) - softplus(y)
+    }
+}
+
+// Increasing vectors through x[0] = y[0] and x[k] = x[k - 1] + exp(y[k]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ordered {
+    pub dimension: usize,
+}
+
+impl VectorTransform for Ordered {
+    fn constrained_dimension(&self) -> usize {
+        self.dimension
+    }
+    fn unconstrained_dimension(&self) -> usize {
+        self.dimension
+    }
+    fn constrain(&self, y: &[f64], x: &mut [f64]) -> f64 {
+        let mut log_jacobian = 0.0;
+        for k in 0..self.dimension {
+            if k == 0 {
+                x[0] = y[0];
+            } else {
+                x[k] = x[k - 1] + y[k].exp();
+                log_jacobian += y[k];
+            }
+        }
+        log_jacobian
+    }
+    fn unconstrain(&self, x: &[f64], y: &mut [f64]) {
+        for k in 0..self.dimension {
+            y[k] = if k == 0 { x[0] } else { (x[k] - x[k - 1]).ln() };
+        }
+    }
+}
+
+// Probability vectors of the given dimension through stick breaking, where the kth stick takes
+// the fraction logistic(y[k] - ln(dimension - 1 - k)) of what remains, so that y = 0 maps to the
+// uniform probability vector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Simplex {
+    pub dimension: usize,
+}
+
+impl VectorTransform for Simplex {
+    fn constrained_dimension(&self) -> usize {
+        self.dimension
+    }
+    fn unconstrained_dimension(&self) -> usize {
+        self.dimension.saturating_sub(1)
+    }
+    fn constrain(&self, y: &[f64], x: &mut [f64]) -> f64 {
+        let k = self.dimension;
+        let mut remaining = 1.0;
+        let mut log_jacobian = 0.0;
+        for i in 0..k.saturating_sub(1) {
+            let t = y[i] - ((k - 1 - i) as f64).ln();
+            let z = logistic(t);
+            x[i] = remaining * z;
+            log_jacobian += remaining.ln() - softplus(-t) - softplus(t);
+            remaining -= x[i];
+        }
+        if k > 0 {
+            x[k - 1] = remaining;
+        }
+        log_jacobian
+    }
+    fn unconstrain(&self, x: &[f64], y: &mut [f64]) {
+        let k = self.dimension;
+        let mut remaining = 1.0;
+        for i in 0..k.saturating_sub(1) {
+            y[i] = logit(x[i] / remaining) + ((k - 1 - i) as f64).ln();
+            remaining -= x[i];
+        }
+    }
+}
+
+// Covariance matrices of the given dimension n, stored row-major as n * n values, through their
+// Cholesky factor L: the unconstrained coordinates are the n (n + 1) / 2 elements of L in
+// row-major order with the log of each diagonal element in place of that element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CholeskyCovariance {
+    pub dimension: usize,
+}
+
+impl VectorTransform for CholeskyCovariance {
+    fn constrained_dimension(&self) -> usize {
+        self.dimension * self.dimension
+    }
+    fn unconstrained_dimension(&self) -> usize {
+        self.dimension * (self.dimension + 1) / 2
+    }
+    // The log Jacobian is n ln 2 + sum over i (from 0) of (n - i) ln L[i][i] for the map from L
+    // to L L^T plus sum over i of ln L[i][i] for the log-diagonal transform.
+    fn constrain(&self, y: &[f64], x: &mut [f64]) -> f64 {
+        let n = self.dimension;
+        let mut l = vec![0.0; n * n];
+        let mut log_jacobian = (n as f64) * core::f64::consts::LN_2;
+        let mut k = 0;
+        for i in 0..n {
+            for j in 0..=i {
+                if i == j {
+                    l[i * n + j] = y[k].exp();
+                    log_jacobian += ((n - i + 1) as f64) * y[k];
+                } else {
+                    l[i * n + j] = y[k];
+                }
+                k += 1;
+            }
+        }
+        for i in 0..n {
+            for j in 0..=i {
+                let value: f64 = (0..=j).map(|m| l[i * n + m] * l[j * n + m]).sum();
+                x[i * n + j] = value;
+                x[j * n + i] = value;
+            }
+        }
+        log_jacobian
+    }
+    fn unconstrain(&self, x: &[f64], y: &mut [f64]) {
+        let n = self.dimension;
+        let mut l = vec![0.0; n * n];
+        let mut k = 0;
+        for i in 0..n {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|m| l[i * n + m] * l[j * n + m]).sum();
+                if i == j {
+                    l[i * n + i] = (x[i * n + i] - sum).sqrt();
+                    y[k] = l[i * n + i].ln();
+                } else {
+                    l[i * n + j] = (x[i * n + j] - sum) / l[j * n + j];
+                    y[k] = l[i * n + j];
+                }
+                k += 1;
+            }
+        }
+    }
+}
+
+// Non-centered parameterization of a hierarchical model: the coordinates in `indices` are
+// standardized innovations z with x = location + scale * z, where `location_and_scale` computes
+// the location and scale from the remaining coordinates (which pass through unchanged and must
+// not include any of `indices`). Funnel-shaped posteriors, where the scale of the group-level
+// coordinates depends strongly on a hyperparameter, become much closer to independent.
+#[derive(Debug, Clone)]
+pub struct NonCentered<F> {
+    pub dimension: usize,
+    pub indices: Vec<usize>,
+    pub location_and_scale: F,
+}
+
+impl<F: Fn(&[f64]) -> (f64, f64)> VectorTransform for NonCentered<F> {
+    fn constrained_dimension(&self) -> usize {
+        self.dimension
+    }
+    fn unconstrained_dimension(&self) -> usize {
+        self.dimension
+    }
+    fn constrain(&self, y: &[f64], x: &mut [f64]) -> f64 {
+        x.copy_from_slice(y);
+        let (location, scale) = (self.location_and_scale)(y);
+        for &i in &self.indices {
+            x[i] = location + scale * y[i];
+        }
+        (self.indices.len() as f64) * scale.ln()
+    }
+    fn unconstrain(&self, x: &[f64], y: &mut [f64]) {
+        y.copy_from_slice(x);
+        let (location, scale) = (self.location_and_scale)(x);
+        for &i in &self.indices {
+            y[i] = (x[i] - location) / scale;
+        }
+    }
+}
+
+fn log_scale(fx: f64, on_log_scale: bool) -> f64 {
+    if on_log_scale {
+        fx
+    } else {
+        fx.ln()
+    }
+}
+
+// Univariate target on the unconstrained scale, i.e., the log density of the wrapped target at
+// the constrained value plus the log Jacobian of the transform.
+#[derive(Debug, Clone, Copy)]
+pub struct Transformed<T, R> {
+    pub target: T,
+    pub transform: R,
+}
+
+impl<T: UnivariateTarget, R: ScalarTransform> UnivariateTarget for Transformed<T, R> {
+    fn evaluate(&mut self, y: f64) -> f64 {
+        let x = self.transform.constrain(y);
+        let log_density = log_scale(self.target.evaluate(x), self.target.on_log_scale());
+        if log_density == f64::NEG_INFINITY {
+            return log_density;
+        }
+        log_density + self.transform.log_jacobian(y)
+    }
+}
+
+// Multivariate target on the unconstrained scale, i.e., the log density of the wrapped target at
+// the constrained value plus the log absolute determinant of the Jacobian of the transform.
+#[derive(Debug, Clone)]
+pub struct TransformedVector<T, R> {
+    target: T,
+    transform: R,
+    constrained: Vec<f64>,
+}
+
+impl<T: MultivariateTarget, R: VectorTransform> TransformedVector<T, R> {
+    pub fn new(target: T, transform: R) -> Self {
+        let constrained = vec![0.0; transform.constrained_dimension()];
+        Self {
+            target,
+            transform,
+            constrained,
+        }
+    }
+    pub fn constrain(&self, y: &[f64]) -> Vec<f64> {
+        let mut x = vec![0.0; self.transform.constrained_dimension()];
+        self.transform.constrain(y, &mut x);
+        x
+    }
+    pub fn unconstrain(&self, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0.0; self.transform.unconstrained_dimension()];
+        self.transform.unconstrain(x, &mut y);
+        y
+    }
+}
+
+impl<T: MultivariateTarget, R: VectorTransform> MultivariateTarget for TransformedVector<T, R> {
+    fn dimension(&self) -> usize {
+        self.transform.unconstrained_dimension()
+    }
+    fn evaluate(&mut self, y: &[f64]) -> f64 {
+        let log_jacobian = self.transform.constrain(y, &mut self.constrained);
+        let fx = self.target.evaluate(&self.constrained);
+        let log_density = log_scale(fx, self.target.on_log_scale());
+        if log_density == f64::NEG_INFINITY {
+            return log_density;
+        }
+        log_density + log_jacobian
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::targets::{Beta, Dirichlet, Funnel, Gamma, Normal};
+    use crate::univariate::stepping_out::{
+        univariate_slice_sampler_stepping_out_and_shrinkage, TuningParameters,
+    };
+
+    fn sample_mean<R: ScalarTransform + Copy, T: UnivariateTarget>(target: T, transform: R) -> f64 {
+        let mut transformed = Transformed { target, transform };
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(79));
+        let mut y = 0.0;
+        let mut sum = 0.0;
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (y, _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                y,
+                |y| transformed.evaluate(y),
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum += transform.constrain(y);
+        }
+        sum / (n_samples as f64)
+    }
+
+    #[test]
+    fn test_scalar_transforms() {
+        let mean = sample_mean(
+            Gamma {
+                shape: 2.0,
+                rate: 4.0,
+            },
+            Positive,
+        );
+        assert!((mean - 0.5).abs() < 0.01);
+        let mean = sample_mean(
+            Beta {
+                alpha: 2.0,
+                beta: 6.0,
+            },
+            Interval(0.0, 1.0),
+        );
+        assert!((mean - 0.25).abs() < 0.01);
+        let interval = Interval(-1.0, 3.0);
+        assert!((interval.constrain(interval.unconstrain(2.5)) - 2.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_simplex() {
+        let mut target = TransformedVector::new(
+            Dirichlet {
+                alpha: vec![2.0, 3.0, 5.0],
+            },
+            Simplex { dimension: 3 },
+        );
+        let x = [0.2, 0.5, 0.3];
+        let y = target.unconstrain(&x);
+        assert_eq!(y.len(), 2);
+        for (a, b) in target.constrain(&y).iter().zip(x) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        for a in target.constrain(&[0.0, 0.0]) {
+            assert!((a - 1.0 / 3.0).abs() < 1e-12);
+        }
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(83));
+        let mut y = vec![0.0; 2];
+        let mut sums = [0.0; 3];
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            for i in 0..2 {
+                let mut z = y.clone();
+                (y[i], _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                    y[i],
+                    |yi| {
+                        z[i] = yi;
+                        target.evaluate(&z)
+                    },
+                    true,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+            }
+            for (sum, x) in sums.iter_mut().zip(target.constrain(&y)) {
+                *sum += x;
+            }
+        }
+        for (sum, mean) in sums.iter().zip([0.2, 0.3, 0.5]) {
+            assert!((sum / (n_samples as f64) - mean).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_ordered() {
+        let transform = Ordered { dimension: 3 };
+        let mut x = [0.0; 3];
+        let log_jacobian = transform.constrain(&[1.0, 0.0, 2.0f64.ln()], &mut x);
+        assert_eq!(x, [1.0, 2.0, 4.0]);
+        assert!((log_jacobian - 2.0f64.ln()).abs() < 1e-12);
+        let mut y = [0.0; 3];
+        transform.unconstrain(&x, &mut y);
+        assert!((y[2] - 2.0f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cholesky_covariance() {
+        let transform = CholeskyCovariance { dimension: 3 };
+        let x = [4.0, 2.0, 0.4, 2.0, 5.0, 1.0, 0.4, 1.0, 3.0];
+        let mut y = [0.0; 6];
+        transform.unconstrain(&x, &mut y);
+        let mut z = [0.0; 9];
+        transform.constrain(&y, &mut z);
+        for (a, b) in z.iter().zip(x) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_non_centered_funnel() {
+        let transform = NonCentered {
+            dimension: 3,
+            indices: vec![1, 2],
+            location_and_scale: |y: &[f64]| (0.0, (0.5 * y[0]).exp()),
+        };
+        let x = [2.0, 1.5, -0.5];
+        let y = {
+            let mut y = [0.0; 3];
+            transform.unconstrain(&x, &mut y);
+            y
+        };
+        let mut z = [0.0; 3];
+        let log_jacobian = transform.constrain(&y, &mut z);
+        assert!((log_jacobian - 2.0).abs() < 1e-12);
+        for (a, b) in z.iter().zip(x) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        // On the non-centered scale the funnel is a product of independent normals
+        let mut target = TransformedVector::new(Funnel { dimension: 3 }, transform);
+        let mut standard = Normal {
+            mean: 0.0,
+            standard_deviation: 1.0,
+        };
+        let expected = standard.evaluate(y[0] / 3.0) - 3.0f64.ln()
+            + standard.evaluate(y[1])
+            + standard.evaluate(y[2]);
+        assert!((target.evaluate(&y) - expected).abs() < 1e-12);
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(89));
+        let mut y = vec![0.0; 3];
+        let mut sum_of_squares = 0.0;
+        let n_samples = 20_000;
+        for _ in 0..n_samples {
+            for i in 0..3 {
+                let mut z = y.clone();
+                (y[i], _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                    y[i],
+                    |yi| {
+                        z[i] = yi;
+                        target.evaluate(&z)
+                    },
+                    true,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+            }
+            sum_of_squares += y[0] * y[0];
+        }
+        assert!((sum_of_squares / (n_samples as f64) - 9.0).abs() < 0.5);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::BudgetExhausted;
+use crate::math::{default_rng, ln};
+use crate::univariate::stepping_out::TuningParameters;
+use crate::univariate::{compare_on_log_scale, StepLimit};
+use core::future::Future;
+
+// Unnormalized density of a univariate target whose evaluation is awaited, e.g., a likelihood
+// computed by another process, a GPU service, or over the network. It is evaluated on the log
+// scale unless on_log_scale returns false.
+pub trait AsyncUnivariateTarget {
+    fn evaluate(&mut self, x: f64) -> impl Future<Output = f64>;
+    fn on_log_scale(&self) -> bool {
+        true
+    }
+}
+
+// Counts target evaluations against the evaluation budget (if any).
+struct Counter {
+    evaluations: u64,
+    max_evaluations: Option<u64>,
+}
+
+impl Counter {
+    async fn evaluate<T: AsyncUnivariateTarget + ?Sized>(
+        &mut self,
+        target: &mut T,
+        x: f64,
+    ) -> Result<f64, BudgetExhausted> {
+        if Some(self.evaluations) == self.max_evaluations {
+            return Err(BudgetExhausted);
+        }
+        self.evaluations += 1;
+        Ok(target.evaluate(x).await)
+    }
+}
+
+// Neal (2003) univariate slice sampler using the stepping out and shrinkage procedures for a
+// target whose evaluation is awaited, so that many chains can wait on expensive evaluations
+// without a thread each. The current state is returned unchanged if the evaluation budget (if
+// any) is exhausted.
+pub async fn univariate_slice_sampler_stepping_out_and_shrinkage<
+    T: AsyncUnivariateTarget + ?Sized,
+>(
+    x: f64,
+    target: &mut T,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let mut counter = Counter {
+        evaluations: 0,
+        max_evaluations: tuning_parameters.max_evaluations(),
+    };
+    match stepping_out_and_shrinkage(x, target, tuning_parameters, &mut counter, rng).await {
+        Ok(x1) => (x1, counter.evaluations),
+        Err(BudgetExhausted) => (x, counter.evaluations),
+    }
+}
+
+async fn stepping_out_and_shrinkage<T: AsyncUnivariateTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    tuning_parameters: &TuningParameters,
+    counter: &mut Counter,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<f64, BudgetExhausted> {
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let w = tuning_parameters.width();
+    // Step 1 (slice, keeping the state if its density on the natural scale is zero or infinite)
+    let fx = counter.evaluate(target, x).await?;
+    if compare_on_log_scale(fx, on_log_scale).is_none() {
+        return Ok(x);
+    }
+    let y = if on_log_scale {
+        ln(rng.f64()) + fx
+    } else {
+        rng.f64() * fx
+    };
+    // Step 2 (stepping out from a randomly positioned initial interval)
+    let mut l = x - rng.f64() * w;
+    let mut r = l + w;
+    let (mut j, mut k) = match tuning_parameters.max_number_of_steps() {
+        StepLimit::Unlimited => (None, None),
+        StepLimit::None | StepLimit::Max(1) => (Some(0), Some(0)),
+        StepLimit::Max(m) => {
+            let j = ((rng.f64() * (m as f64)) as u32).min(m - 1);
+            (Some(j), Some(m - 1 - j))
+        }
+    };
+    while j != Some(0) && y < counter.evaluate(target, l).await? {
+        l -= w;
+        j = j.map(|j| j - 1);
+    }
+    while k != Some(0) && y < counter.evaluate(target, r).await? {
+        r += w;
+        k = k.map(|k| k - 1);
+    }
+    // Step 3 (shrinkage)
+    loop {
+        let x1 = l + rng.f64() * (r - l);
+        if y < counter.evaluate(target, x1).await? {
+            return Ok(x1);
+        }
+        // The interval has collapsed onto the current state (possible only if the target is not a
+        // deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return Ok(x);
+        }
+        if x1 < x {
+            l = x1;
+        } else {
+            r = x1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    // Target whose evaluation is pending once before completing, as a remote call would be.
+    struct Remote;
+
+    impl AsyncUnivariateTarget for Remote {
+        async fn evaluate(&mut self, x: f64) -> f64 {
+            let mut pending = true;
+            core::future::poll_fn(|context| {
+                if pending {
+                    pending = false;
+                    context.waker().wake_by_ref();
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            })
+            .await;
+            -0.5 * x * x
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_normal() {
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(137));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            (x, _) = block_on(univariate_slice_sampler_stepping_out_and_shrinkage(
+                x,
+                &mut Remote,
+                &tuning_parameters,
+                &mut rng,
+            ));
+            sum += x;
+            sum_of_squares += x * x;
+        }
+        let mean = sum / (n_samples as f64);
+        assert!(mean.abs() < 0.05);
+        assert!((sum_of_squares / (n_samples as f64) - mean * mean - 1.0).abs() < 0.05);
+    }
+}
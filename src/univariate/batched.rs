@@ -0,0 +1,217 @@
+use crate::error::BudgetExhausted;
+use crate::math::{default_rng, ln};
+use crate::target::UnivariateTarget;
+use crate::univariate::stepping_out::TuningParameters;
+use crate::univariate::{compare_on_log_scale, StepLimit};
+
+// Counts target evaluations against the evaluation budget (if any).
+struct Counter {
+    evaluations: u64,
+    max_evaluations: Option<u64>,
+}
+
+impl Counter {
+    // Reserves up to n evaluations, returning how many the budget allows.
+    fn reserve(&mut self, n: usize) -> Result<usize, BudgetExhausted> {
+        let n = match self.max_evaluations {
+            Some(max) => n.min((max - self.evaluations).try_into().unwrap_or(usize::MAX)),
+            None => n,
+        };
+        if n == 0 {
+            return Err(BudgetExhausted);
+        }
+        self.evaluations += n as u64;
+        Ok(n)
+    }
+    fn evaluate<T: UnivariateTarget + ?Sized>(
+        &mut self,
+        target: &mut T,
+        x: f64,
+    ) -> Result<f64, BudgetExhausted> {
+        self.reserve(1)?;
+        Ok(target.evaluate(x))
+    }
+}
+
+// Steps out from end by step until a point outside the slice is found or the remaining number of
+// steps (if limited) is used up, evaluating up to N candidate endpoints per call to
+// evaluate_batch. Candidates beyond the first point outside the slice are evaluated
+// speculatively and discarded.
+fn step_out<const N: usize, T: UnivariateTarget + ?Sized>(
+    target: &mut T,
+    y: f64,
+    mut end: f64,
+    step: f64,
+    mut remaining: Option<u32>,
+    counter: &mut Counter,
+) -> Result<f64, BudgetExhausted> {
+    let mut xs = [0.0; N];
+    let mut fxs = [0.0; N];
+    loop {
+        let n = remaining.map_or(N, |k| (k as usize).min(N));
+        if n == 0 {
+            return Ok(end);
+        }
+        let n = counter.reserve(n)?;
+        for (i, z) in xs[..n].iter_mut().enumerate() {
+            *z = end + (i as f64) * step;
+        }
+        target.evaluate_batch(&xs[..n], &mut fxs[..n]);
+        if let Some(i) = fxs[..n].iter().position(|&fx| fx <= y || fx.is_nan()) {
+            return Ok(xs[i]);
+        }
+        end += (n as f64) * step;
+        remaining = remaining.map(|k| k - n as u32);
+    }
+}
+
+// Neal (2003) univariate slice sampler using the stepping out and shrinkage procedures, where
+// stepping out evaluates the target at N candidate endpoints per call to evaluate_batch, which
+// pays off for targets with a fast batched evaluation. Speculative candidates count as target
+// evaluations. The current state is returned unchanged if the evaluation budget (if any) is
+// exhausted.
+pub fn sample_batched<const N: usize, T: UnivariateTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    assert!(N > 0, "batches must contain at least one point");
+    let mut counter = Counter {
+        evaluations: 0,
+        max_evaluations: tuning_parameters.max_evaluations(),
+    };
+    match batched_stepping_out_and_shrinkage::<N, T>(
+        x,
+        target,
+        tuning_parameters,
+        &mut counter,
+        rng,
+    ) {
+        Ok(x1) => (x1, counter.evaluations),
+        Err(BudgetExhausted) => (x, counter.evaluations),
+    }
+}
+
+fn batched_stepping_out_and_shrinkage<const N: usize, T: UnivariateTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    tuning_parameters: &TuningParameters,
+    counter: &mut Counter,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<f64, BudgetExhausted> {
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let on_log_scale = target.on_log_scale();
+    let w = tuning_parameters.width();
+    // Step 1 (slice, keeping the state if its density on the natural scale is zero or infinite)
+    let fx = counter.evaluate(target, x)?;
+    if compare_on_log_scale(fx, on_log_scale).is_none() {
+        return Ok(x);
+    }
+    let y = if on_log_scale {
+        ln(rng.f64()) + fx
+    } else {
+        rng.f64() * fx
+    };
+    // Step 2 (stepping out from a randomly positioned initial interval)
+    let l = x - rng.f64() * w;
+    let r = l + w;
+    let (left_steps, right_steps) = match tuning_parameters.max_number_of_steps() {
+        StepLimit::Unlimited => (None, None),
+        StepLimit::None | StepLimit::Max(1) => (Some(0), Some(0)),
+        StepLimit::Max(m) => {
+            let j = ((rng.f64() * (m as f64)) as u32).min(m - 1);
+            (Some(j), Some(m - 1 - j))
+        }
+    };
+    let mut l = step_out::<N, T>(target, y, l, -w, left_steps, counter)?;
+    let mut r = step_out::<N, T>(target, y, r, w, right_steps, counter)?;
+    // Step 3 (shrinkage)
+    loop {
+        let x1 = l + rng.f64() * (r - l);
+        if y < counter.evaluate(target, x1)? {
+            return Ok(x1);
+        }
+        // The interval has collapsed onto the current state (possible only if the target is not a
+        // deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return Ok(x);
+        }
+        if x1 < x {
+            l = x1;
+        } else {
+            r = x1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingBatches {
+        calls: u64,
+        points: u64,
+    }
+
+    impl UnivariateTarget for CountingBatches {
+        fn evaluate(&mut self, x: f64) -> f64 {
+            -0.5 * x * x
+        }
+        fn evaluate_batch(&mut self, xs: &[f64], out: &mut [f64]) {
+            self.calls += 1;
+            self.points += xs.len() as u64;
+            for (x, fx) in xs.iter().zip(out) {
+                *fx = self.evaluate(*x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_normal() {
+        let tuning_parameters = TuningParameters::builder().width(0.1).build().unwrap();
+        let mut target = CountingBatches {
+            calls: 0,
+            points: 0,
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(127));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            (x, _) = sample_batched::<8, _>(x, &mut target, &tuning_parameters, &mut rng);
+            sum += x;
+            sum_of_squares += x * x;
+        }
+        let mean = sum / (n_samples as f64);
+        assert!(mean.abs() < 0.05);
+        assert!((sum_of_squares / (n_samples as f64) - mean * mean - 1.0).abs() < 0.05);
+        // A narrow width needs many steps, which are taken several at a time
+        assert!(target.points > 4 * target.calls);
+    }
+
+    #[test]
+    fn test_batched_budget() {
+        let tuning_parameters = TuningParameters::builder()
+            .width(0.01)
+            .max_evaluations(5)
+            .build()
+            .unwrap();
+        let mut target = CountingBatches {
+            calls: 0,
+            points: 0,
+        };
+        let (x, evaluations) =
+            sample_batched::<8, _>(0.5, &mut target, &tuning_parameters, &mut None);
+        assert_eq!((x, evaluations), (0.5, 5));
+        assert_eq!(target.points, 4);
+    }
+}
@@ -1,134 +1,200 @@
-#[derive(Debug)]
-pub struct TuningParameters {
-    initial_width: f64,
-    max_number_of_doubles: u32,
+use crate::error::{
+    budgeted_target, check_bounds, check_degenerate, check_start, checked_target, BudgetExhausted,
+    SliceError,
+};
+use crate::univariate::expansion::{expansion_and_shrinkage, Doubling};
+use crate::univariate::{tuning, Phases, StepLimit};
+
+// Settings specific to doubling, extending the common tuning parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DoublingExtension {
+    max_plateau_rejections: u32,
+    skip_acceptance_check: bool,
 }
 
+// Tuning parameters of doubling, which are the common ones extended by DoublingExtension.
+pub type TuningParameters = tuning::TuningParameters<DoublingExtension>;
+pub type TuningParametersBuilder = tuning::TuningParametersBuilder<DoublingExtension>;
+
 impl TuningParameters {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn max_number_of_doubles(&self) -> StepLimit {
+        self.max_number_of_expansions()
     }
-    pub fn width(self, value: f64) -> Self {
-        Self {
-            initial_width: value,
-            ..self
-        }
+    pub fn max_plateau_rejections(&self) -> u32 {
+        self.extension().max_plateau_rejections
     }
-    pub fn max_number_of_doubles(self, value: u32) -> Self {
-        Self {
-            max_number_of_doubles: value,
-            ..self
-        }
+    pub fn skip_acceptance_check_for_unimodal(&self) -> bool {
+        self.extension().skip_acceptance_check
     }
 }
 
-impl Default for TuningParameters {
-    fn default() -> Self {
-        TuningParameters {
-            initial_width: 1.0,
-            max_number_of_doubles: 0,
-        }
+impl TuningParametersBuilder {
+    pub fn max_number_of_doubles<L: Into<StepLimit>>(self, value: L) -> Self {
+        self.max_number_of_expansions(value)
+    }
+    // Leaves the interval unshrunk after each of the first value rejected proposals at which the
+    // density is zero, so that when a doubled interval spans a disconnected slice, e.g., two
+    // modes separated by a zero-density gap, proposals in the gap do not shrink the far mode out
+    // of reach. Shrinkage resumes after value such rejections, so that the update still ends if
+    // the slice is tiny relative to the interval. Rejections at positive density always shrink.
+    pub fn max_plateau_rejections(mut self, value: u32) -> Self {
+        self.extension_mut().max_plateau_rejections = value;
+        self
+    }
+    // Skips the acceptance check after shrinkage, which Neal (2003) notes is unnecessary when the
+    // target is unimodal. Only use this when unimodality is guaranteed, since otherwise the
+    // sampler no longer leaves the target invariant.
+    pub fn skip_acceptance_check_for_unimodal(mut self) -> Self {
+        self.extension_mut().skip_acceptance_check = true;
+        self
     }
 }
 
-// Neal (2003) univariate slice sampler using the doubling and shrinkage procedures
+// Neal (2003) univariate slice sampler using the doubling and shrinkage procedures, where the
+// current state is returned unchanged if the evaluation budget (if any) is exhausted. With bounds
+// in the tuning parameters, the target is treated as zero outside them, and the sampler panics
+// if they do not contain x.
 pub fn univariate_slice_sampler_doubling_and_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let (x1, evaluation_counter, _) = doubling_and_shrinkage_with_interval(
+        x,
+        f,
+        on_log_scale,
+        tuning_parameters.width(),
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    );
+    (x1, evaluation_counter)
+}
+
+// Like univariate_slice_sampler_doubling_and_shrinkage, but also returning the interval from
+// which the new state was drawn ([x, x] if the budget is exhausted), e.g., for custom width
+// adaptation.
+pub fn univariate_slice_sampler_doubling_and_shrinkage_with_interval<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64, (f64, f64)) {
+    doubling_and_shrinkage_with_interval(
+        x,
+        f,
+        on_log_scale,
+        tuning_parameters.width(),
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    )
+}
+
+// Like univariate_slice_sampler_doubling_and_shrinkage_with_interval, but with the initial
+// width w in place of the one in the tuning parameters.
+pub(crate) fn doubling_and_shrinkage_with_interval<S: FnMut(f64) -> f64>(
     x: f64,
     mut f: S,
     on_log_scale: bool,
+    w: f64,
     tuning_parameters: &TuningParameters,
+    phases: &mut Phases,
     rng: &mut Option<fastrand::Rng>,
-) -> (f64, u32) {
-    let w = if tuning_parameters.initial_width <= 0.0 {
-        f64::MIN_POSITIVE
-    } else {
-        tuning_parameters.initial_width
-    };
-    let mut maybe;
-    let rng = match rng {
-        Some(rng) => rng,
-        None => {
-            maybe = fastrand::Rng::new();
-            &mut maybe
-        }
-    };
-    let mut u = || rng.f64();
-    let mut evaluation_counter = 0;
-    let mut f_with_counter = |x: f64| {
-        evaluation_counter += 1;
-        f(x)
-    };
-    // Step 1 (slice)
-    let y = {
-        let fx = f_with_counter(x);
-        if on_log_scale {
-            u().ln() + fx
-        } else {
-            u() * fx
-        }
-    };
-    // Step 2 (doubling, unless max_number_of_steps == 1)
-    let mut l = x - u() * w;
-    let mut r = l + w;
-    match tuning_parameters.max_number_of_doubles {
-        0 => {
-            while y < f_with_counter(l) && y < f_with_counter(r) {
-                let w = r - l;
-                if u() < 0.5 {
-                    l -= w;
-                } else {
-                    r += w;
-                }
-            }
-        }
-        1 => {}
-        _ => {
-            let mut k = tuning_parameters.max_number_of_doubles;
-            while k > 0 && (y < f_with_counter(l) || y < f_with_counter(r)) {
-                k -= 1;
-                let w = r - l;
-                if u() < 0.5 {
-                    l -= w;
-                } else {
-                    r += w;
-                }
-            }
+) -> (f64, u64, (f64, f64)) {
+    if let Some((lower, upper)) = tuning_parameters.bounds() {
+        if let Err(error) = check_bounds(x, lower, upper) {
+            panic!("{}", error);
         }
     }
-    // Step 3 (shrinkage)
-    loop {
-        let x1 = l + u() * (r - l);
-        let fx1 = f_with_counter(x1);
-        if y < fx1 {
-            let mut lp = l;
-            let mut rp = r;
-            let mut d = false;
-            let mut accept = true;
-            while rp - lp > 1.1 * w {
-                let m = (lp + rp) / 2.0;
-                if (x < m && x1 >= m) || (x >= m && x1 < m) {
-                    d = true;
-                }
-                if x1 < m {
-                    rp = m;
-                } else {
-                    lp = m;
-                }
-                if d && y >= f_with_counter(lp) && y >= f_with_counter(rp) {
-                    accept = false;
-                    break;
-                }
-            }
-            if accept {
-                return (x1, evaluation_counter);
-            }
-        }
-        if x1 < x {
-            l = x1;
-        } else {
-            r = x1;
-        }
+    let result = doubling_and_shrinkage(
+        x,
+        budgeted_target(
+            |x| Ok(f(x)),
+            tuning_parameters.max_evaluations(),
+            BudgetExhausted,
+        ),
+        on_log_scale,
+        w,
+        tuning_parameters,
+        phases,
+        rng,
+    );
+    match result {
+        Ok(value) => value,
+        Err(BudgetExhausted) => (x, tuning_parameters.max_evaluations().unwrap_or(0), (x, x)),
+    }
+}
+
+// Like univariate_slice_sampler_doubling_and_shrinkage, but reports a non-finite or
+// zero-density starting point, bounds that do not contain it, NaN target values, and an exhausted
+// evaluation budget as errors.
+pub fn try_univariate_slice_sampler_doubling_and_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let w = tuning_parameters.width();
+    let x = check_start(x)?;
+    if let Some((lower, upper)) = tuning_parameters.bounds() {
+        check_bounds(x, lower, upper)?;
     }
+    doubling_and_shrinkage(
+        x,
+        budgeted_target(
+            checked_target(f, on_log_scale),
+            tuning_parameters.max_evaluations(),
+            SliceError::EvaluationBudgetExhausted(tuning_parameters.max_evaluations().unwrap_or(0)),
+        ),
+        on_log_scale,
+        w,
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    )
+    .and_then(|result| check_degenerate(x, result, tuning_parameters.min_width()))
+}
+
+fn doubling_and_shrinkage<E, S: FnMut(f64) -> Result<f64, E>>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    tuning_parameters: &TuningParameters,
+    phases: &mut Phases,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64, (f64, f64)), E> {
+    let strategy = Doubling::new(
+        tuning_parameters.max_number_of_doubles(),
+        tuning_parameters.skip_acceptance_check_for_unimodal(),
+    );
+    // The target is zero outside the bounds (if any)
+    let (lower, upper) = tuning_parameters
+        .bounds()
+        .unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+    let zero = if on_log_scale { f64::NEG_INFINITY } else { 0.0 };
+    expansion_and_shrinkage(
+        x,
+        |z| {
+            if z < lower || z > upper {
+                Ok(zero)
+            } else {
+                f(z)
+            }
+        },
+        on_log_scale,
+        w,
+        tuning_parameters.min_width().unwrap_or(0.0),
+        tuning_parameters.max_plateau_rejections(),
+        &strategy,
+        phases,
+        rng,
+    )
 }
 
 #[cfg(test)]
@@ -139,7 +205,7 @@ mod tests {
     fn test_triangle_distribution() {
         let mut sum = 0.0;
         let n_samples = 100_000;
-        let tuning_parameters = TuningParameters::new().width(1.);
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
         let mut x = 0.5;
         let mut total_calls = 0;
         for _ in 0..n_samples {
@@ -165,4 +231,122 @@ mod tests {
         println!("{}", (total_calls as f64) / (n_samples as f64));
         assert!(diff < 0.01);
     }
+
+    #[test]
+    fn test_no_expansion() {
+        // Without expansion the draw stays within the initial interval of width w around x
+        let tuning_parameters = TuningParameters::builder()
+            .width(0.5)
+            .max_number_of_doubles(StepLimit::None)
+            .build()
+            .unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(5));
+        for _ in 0..1_000 {
+            let (x, _) = univariate_slice_sampler_doubling_and_shrinkage(
+                0.0,
+                |x| -0.5 * x * x,
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+            assert!(x.abs() < 0.5);
+        }
+    }
+
+    // Runs on two triangular modes at -10 and 10 separated by a zero-density gap, returning the
+    // second moment and the number of moves between the modes.
+    fn two_far_modes(tuning_parameters: &TuningParameters, seed: u64) -> (f64, u32) {
+        let mut rng = Some(fastrand::Rng::with_seed(seed));
+        let mut x = -10.0;
+        let mut sum_of_squares = 0.0;
+        let mut switches = 0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            let previous = x;
+            (x, _) = univariate_slice_sampler_doubling_and_shrinkage(
+                x,
+                |x| (1.0 - (x.abs() - 10.0).abs()).max(0.0),
+                false,
+                tuning_parameters,
+                &mut rng,
+            );
+            if previous * x < 0.0 {
+                switches += 1;
+            }
+            sum_of_squares += x * x;
+        }
+        (sum_of_squares / (n_samples as f64), switches)
+    }
+
+    #[test]
+    fn test_max_plateau_rejections() {
+        let shrinking = TuningParameters::builder().width(40.0).build().unwrap();
+        let splitting = TuningParameters::builder()
+            .width(40.0)
+            .max_plateau_rejections(100)
+            .build()
+            .unwrap();
+        assert_eq!(splitting.max_plateau_rejections(), 100);
+        let (_, shrinking_switches) = two_far_modes(&shrinking, 31);
+        let (second_moment, splitting_switches) = two_far_modes(&splitting, 31);
+        assert!((second_moment - (100.0 + 1.0 / 6.0)).abs() < 0.5);
+        assert!(splitting_switches > 3 * shrinking_switches);
+    }
+
+    #[test]
+    fn test_skip_acceptance_check_for_unimodal() {
+        let tuning_parameters = TuningParameters::builder()
+            .width(0.1)
+            .skip_acceptance_check_for_unimodal()
+            .build()
+            .unwrap();
+        assert!(tuning_parameters.skip_acceptance_check_for_unimodal());
+        let mut rng = Some(fastrand::Rng::with_seed(17));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            (x, _) = univariate_slice_sampler_doubling_and_shrinkage(
+                x,
+                |x| -0.5 * x * x,
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum += x;
+            sum_of_squares += x * x;
+        }
+        let mean = sum / (n_samples as f64);
+        let variance = sum_of_squares / (n_samples as f64) - mean * mean;
+        assert!(mean.abs() < 0.05);
+        assert!((variance - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_endpoints_evaluated_once() {
+        let tuning_parameters = TuningParameters::builder()
+            .width(0.01)
+            .skip_acceptance_check_for_unimodal()
+            .build()
+            .unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(23));
+        for _ in 0..100 {
+            let mut points = Vec::new();
+            univariate_slice_sampler_doubling_and_shrinkage(
+                0.0,
+                |x| {
+                    points.push(x);
+                    -0.5 * x * x
+                },
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+            let n_points = points.len();
+            points.sort_by(f64::total_cmp);
+            points.dedup();
+            assert_eq!(points.len(), n_points);
+        }
+    }
 }
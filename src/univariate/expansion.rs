@@ -0,0 +1,556 @@
+use crate::error::{check_width, TuningError};
+use crate::math::{default_rng, ln};
+use crate::univariate::{compare_on_log_scale, Observer, Phases, StepLimit};
+
+// Procedure for expanding the initial interval around the current state so that it brackets the
+// slice {z : y < f(z)}, together with the acceptance check (if any) that makes shrinkage from the
+// expanded interval leave the target invariant. Implementations should evaluate the target only
+// through f and draw uniforms only through u.
+pub trait ExpansionStrategy {
+    // Expands the initial interval, which contains x, returning the expanded interval.
+    fn expand<E, F, U>(
+        &self,
+        x: f64,
+        y: f64,
+        interval: (f64, f64),
+        f: &mut F,
+        u: &mut U,
+    ) -> Result<(f64, f64), E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+        U: FnMut() -> f64;
+
+    // Whether x1, a point in the slice drawn from the interval expanded from x with initial width
+    // w, is accepted, i.e., whether expanding from x1 could have produced the same interval.
+    fn accepts<E, F>(
+        &self,
+        _x: f64,
+        _x1: f64,
+        _y: f64,
+        _w: f64,
+        _interval: (f64, f64),
+        _f: &mut F,
+    ) -> Result<bool, E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+    {
+        Ok(true)
+    }
+}
+
+// Neal's (2003) stepping out, adding intervals of the initial width to each side in turn, so
+// the cost grows linearly as the width underestimates the slice. Doubling instead adds w, 2 w,
+// 4 w, ... on randomly chosen sides, at logarithmic cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SteppingOut {
+    max_number_of_steps: StepLimit,
+}
+
+impl SteppingOut {
+    pub fn new(max_number_of_steps: StepLimit) -> Self {
+        Self {
+            max_number_of_steps,
+        }
+    }
+}
+
+impl ExpansionStrategy for SteppingOut {
+    fn expand<E, F, U>(
+        &self,
+        _x: f64,
+        y: f64,
+        (mut l, mut r): (f64, f64),
+        f: &mut F,
+        u: &mut U,
+    ) -> Result<(f64, f64), E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+        U: FnMut() -> f64,
+    {
+        let w = r - l;
+        match self.max_number_of_steps {
+            StepLimit::Unlimited => {
+                while y < f(l)? {
+                    l -= w
+                }
+                while y < f(r)? {
+                    r += w
+                }
+            }
+            // A single step is the initial interval, so no uniform is drawn to split it
+            StepLimit::None | StepLimit::Max(1) => {}
+            StepLimit::Max(m) => {
+                // The float-to-integer cast saturates, so clamping keeps j + k = m - 1 even when
+                // u() * m rounds up to m.
+                let mut j = ((u() * (m as f64)) as u32).min(m.saturating_sub(1));
+                let mut k = m.saturating_sub(1) - j;
+                while j > 0 && y < f(l)? {
+                    l -= w;
+                    j -= 1;
+                }
+                while k > 0 && y < f(r)? {
+                    r += w;
+                    k -= 1;
+                }
+            }
+        }
+        Ok((l, r))
+    }
+}
+
+// Neal's (2003) doubling, doubling the interval on a randomly chosen side until both endpoints
+// are outside the slice, with the acceptance check optionally skipped for unimodal targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Doubling {
+    max_number_of_doubles: StepLimit,
+    skip_acceptance_check: bool,
+}
+
+impl Doubling {
+    pub fn new(max_number_of_doubles: StepLimit, skip_acceptance_check: bool) -> Self {
+        Self {
+            max_number_of_doubles,
+            skip_acceptance_check,
+        }
+    }
+}
+
+impl ExpansionStrategy for Doubling {
+    fn expand<E, F, U>(
+        &self,
+        _x: f64,
+        y: f64,
+        (mut l, mut r): (f64, f64),
+        f: &mut F,
+        u: &mut U,
+    ) -> Result<(f64, f64), E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+        U: FnMut() -> f64,
+    {
+        let mut remaining = remaining(self.max_number_of_doubles);
+        // Endpoint values are cached so only the endpoint that moved is evaluated again
+        let mut in_slice = |cache: &mut Option<f64>, z: f64| {
+            let fz = match *cache {
+                Some(fz) => fz,
+                None => f(z)?,
+            };
+            *cache = Some(fz);
+            Ok(y < fz)
+        };
+        let mut fl = None;
+        let mut fr = None;
+        while remaining != Some(0) && (in_slice(&mut fl, l)? || in_slice(&mut fr, r)?) {
+            remaining = remaining.map(|k| k - 1);
+            let w = r - l;
+            if u() < 0.5 {
+                l -= w;
+                fl = None;
+            } else {
+                r += w;
+                fr = None;
+            }
+        }
+        Ok((l, r))
+    }
+
+    // The check is only needed if the interval was doubled, i.e. is wider than w, where the factor
+    // 1.1 allows for rounding error, and the target may be multimodal.
+    fn accepts<E, F>(
+        &self,
+        x: f64,
+        x1: f64,
+        y: f64,
+        w: f64,
+        (mut lp, mut rp): (f64, f64),
+        f: &mut F,
+    ) -> Result<bool, E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+    {
+        if self.skip_acceptance_check {
+            return Ok(true);
+        }
+        let mut d = false;
+        while rp - lp > 1.1 * w {
+            let m = (lp + rp) / 2.0;
+            if (x < m && x1 >= m) || (x >= m && x1 < m) {
+                d = true;
+            }
+            if x1 < m {
+                rp = m;
+            } else {
+                lp = m;
+            }
+            if d && y >= f(lp)? && y >= f(rp)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+// Geometric growth by an integer factor: the interval is enlarged to factor times its width,
+// with the current interval placed uniformly at random among the factor equal blocks of the
+// enlarged interval, until both endpoints are outside the slice. A factor of 2 is doubling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometric {
+    factor: u32,
+    max_number_of_expansions: StepLimit,
+}
+
+impl Geometric {
+    pub fn new(factor: u32, max_number_of_expansions: StepLimit) -> Result<Self, TuningError> {
+        if factor < 2 {
+            return Err(TuningError::InvalidFactor(factor));
+        }
+        Ok(Self {
+            factor,
+            max_number_of_expansions,
+        })
+    }
+}
+
+impl ExpansionStrategy for Geometric {
+    fn expand<E, F, U>(
+        &self,
+        _x: f64,
+        y: f64,
+        (mut l, mut r): (f64, f64),
+        f: &mut F,
+        u: &mut U,
+    ) -> Result<(f64, f64), E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+        U: FnMut() -> f64,
+    {
+        let mut remaining = remaining(self.max_number_of_expansions);
+        let mut in_slice = |cache: &mut Option<f64>, z: f64| {
+            let fz = match *cache {
+                Some(fz) => fz,
+                None => f(z)?,
+            };
+            *cache = Some(fz);
+            Ok(y < fz)
+        };
+        let mut fl = None;
+        let mut fr = None;
+        while remaining != Some(0) && (in_slice(&mut fl, l)? || in_slice(&mut fr, r)?) {
+            remaining = remaining.map(|k| k - 1);
+            let w = r - l;
+            let blocks_to_left = ((u() * (self.factor as f64)) as u32).min(self.factor - 1);
+            if blocks_to_left > 0 {
+                l -= (blocks_to_left as f64) * w;
+                fl = None;
+            }
+            if blocks_to_left < self.factor - 1 {
+                r += ((self.factor - 1 - blocks_to_left) as f64) * w;
+                fr = None;
+            }
+        }
+        Ok((l, r))
+    }
+
+    // As for doubling, descending through the blocks containing x1 and rejecting if expansion
+    // from x1 would have stopped at a block not containing x.
+    fn accepts<E, F>(
+        &self,
+        x: f64,
+        x1: f64,
+        y: f64,
+        w: f64,
+        (mut lp, mut rp): (f64, f64),
+        f: &mut F,
+    ) -> Result<bool, E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+    {
+        let factor = self.factor as f64;
+        let mut d = false;
+        while rp - lp > 1.1 * w {
+            let block_width = (rp - lp) / factor;
+            let block = |z: f64| (((z - lp) / block_width) as u32).min(self.factor - 1) as f64;
+            let b1 = block(x1);
+            if block(x) != b1 {
+                d = true;
+            }
+            if b1 < factor - 1.0 {
+                rp = lp + (b1 + 1.0) * block_width;
+            }
+            lp += b1 * block_width;
+            if d && y >= f(lp)? && y >= f(rp)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn remaining(limit: StepLimit) -> Option<u32> {
+    match limit {
+        StepLimit::Unlimited => None,
+        StepLimit::None => Some(0),
+        StepLimit::Max(p) => Some(p),
+    }
+}
+
+// Neal (2003) univariate slice sampler using the given expansion strategy from an initial
+// interval of width w followed by shrinkage. Panics if w is not finite and positive.
+pub fn univariate_slice_sampler_expansion_and_shrinkage<X, S>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    strategy: &X,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64)
+where
+    X: ExpansionStrategy,
+    S: FnMut(f64) -> f64,
+{
+    if let Err(error) = check_width(w) {
+        panic!("{}", error);
+    }
+    let result = expansion_and_shrinkage(
+        x,
+        |x| Ok::<f64, core::convert::Infallible>(f(x)),
+        on_log_scale,
+        w,
+        0.0,
+        0,
+        strategy,
+        &mut Phases::default(),
+        rng,
+    );
+    match result {
+        Ok((x1, evaluation_counter, _)) => (x1, evaluation_counter),
+        Err(never) => match never {},
+    }
+}
+
+// Shared core of the expansion samplers, where shrinkage stops and returns the current state once
+// the interval is narrower than min_width (zero for no minimum), and where the first
+// max_plateau_rejections rejections at points of zero density leave the interval unshrunk.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn expansion_and_shrinkage<X, E, S, O>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    min_width: f64,
+    max_plateau_rejections: u32,
+    strategy: &X,
+    observer: &mut O,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64, (f64, f64)), E>
+where
+    X: ExpansionStrategy,
+    S: FnMut(f64) -> Result<f64, E>,
+    O: Observer,
+{
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let mut u = || rng.f64();
+    let mut evaluation_counter = 0;
+    // Step 1 (slice, compared on the log scale for a tiny density on the natural scale, which the
+    // observer then sees on the log scale too)
+    let fx = f(x)?;
+    evaluation_counter += 1;
+    let log_scale = match compare_on_log_scale(fx, on_log_scale) {
+        Some(log_scale) => log_scale,
+        None => return Ok((x, evaluation_counter, (x, x))),
+    };
+    let y = if on_log_scale {
+        ln(u()) + fx
+    } else if log_scale {
+        ln(u()) + ln(fx)
+    } else {
+        u() * fx
+    };
+    let mut f_with_counter = |x: f64| {
+        evaluation_counter += 1;
+        let fx = f(x)?;
+        Ok(if log_scale && !on_log_scale {
+            ln(fx)
+        } else {
+            fx
+        })
+    };
+    // Step 2 (expansion of a randomly positioned initial interval)
+    let l = x - u() * w;
+    observer.slice(y, (l, l + w));
+    let mut f_while_expanding = |z: f64| {
+        let fz = f_with_counter(z)?;
+        observer.expansion_evaluation(z, fz);
+        Ok(fz)
+    };
+    let (mut l, mut r) = strategy.expand(x, y, (l, l + w), &mut f_while_expanding, &mut u)?;
+    observer.expanded((l, r));
+    let mut plateau_rejections = max_plateau_rejections;
+    // Step 3 (shrinkage, with the strategy's acceptance check)
+    loop {
+        let x1 = l + u() * (r - l);
+        let fx1 = f_with_counter(x1)?;
+        let accepted = y < fx1 && strategy.accepts(x, x1, y, w, (l, r), &mut f_with_counter)?;
+        observer.proposal((l, r), x1, fx1, accepted);
+        if accepted {
+            return Ok((x1, evaluation_counter, (l, r)));
+        }
+        // The interval has collapsed onto the current state (possible only if the target is not a
+        // deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return Ok((x, evaluation_counter, (l, r)));
+        }
+        // Whether to shrink depends only on the rejected point and the number of such rejections
+        // so far, as it would had the update started from the accepted point, so skipping
+        // shrinkage at a zero-density point keeps the sampler valid
+        let zero_density = if on_log_scale || log_scale {
+            fx1 == f64::NEG_INFINITY
+        } else {
+            fx1 == 0.0
+        };
+        if zero_density && plateau_rejections > 0 {
+            plateau_rejections -= 1;
+            continue;
+        }
+        if x1 < x {
+            l = x1;
+        } else {
+            r = x1;
+        }
+        if r - l < min_width {
+            return Ok((x, evaluation_counter, (l, r)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometric_growth() {
+        let strategy = Geometric::new(3, StepLimit::Unlimited).unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(41));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            // A bimodal target exercises the acceptance check
+            (x, _) = univariate_slice_sampler_expansion_and_shrinkage(
+                x,
+                |x| {
+                    let a = -0.5 * (x - 2.0) * (x - 2.0);
+                    let b = -0.5 * (x + 2.0) * (x + 2.0);
+                    a.max(b) + (1.0 + (-(a - b).abs()).exp()).ln()
+                },
+                true,
+                0.1,
+                &strategy,
+                &mut rng,
+            );
+            sum += x;
+            sum_of_squares += x * x;
+        }
+        let mean = sum / (n_samples as f64);
+        let second_moment = sum_of_squares / (n_samples as f64);
+        assert!(mean.abs() < 0.1);
+        assert!((second_moment - 5.0).abs() < 0.1);
+        assert_eq!(
+            Geometric::new(1, StepLimit::Unlimited),
+            Err(TuningError::InvalidFactor(1))
+        );
+    }
+
+    #[test]
+    fn test_collapsed_interval() {
+        // After its first evaluation the target is zero everywhere, so no point is accepted and
+        // shrinkage collapses onto the current state
+        let mut first = true;
+        let f = |_: f64| {
+            let value = if first { 1.0 } else { 0.0 };
+            first = false;
+            value
+        };
+        let strategy = SteppingOut::new(StepLimit::Unlimited);
+        let mut rng = Some(fastrand::Rng::with_seed(109));
+        let (x, evaluations) = univariate_slice_sampler_expansion_and_shrinkage(
+            0.5, f, false, 1.0, &strategy, &mut rng,
+        );
+        assert_eq!(x, 0.5);
+        assert!(evaluations > 2);
+    }
+
+    #[test]
+    fn test_stepping_out_uniform_near_one() {
+        // u() * m rounds up to m for large m, which must not underflow the steps to the right
+        let strategy = SteppingOut::new(StepLimit::Max(u32::MAX));
+        let mut f = |x: f64| Ok::<f64, ()>(if x.abs() < 1.0 { 1.0 } else { 0.0 });
+        let mut u = || 1.0 - f64::EPSILON / 2.0;
+        let (l, r) = strategy
+            .expand(0.0, 0.5, (-0.5, 0.5), &mut f, &mut u)
+            .unwrap();
+        assert_eq!((l, r), (-1.5, 0.5));
+    }
+
+    #[test]
+    fn test_extreme_natural_densities() {
+        let strategy = SteppingOut::new(StepLimit::Unlimited);
+        let mut rng = Some(fastrand::Rng::with_seed(197));
+        // A density so small that u f(x) could underflow is compared on the log scale
+        let f = |x: f64| 1e-320 * (-0.5 * x * x).exp();
+        let mut x = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 20_000;
+        for _ in 0..n_samples {
+            (x, _) = univariate_slice_sampler_expansion_and_shrinkage(
+                x, f, false, 1.0, &strategy, &mut rng,
+            );
+            sum_of_squares += x * x;
+        }
+        assert!((sum_of_squares / (n_samples as f64) - 1.0).abs() < 0.05);
+        // A zero or infinite density at the state keeps the state rather than stepping out
+        // without end over a heavy-tailed support
+        let f = |x: f64| if x < 0.0 { 0.0 } else { 1.0 / (1.0 + x * x) };
+        let result = univariate_slice_sampler_expansion_and_shrinkage(
+            -1.0, f, false, 1.0, &strategy, &mut rng,
+        );
+        assert_eq!(result, (-1.0, 1));
+        let f = |x: f64| 1.0 / x.abs();
+        let result = univariate_slice_sampler_expansion_and_shrinkage(
+            0.0, f, false, 1.0, &strategy, &mut rng,
+        );
+        assert_eq!(result, (0.0, 1));
+        // Likewise for a log density that is not finite at the state
+        let mut rng = Some(fastrand::Rng::with_seed(3));
+        let f = |x: f64| {
+            if x < 0.0 {
+                f64::NEG_INFINITY
+            } else {
+                -(1.0 + x * x).ln()
+            }
+        };
+        let result = univariate_slice_sampler_expansion_and_shrinkage(
+            -0.5, f, true, 1.0, &strategy, &mut rng,
+        );
+        assert_eq!(result, (-0.5, 1));
+        for fx in [f64::INFINITY, f64::NAN] {
+            let result = univariate_slice_sampler_expansion_and_shrinkage(
+                0.0,
+                |_| fx,
+                true,
+                1.0,
+                &strategy,
+                &mut rng,
+            );
+            assert_eq!(result, (0.0, 1));
+        }
+    }
+}
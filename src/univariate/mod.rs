@@ -1,3 +1,94 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod batched;
 pub mod doubling;
+pub mod expansion;
+#[cfg(feature = "std")]
+pub mod parallel;
+pub mod retrospective;
 pub mod shrinkage;
 pub mod stepping_out;
+#[cfg(feature = "std")]
+pub mod subsampling;
+pub mod truncated;
+pub mod tuning;
+#[cfg(feature = "std")]
+pub mod variance;
+pub mod variant;
+#[cfg(feature = "std")]
+pub mod verbose;
+
+// Densities on the natural scale below which u fx could underflow to zero (u being at least
+// 2^-53 unless zero), which would make the slice the whole support.
+const MIN_NATURAL_DENSITY: f64 = f64::MIN_POSITIVE * 9_007_199_254_740_992.0;
+
+// Whether an update from a state with density fx compares densities on the log scale, which it
+// does for a target on the log scale and for one on the natural scale whose density at the state
+// is so small that u fx could underflow (the slice being the same on either scale). Returns None
+// for a density at the state that is zero, NaN, or infinite (a log density that is not finite),
+// whose slice is unbounded or empty, so that the update should keep the state.
+pub(crate) fn compare_on_log_scale(fx: f64, on_log_scale: bool) -> Option<bool> {
+    if on_log_scale {
+        fx.is_finite().then_some(true)
+    } else if fx > 0.0 && fx < f64::INFINITY {
+        Some(fx < MIN_NATURAL_DENSITY)
+    } else {
+        None
+    }
+}
+
+// Target evaluations made while expanding the initial interval and proposals rejected while
+// shrinking, during one update, with its slice level and the interval of its last proposal.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Phases {
+    pub(crate) expansion_evaluations: u64,
+    pub(crate) shrinkage_rejections: u64,
+    pub(crate) slice_level: f64,
+    pub(crate) final_interval: (f64, f64),
+}
+
+// Hooks through which the expansion and shrinkage core reports the progress of an update.
+pub(crate) trait Observer {
+    fn slice(&mut self, _y: f64, _initial_interval: (f64, f64)) {}
+    fn expansion_evaluation(&mut self, _z: f64, _fz: f64) {}
+    fn expanded(&mut self, _interval: (f64, f64)) {}
+    fn proposal(&mut self, _interval: (f64, f64), _x1: f64, _fx1: f64, _accepted: bool) {}
+}
+
+impl Observer for Phases {
+    fn slice(&mut self, y: f64, _initial_interval: (f64, f64)) {
+        self.slice_level = y;
+    }
+    fn expansion_evaluation(&mut self, _z: f64, _fz: f64) {
+        self.expansion_evaluations += 1;
+    }
+    fn proposal(&mut self, interval: (f64, f64), _x1: f64, _fx1: f64, accepted: bool) {
+        self.final_interval = interval;
+        if !accepted {
+            self.shrinkage_rejections += 1;
+        }
+    }
+}
+
+// Limit on the expansion of the initial interval, following Neal (2003): Unlimited expands until
+// the interval brackets the slice, None keeps the initial interval, and Max(n) limits the
+// interval to n widths when stepping out (Neal's m) or allows at most n doublings (Neal's p).
+// When stepping out, Max(1) is therefore the same as None.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepLimit {
+    Unlimited,
+    None,
+    Max(u32),
+}
+
+// The sentinels used by earlier versions: 0 for unlimited, 1 for no expansion, and n > 1 for at
+// most n.
+impl From<u32> for StepLimit {
+    fn from(n: u32) -> Self {
+        match n {
+            0 => StepLimit::Unlimited,
+            1 => StepLimit::None,
+            n => StepLimit::Max(n),
+        }
+    }
+}
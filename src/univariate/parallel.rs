@@ -0,0 +1,173 @@
+use crate::error::BudgetExhausted;
+use crate::math::{default_rng, ln};
+use crate::univariate::stepping_out::TuningParameters;
+use crate::univariate::{compare_on_log_scale, StepLimit};
+use std::panic::resume_unwind;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+// Counts target evaluations from both sides against the evaluation budget (if any).
+struct Budget {
+    evaluations: AtomicU64,
+    max_evaluations: Option<u64>,
+}
+
+impl Budget {
+    fn evaluate<S: Fn(f64) -> f64>(&self, f: &S, x: f64) -> Result<f64, BudgetExhausted> {
+        self.evaluations
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (Some(n) != self.max_evaluations).then_some(n + 1)
+            })
+            .map_err(|_| BudgetExhausted)?;
+        Ok(f(x))
+    }
+}
+
+fn step_out<S: Fn(f64) -> f64>(
+    f: &S,
+    budget: &Budget,
+    y: f64,
+    mut end: f64,
+    step: f64,
+    mut remaining: Option<u32>,
+) -> Result<f64, BudgetExhausted> {
+    while remaining != Some(0) && y < budget.evaluate(f, end)? {
+        end += step;
+        remaining = remaining.map(|k| k - 1);
+    }
+    Ok(end)
+}
+
+// Neal (2003) univariate slice sampler using the stepping out and shrinkage procedures, where
+// the two sides are stepped out concurrently on scoped threads. Given the slice level the sides
+// are independent, so for expensive targets this nearly halves the time spent stepping out,
+// while the draws have the same distribution as the sequential sampler's. The current state is
+// returned unchanged if the evaluation budget (if any) is exhausted.
+pub fn univariate_slice_sampler_parallel_stepping_out_and_shrinkage<S: Fn(f64) -> f64 + Sync>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let budget = Budget {
+        evaluations: AtomicU64::new(0),
+        max_evaluations: tuning_parameters.max_evaluations(),
+    };
+    let result =
+        parallel_stepping_out_and_shrinkage(x, &f, on_log_scale, tuning_parameters, &budget, rng);
+    let evaluation_counter = budget.evaluations.into_inner();
+    match result {
+        Ok(x1) => (x1, evaluation_counter),
+        Err(BudgetExhausted) => (x, evaluation_counter),
+    }
+}
+
+fn parallel_stepping_out_and_shrinkage<S: Fn(f64) -> f64 + Sync>(
+    x: f64,
+    f: &S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    budget: &Budget,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<f64, BudgetExhausted> {
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let w = tuning_parameters.width();
+    // Step 1 (slice, keeping the state if its density on the natural scale is zero or infinite)
+    let fx = budget.evaluate(f, x)?;
+    if compare_on_log_scale(fx, on_log_scale).is_none() {
+        return Ok(x);
+    }
+    let y = if on_log_scale {
+        ln(rng.f64()) + fx
+    } else {
+        rng.f64() * fx
+    };
+    // Step 2 (stepping out from a randomly positioned initial interval, one side per thread)
+    let l = x - rng.f64() * w;
+    let r = l + w;
+    let (left_steps, right_steps) = match tuning_parameters.max_number_of_steps() {
+        StepLimit::Unlimited => (None, None),
+        StepLimit::None | StepLimit::Max(1) => (Some(0), Some(0)),
+        StepLimit::Max(m) => {
+            let j = ((rng.f64() * (m as f64)) as u32).min(m - 1);
+            (Some(j), Some(m - 1 - j))
+        }
+    };
+    let (l, r) = thread::scope(|scope| {
+        let left = scope.spawn(|| step_out(f, budget, y, l, -w, left_steps));
+        let r = step_out(f, budget, y, r, w, right_steps);
+        (left.join().unwrap_or_else(|error| resume_unwind(error)), r)
+    });
+    let (mut l, mut r) = (l?, r?);
+    // Step 3 (shrinkage)
+    loop {
+        let x1 = l + rng.f64() * (r - l);
+        if y < budget.evaluate(f, x1)? {
+            return Ok(x1);
+        }
+        // The interval has collapsed onto the current state (possible only if the target is not a
+        // deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return Ok(x);
+        }
+        if x1 < x {
+            l = x1;
+        } else {
+            r = x1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_normal() {
+        let tuning_parameters = TuningParameters::builder().width(0.1).build().unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(131));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let n_samples = 20_000;
+        for _ in 0..n_samples {
+            (x, _) = univariate_slice_sampler_parallel_stepping_out_and_shrinkage(
+                x,
+                |x| -0.5 * x * x,
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum += x;
+            sum_of_squares += x * x;
+        }
+        let mean = sum / (n_samples as f64);
+        assert!(mean.abs() < 0.05);
+        assert!((sum_of_squares / (n_samples as f64) - mean * mean - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_parallel_budget() {
+        let tuning_parameters = TuningParameters::builder()
+            .width(0.01)
+            .max_evaluations(10)
+            .build()
+            .unwrap();
+        let (x, evaluations) = univariate_slice_sampler_parallel_stepping_out_and_shrinkage(
+            0.5,
+            |x| -0.5 * x * x,
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert_eq!((x, evaluations), (0.5, 10));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::math::{default_rng, ln};
+use crate::target::RefinableTarget;
+use crate::univariate::expansion::{ExpansionStrategy, SteppingOut};
+use crate::univariate::stepping_out::TuningParameters;
+use core::convert::Infallible;
+
+// Whether z is in the slice {z : log f(z) - log f(x) > e}, refining the bounds at x or z (the
+// wider one) only while they cannot decide it. The level at x persists across comparisons.
+fn in_slice<T: RefinableTarget + ?Sized>(
+    target: &mut T,
+    x: f64,
+    level_x: &mut u32,
+    bounds_x: &mut Option<(f64, f64)>,
+    z: f64,
+    e: f64,
+    evaluations: &mut u64,
+) -> bool {
+    let max_level = target.max_level();
+    let mut level_z = 0;
+    loop {
+        let (lo_x, hi_x) = match *bounds_x {
+            Some(bounds) => bounds,
+            None => {
+                *evaluations += 1;
+                let bounds = target.bounds(x, *level_x);
+                *bounds_x = Some(bounds);
+                bounds
+            }
+        };
+        *evaluations += 1;
+        let (lo_z, hi_z) = target.bounds(z, level_z);
+        if lo_z - hi_x > e {
+            return true;
+        }
+        if hi_z - lo_x <= e {
+            return false;
+        }
+        if *level_x < max_level && (hi_x - lo_x >= hi_z - lo_z || level_z == max_level) {
+            *level_x += 1;
+            *bounds_x = None;
+        } else if level_z < max_level {
+            level_z += 1;
+        } else {
+            return lo_z - lo_x > e;
+        }
+    }
+}
+
+// Stepping out and shrinkage given only an oracle in_slice(z, e) deciding whether
+// log f(z) - log f(x) > e, where e = log u is drawn once per update. Points in the slice have
+// infinite pseudo-density relative to the level 0 and points outside it have zero, so the decisions
+// (and, with an exact oracle, the draws) are those of the exact sampler.
+pub(crate) fn indicator_slice_sampler<F: FnMut(f64, f64) -> bool>(
+    x: f64,
+    mut in_slice: F,
+    tuning_parameters: &TuningParameters,
+    rng: &mut fastrand::Rng,
+) -> f64 {
+    // Step 1 (slice, relative to log f(x))
+    let e = ln(rng.f64());
+    // Step 2 (stepping out)
+    let w = tuning_parameters.width();
+    let l = x - rng.f64() * w;
+    let strategy = SteppingOut::new(tuning_parameters.max_number_of_steps());
+    let mut indicator = |z: f64| {
+        Ok::<f64, Infallible>(if in_slice(z, e) {
+            f64::INFINITY
+        } else {
+            f64::NEG_INFINITY
+        })
+    };
+    let expanded = strategy.expand(x, 0.0, (l, l + w), &mut indicator, &mut || rng.f64());
+    let (mut l, mut r) = match expanded {
+        Ok(interval) => interval,
+        Err(never) => match never {},
+    };
+    // Step 3 (shrinkage)
+    loop {
+        let x1 = l + rng.f64() * (r - l);
+        if in_slice(x1, e) {
+            return x1;
+        }
+        // The interval has collapsed onto the current state (possible only if the oracle is
+        // inconsistent) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return x;
+        }
+        if x1 < x {
+            l = x1;
+        } else {
+            r = x1;
+        }
+    }
+}
+
+// Stepping out and shrinkage for a target evaluated to adaptive precision, making exactly the
+// decisions of the exact sampler (so that it samples the target exactly) while refining the
+// bounds on the log density only when they cannot decide whether a point is in the slice. Writing
+// the slice level as log f(x) + log u, a point z is in the slice if log f(z) - log f(x) > log u,
+// which bounds at coarse levels usually settle. Returns the new state and the number of calls to
+// bounds.
+pub fn retrospective_slice_sampler<T: RefinableTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let mut evaluations = 0;
+    let mut level_x = 0;
+    let mut bounds_x = None;
+    let x1 = indicator_slice_sampler(
+        x,
+        |z, e| {
+            in_slice(
+                target,
+                x,
+                &mut level_x,
+                &mut bounds_x,
+                z,
+                e,
+                &mut evaluations,
+            )
+        },
+        tuning_parameters,
+        rng,
+    );
+    (x1, evaluations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Standard normal log density with bounds of half-width 2^-level below max_level, recording
+    // the highest level requested.
+    struct Noisy {
+        max_level: u32,
+        highest_level: u32,
+    }
+
+    impl RefinableTarget for Noisy {
+        fn bounds(&mut self, x: f64, level: u32) -> (f64, f64) {
+            self.highest_level = self.highest_level.max(level);
+            let exact = -0.5 * x * x;
+            if level == self.max_level {
+                (exact, exact)
+            } else {
+                let half_width = 0.5f64.powi(level as i32);
+                (exact - half_width, exact + half_width)
+            }
+        }
+        fn max_level(&self) -> u32 {
+            self.max_level
+        }
+    }
+
+    #[test]
+    fn test_standard_normal() {
+        let mut target = Noisy {
+            max_level: 40,
+            highest_level: 0,
+        };
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(163));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let mut total = 0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            let evaluations;
+            (x, evaluations) =
+                retrospective_slice_sampler(x, &mut target, &tuning_parameters, &mut rng);
+            sum += x;
+            sum_of_squares += x * x;
+            total += evaluations;
+        }
+        let n = n_samples as f64;
+        assert!((sum / n).abs() < 0.03);
+        assert!((sum_of_squares / n - 1.0).abs() < 0.03);
+        // Most comparisons are settled at coarse levels
+        assert!((total as f64) / n < 40.0);
+        assert!(target.highest_level < 40);
+    }
+
+    #[test]
+    fn test_matches_exact_sampler() {
+        // With exact bounds at every level, the draws are those of stepping out and shrinkage
+        // with the same uniforms
+        let mut target = Noisy {
+            max_level: 0,
+            highest_level: 0,
+        };
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(167));
+        let mut exact_rng = Some(fastrand::Rng::with_seed(167));
+        let mut x = 0.3;
+        let mut exact_x = 0.3;
+        for _ in 0..100 {
+            (x, _) = retrospective_slice_sampler(x, &mut target, &tuning_parameters, &mut rng);
+            (exact_x, _) =
+                crate::univariate::stepping_out::univariate_slice_sampler_stepping_out_and_shrinkage(
+                    exact_x,
+                    |x| -0.5 * x * x,
+                    true,
+                    &tuning_parameters,
+                    &mut exact_rng,
+                );
+            assert!((x - exact_x).abs() < 1e-12);
+        }
+    }
+}
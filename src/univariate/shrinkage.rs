@@ -1,49 +1,323 @@
-// Neal (2003) univariate slice sampler using shrinkage procedures
+use crate::error::{
+    check_bounds, check_start, check_width, checked_target, SliceError, TuningError,
+};
+use crate::math::{default_rng, ln};
+use crate::univariate::{compare_on_log_scale, Phases};
+use core::convert::Infallible;
+
+// Width used by default to step out on a side whose bound is infinite.
+const STEPPING_OUT_WIDTH: f64 = 1.0;
+
+// Neal (2003) univariate slice sampler using shrinkage procedures, where an infinite bound is
+// handled by stepping out on that side in steps of width 1. Panics if the bounds are NaN or do
+// not contain x.
 pub fn univariate_slice_sampler_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let (x1, evaluation_counter, _) =
+        univariate_slice_sampler_shrinkage_with_interval(x, f, on_log_scale, left, right, rng);
+    (x1, evaluation_counter)
+}
+
+// Like univariate_slice_sampler_shrinkage, but stepping out on a side whose bound is infinite in
+// steps of width w, e.g., about the scale of the target. Also panics if a bound is infinite and w
+// is not finite and positive.
+pub fn univariate_slice_sampler_shrinkage_with_step_width<S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    w: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    if let Err(error) = check_bounds(x, left, right) {
+        panic!("{}", error);
+    }
+    if let Err(error) = check_step_width(left, right, w) {
+        panic!("{}", error);
+    }
+    let result = shrinkage(
+        x,
+        |x| Ok::<f64, Infallible>(f(x)),
+        on_log_scale,
+        left,
+        right,
+        w,
+        false,
+        &mut Phases::default(),
+        rng,
+    );
+    match result {
+        Ok((x1, evaluation_counter, _)) => (x1, evaluation_counter),
+        Err(never) => match never {},
+    }
+}
+
+// Like univariate_slice_sampler_shrinkage, but reports bounds that are NaN or do not contain x, a
+// non-finite or zero-density starting point, and NaN target values as errors.
+pub fn try_univariate_slice_sampler_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let x = check_start(x)?;
+    check_bounds(x, left, right)?;
+    shrinkage(
+        x,
+        checked_target(f, on_log_scale),
+        on_log_scale,
+        left,
+        right,
+        STEPPING_OUT_WIDTH,
+        false,
+        &mut Phases::default(),
+        rng,
+    )
+    .map(|(x1, evaluation_counter, _)| (x1, evaluation_counter))
+}
+
+// Like univariate_slice_sampler_shrinkage_with_step_width, but reports errors as
+// try_univariate_slice_sampler_shrinkage does, and an invalid w if a bound is infinite.
+pub fn try_univariate_slice_sampler_shrinkage_with_step_width<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    w: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let x = check_start(x)?;
+    check_bounds(x, left, right)?;
+    check_step_width(left, right, w)?;
+    shrinkage(
+        x,
+        checked_target(f, on_log_scale),
+        on_log_scale,
+        left,
+        right,
+        w,
+        false,
+        &mut Phases::default(),
+        rng,
+    )
+    .map(|(x1, evaluation_counter, _)| (x1, evaluation_counter))
+}
+
+// Like univariate_slice_sampler_shrinkage, but also returning the interval from which the new
+// state was drawn, e.g., for custom width adaptation.
+pub fn univariate_slice_sampler_shrinkage_with_interval<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64, (f64, f64)) {
+    shrinkage_with_interval(x, f, on_log_scale, left, right, &mut Phases::default(), rng)
+}
+
+// Like univariate_slice_sampler_shrinkage_with_interval, but also recording the evaluations made
+// while stepping out and the rejected proposals.
+pub(crate) fn shrinkage_with_interval<S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    phases: &mut Phases,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64, (f64, f64)) {
+    if let Err(error) = check_bounds(x, left, right) {
+        panic!("{}", error);
+    }
+    let result = shrinkage(
+        x,
+        |x| Ok::<f64, Infallible>(f(x)),
+        on_log_scale,
+        left,
+        right,
+        STEPPING_OUT_WIDTH,
+        false,
+        phases,
+        rng,
+    );
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
+
+// Like univariate_slice_sampler_shrinkage, but each rejection also bisects the interval at its
+// midpoint and keeps the half containing x, so the interval at least halves with every rejection.
+pub fn univariate_slice_sampler_bisection_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    if let Err(error) = check_bounds(x, left, right) {
+        panic!("{}", error);
+    }
+    let result = shrinkage(
+        x,
+        |x| Ok::<f64, Infallible>(f(x)),
+        on_log_scale,
+        left,
+        right,
+        STEPPING_OUT_WIDTH,
+        true,
+        &mut Phases::default(),
+        rng,
+    );
+    match result {
+        Ok((x1, evaluation_counter, _)) => (x1, evaluation_counter),
+        Err(never) => match never {},
+    }
+}
+
+// Like univariate_slice_sampler_bisection_shrinkage, but reports errors as
+// try_univariate_slice_sampler_shrinkage does.
+pub fn try_univariate_slice_sampler_bisection_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    left: f64,
+    right: f64,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let x = check_start(x)?;
+    check_bounds(x, left, right)?;
+    shrinkage(
+        x,
+        checked_target(f, on_log_scale),
+        on_log_scale,
+        left,
+        right,
+        STEPPING_OUT_WIDTH,
+        true,
+        &mut Phases::default(),
+        rng,
+    )
+    .map(|(x1, evaluation_counter, _)| (x1, evaluation_counter))
+}
+
+// The step width matters only if a bound is infinite.
+fn check_step_width(left: f64, right: f64, w: f64) -> Result<(), TuningError> {
+    if left.is_infinite() || right.is_infinite() {
+        check_width(w)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn shrinkage<E, S: FnMut(f64) -> Result<f64, E>>(
     x: f64,
     mut f: S,
     on_log_scale: bool,
     left: f64,
     right: f64,
+    w: f64,
+    bisection: bool,
+    phases: &mut Phases,
     rng: &mut Option<fastrand::Rng>,
-) -> (f64, u32) {
+) -> Result<(f64, u64, (f64, f64)), E> {
     let mut maybe;
     let rng = match rng {
         Some(rng) => rng,
         None => {
-            maybe = fastrand::Rng::new();
+            maybe = default_rng();
             &mut maybe
         }
     };
     let mut u = || rng.f64();
     let mut evaluation_counter = 0;
+    // Step 1 (slice, compared on the log scale for a tiny density on the natural scale)
+    let u0: f64 = u();
+    let fx = f(x)?;
+    evaluation_counter += 1;
+    let log_scale = match compare_on_log_scale(fx, on_log_scale) {
+        Some(log_scale) => log_scale,
+        None => return Ok((x, evaluation_counter, (x, x))),
+    };
+    let y = if on_log_scale {
+        ln(u0) + fx
+    } else if log_scale {
+        ln(u0) + ln(fx)
+    } else {
+        u0 * fx
+    };
     let mut f_with_counter = |x: f64| {
         evaluation_counter += 1;
-        f(x)
-    };
-    // Step 1 (slice)
-    let y = {
-        let u: f64 = u();
-        let fx = f_with_counter(x);
-        if on_log_scale {
-            u.ln() + fx
+        let fx = f(x)?;
+        Ok(if log_scale && !on_log_scale {
+            ln(fx)
         } else {
-            u * fx
-        }
+            fx
+        })
     };
-    // Step 3 (shrinkage)
+    // Step 2 (stepping out, only on sides whose bound is infinite)
     let mut l = left;
     let mut r = right;
+    if l.is_infinite() || r.is_infinite() {
+        let l0 = x - u() * w;
+        let mut expansion_evaluations = 0;
+        let mut f_while_expanding = |x: f64| {
+            expansion_evaluations += 1;
+            f_with_counter(x)
+        };
+        if l.is_infinite() {
+            l = l0;
+            while y < f_while_expanding(l)? {
+                l -= w;
+            }
+        }
+        if r.is_infinite() {
+            r = l0 + w;
+            while y < f_while_expanding(r)? {
+                r += w;
+            }
+        }
+        phases.expansion_evaluations = expansion_evaluations;
+    }
+    phases.slice_level = y;
+    // Step 3 (shrinkage)
     loop {
         let x1 = l + u() * (r - l);
-        let fx1 = f_with_counter(x1);
+        let fx1 = f_with_counter(x1)?;
+        phases.final_interval = (l, r);
         if y < fx1 {
-            return (x1, evaluation_counter);
+            return Ok((x1, evaluation_counter, (l, r)));
+        }
+        phases.shrinkage_rejections += 1;
+        // The interval has collapsed onto the current state (possible only if the target is not a
+        // deterministic function) or is no longer finite, so the current state is returned
+        if x1 == x || x1.is_nan() {
+            return Ok((x, evaluation_counter, (l, r)));
+        }
+        if bisection {
+            let m = (l + r) / 2.0;
+            if x < m {
+                r = m;
+            } else {
+                l = m;
+            }
         }
         if x1 < x {
-            l = x1;
+            l = l.max(x1);
         } else {
-            r = x1;
+            r = r.min(x1);
         }
     }
 }
@@ -82,4 +356,138 @@ mod tests {
         println!("{}", (total_calls as f64) / (n_samples as f64));
         assert!(diff < 0.01);
     }
+
+    #[test]
+    fn test_half_bounded_support() {
+        let mut rng = Some(fastrand::Rng::with_seed(29));
+        let mut x = 1.0;
+        let mut sum = 0.0;
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (x, _) = univariate_slice_sampler_shrinkage(
+                x,
+                |x| if x < 0.0 { f64::NEG_INFINITY } else { -x },
+                true,
+                0.0,
+                f64::INFINITY,
+                &mut rng,
+            );
+            assert!(x >= 0.0);
+            sum += x;
+        }
+        let mean = sum / (n_samples as f64);
+        assert!((mean - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_step_width() {
+        // Steps of about the scale of the target keep stepping out short however large it is
+        let scale = 1e6;
+        let f = |x: f64| {
+            if x < 0.0 {
+                f64::NEG_INFINITY
+            } else {
+                -x / scale
+            }
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(41));
+        let mut x = scale;
+        let mut sum = 0.0;
+        let mut evaluations = 0;
+        let n_samples = 10_000;
+        for _ in 0..n_samples {
+            let count;
+            (x, count) = univariate_slice_sampler_shrinkage_with_step_width(
+                x,
+                f,
+                true,
+                0.0,
+                f64::INFINITY,
+                scale,
+                &mut rng,
+            );
+            sum += x;
+            evaluations += count;
+        }
+        assert!((sum / (n_samples as f64) / scale - 1.0).abs() < 0.05);
+        assert!(evaluations < 10 * n_samples);
+        // The step width is validated only when a bound is infinite
+        let result = try_univariate_slice_sampler_shrinkage_with_step_width(
+            1.0,
+            f,
+            true,
+            0.0,
+            f64::INFINITY,
+            0.0,
+            &mut rng,
+        );
+        assert_eq!(
+            result,
+            Err(SliceError::InvalidTuning(TuningError::InvalidWidth(0.0)))
+        );
+        let result = try_univariate_slice_sampler_shrinkage_with_step_width(
+            1.0, f, true, 0.0, 2.0, 0.0, &mut rng,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_start_outside_bounds() {
+        univariate_slice_sampler_shrinkage(2.0, |x| -x * x, true, 0.0, 1.0, &mut None);
+    }
+
+    #[test]
+    fn test_bisection_shrinkage() {
+        // A narrow slice within a wide support needs fewer evaluations with bisection
+        let f = |x: f64| -0.5 * (x / 0.01) * (x / 0.01);
+        let mut rng = Some(fastrand::Rng::with_seed(31));
+        let mut x = 0.0;
+        let mut sum_of_squares = 0.0;
+        let mut bisection_calls = 0;
+        let mut calls = 0;
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            let (x1, calls1) =
+                univariate_slice_sampler_bisection_shrinkage(x, f, true, -100.0, 100.0, &mut rng);
+            let (_, calls2) =
+                univariate_slice_sampler_shrinkage(x, f, true, -100.0, 100.0, &mut rng);
+            x = x1;
+            sum_of_squares += x * x;
+            bisection_calls += calls1;
+            calls += calls2;
+        }
+        let standard_deviation = (sum_of_squares / (n_samples as f64)).sqrt();
+        assert!((standard_deviation - 0.01).abs() < 0.0005);
+        assert!(bisection_calls < calls);
+    }
+
+    #[test]
+    fn test_interval_contains_draw() {
+        let mut rng = Some(fastrand::Rng::with_seed(37));
+        for _ in 0..1_000 {
+            let (x, _, (l, r)) = univariate_slice_sampler_shrinkage_with_interval(
+                0.0,
+                |x| -0.5 * x * x,
+                true,
+                -10.0,
+                10.0,
+                &mut rng,
+            );
+            assert!(-10.0 <= l && l <= x && x <= r && r <= 10.0);
+        }
+    }
+
+    #[test]
+    fn test_collapsed_interval() {
+        let mut first = true;
+        let f = |_: f64| {
+            let value = if first { 0.0 } else { f64::NEG_INFINITY };
+            first = false;
+            value
+        };
+        let mut rng = Some(fastrand::Rng::with_seed(113));
+        let (x, _) = univariate_slice_sampler_shrinkage(0.25, f, true, 0.0, 1.0, &mut rng);
+        assert_eq!(x, 0.25);
+    }
 }
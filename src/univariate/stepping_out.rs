@@ -1,122 +1,176 @@
-#[derive(Debug)]
-pub struct TuningParameters {
-    initial_width: f64,
-    max_number_of_steps: u32,
+use crate::error::{
+    budgeted_target, check_bounds, check_degenerate, check_start, checked_target, BudgetExhausted,
+    SliceError,
+};
+use crate::univariate::expansion::{expansion_and_shrinkage, SteppingOut};
+use crate::univariate::truncated::TruncatedSteppingOut;
+use crate::univariate::{tuning, Phases};
+
+// Tuning parameters of stepping out, which are the common ones with no extension.
+pub type TuningParameters = tuning::TuningParameters;
+pub type TuningParametersBuilder = tuning::TuningParametersBuilder;
+
+// Neal (2003) univariate slice sampler using the stepping out and shrinkage procedures, where the
+// current state is returned unchanged if the evaluation budget (if any) is exhausted. With bounds
+// in the tuning parameters, the target is never evaluated outside them, and the sampler panics
+// if they do not contain x.
+pub fn univariate_slice_sampler_stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let (x1, evaluation_counter, _) = stepping_out_and_shrinkage_with_interval(
+        x,
+        f,
+        on_log_scale,
+        tuning_parameters.width(),
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    );
+    (x1, evaluation_counter)
 }
 
-impl TuningParameters {
-    pub fn new() -> Self {
-        Default::default()
-    }
-    pub fn width(self, value: f64) -> Self {
-        Self {
-            initial_width: value,
-            ..self
+// Like univariate_slice_sampler_stepping_out_and_shrinkage, but also returning the interval from
+// which the new state was drawn ([x, x] if the budget is exhausted), e.g., for custom width
+// adaptation.
+pub fn univariate_slice_sampler_stepping_out_and_shrinkage_with_interval<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64, (f64, f64)) {
+    stepping_out_and_shrinkage_with_interval(
+        x,
+        f,
+        on_log_scale,
+        tuning_parameters.width(),
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    )
+}
+
+// Like univariate_slice_sampler_stepping_out_and_shrinkage_with_interval, but with the initial
+// width w in place of the one in the tuning parameters.
+pub(crate) fn stepping_out_and_shrinkage_with_interval<S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    tuning_parameters: &TuningParameters,
+    phases: &mut Phases,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64, (f64, f64)) {
+    if let Some((lower, upper)) = tuning_parameters.bounds() {
+        if let Err(error) = check_bounds(x, lower, upper) {
+            panic!("{}", error);
         }
     }
-    pub fn max_number_of_steps(self, value: u32) -> Self {
-        Self {
-            max_number_of_steps: value,
-            ..self
-        }
+    let result = stepping_out_and_shrinkage(
+        x,
+        budgeted_target(
+            |x| Ok(f(x)),
+            tuning_parameters.max_evaluations(),
+            BudgetExhausted,
+        ),
+        on_log_scale,
+        w,
+        tuning_parameters,
+        phases,
+        rng,
+    );
+    match result {
+        Ok(value) => value,
+        Err(BudgetExhausted) => (x, tuning_parameters.max_evaluations().unwrap_or(0), (x, x)),
     }
 }
 
-impl Default for TuningParameters {
-    fn default() -> Self {
-        TuningParameters {
-            initial_width: 1.0,
-            max_number_of_steps: 0,
-        }
+// Like univariate_slice_sampler_stepping_out_and_shrinkage, but reports a non-finite or
+// zero-density starting point, bounds that do not contain it, NaN target values, and an exhausted
+// evaluation budget as errors.
+pub fn try_univariate_slice_sampler_stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+    x: f64,
+    f: S,
+    on_log_scale: bool,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let w = tuning_parameters.width();
+    let x = check_start(x)?;
+    if let Some((lower, upper)) = tuning_parameters.bounds() {
+        check_bounds(x, lower, upper)?;
     }
+    stepping_out_and_shrinkage(
+        x,
+        budgeted_target(
+            checked_target(f, on_log_scale),
+            tuning_parameters.max_evaluations(),
+            SliceError::EvaluationBudgetExhausted(tuning_parameters.max_evaluations().unwrap_or(0)),
+        ),
+        on_log_scale,
+        w,
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    )
+    .and_then(|result| check_degenerate(x, result, tuning_parameters.min_width()))
 }
 
-// Neal (2003) univariate slice sampler using the stepping out and shrinkage procedures
-pub fn univariate_slice_sampler_stepping_out_and_shrinkage<S: FnMut(f64) -> f64>(
+fn stepping_out_and_shrinkage<E, S: FnMut(f64) -> Result<f64, E>>(
     x: f64,
-    mut f: S,
+    f: S,
     on_log_scale: bool,
+    w: f64,
     tuning_parameters: &TuningParameters,
+    phases: &mut Phases,
     rng: &mut Option<fastrand::Rng>,
-) -> (f64, u32) {
-    let w = if tuning_parameters.initial_width <= 0.0 {
-        f64::MIN_POSITIVE
-    } else {
-        tuning_parameters.initial_width
-    };
-    let mut maybe;
-    let rng = match rng {
-        Some(rng) => rng,
-        None => {
-            maybe = fastrand::Rng::new();
-            &mut maybe
-        }
-    };
-    let mut u = || rng.f64();
-    let mut evaluation_counter = 0;
-    let mut f_with_counter = |x: f64| {
-        evaluation_counter += 1;
-        f(x)
-    };
-    // Step 1 (slice)
-    let y = {
-        let fx = f_with_counter(x);
-        if on_log_scale {
-            u().ln() + fx
-        } else {
-            u() * fx
-        }
-    };
-    // Step 2 (stepping out, unless max_number_of_steps == 1)
-    let mut l = x - u() * w;
-    let mut r = l + w;
-    match tuning_parameters.max_number_of_steps {
-        0 => {
-            while y < f_with_counter(l) {
-                l -= w
-            }
-            while y < f_with_counter(r) {
-                r += w
-            }
-        }
-        1 => {}
-        _ => {
-            let mut j = (u() * (tuning_parameters.max_number_of_steps as f64)).floor() as u32;
-            let mut k = tuning_parameters.max_number_of_steps - 1 - j;
-            while j > 0 && y < f_with_counter(l) {
-                l -= w;
-                j -= 1;
-            }
-            while k > 0 && y < f_with_counter(r) {
-                r += w;
-                k -= 1;
-            }
-        }
-    }
-    // Step 3 (shrinkage)
-    loop {
-        let x1 = l + u() * (r - l);
-        let fx1 = f_with_counter(x1);
-        if y < fx1 {
-            return (x1, evaluation_counter);
-        }
-        if x1 < x {
-            l = x1;
-        } else {
-            r = x1;
-        }
+) -> Result<(f64, u64, (f64, f64)), E> {
+    let stepping_out = SteppingOut::new(tuning_parameters.max_number_of_steps());
+    let min_width = tuning_parameters.min_width().unwrap_or(0.0);
+    match tuning_parameters.bounds() {
+        None => expansion_and_shrinkage(
+            x,
+            f,
+            on_log_scale,
+            w,
+            min_width,
+            0,
+            &stepping_out,
+            phases,
+            rng,
+        ),
+        Some((lower, upper)) => expansion_and_shrinkage(
+            x,
+            f,
+            on_log_scale,
+            w,
+            min_width,
+            0,
+            &TruncatedSteppingOut {
+                stepping_out,
+                lower,
+                upper,
+            },
+            phases,
+            rng,
+        ),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::univariate::StepLimit;
 
     #[test]
     fn test_triangle_distribution() {
         let mut sum = 0.0;
         let n_samples = 100_000;
-        let tuning_parameters = TuningParameters::new().width(1.);
+        let tuning_parameters = TuningParameters::builder().width(1.).build().unwrap();
         let mut x = 0.5;
         let mut total_calls = 0;
         for _ in 0..n_samples {
@@ -142,4 +196,87 @@ mod tests {
         println!("{}", (total_calls as f64) / (n_samples as f64));
         assert!(diff < 0.01);
     }
+
+    #[test]
+    fn test_evaluation_budget() {
+        // The density is positive only at the current state, so shrinkage never accepts
+        let tuning_parameters = TuningParameters::builder()
+            .max_evaluations(20)
+            .build()
+            .unwrap();
+        let f = |x: f64| if x == 0.5 { 0.0 } else { f64::NEG_INFINITY };
+        let (x, calls) = univariate_slice_sampler_stepping_out_and_shrinkage(
+            0.5,
+            f,
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert_eq!((x, calls), (0.5, 20));
+        let result = try_univariate_slice_sampler_stepping_out_and_shrinkage(
+            0.5,
+            f,
+            true,
+            &tuning_parameters,
+            &mut None,
+        );
+        assert_eq!(result, Err(SliceError::EvaluationBudgetExhausted(20)));
+    }
+
+    #[test]
+    fn test_tuning_parameters_round_trip() {
+        // The getters expose everything needed to store and rebuild a configuration
+        let tuning_parameters = TuningParameters::builder()
+            .width(2.5)
+            .max_number_of_steps(StepLimit::Max(10))
+            .max_evaluations(1_000)
+            .build()
+            .unwrap();
+        let mut builder = TuningParameters::builder()
+            .width(tuning_parameters.width())
+            .max_number_of_steps(tuning_parameters.max_number_of_steps());
+        if let Some(n) = tuning_parameters.max_evaluations() {
+            builder = builder.max_evaluations(n);
+        }
+        assert_eq!(builder.build().unwrap(), tuning_parameters);
+    }
+
+    #[test]
+    fn test_one_step_is_no_stepping() {
+        // Max(1) limits the interval to the initial width, so it draws exactly as None does
+        let draw = |limit| {
+            let tuning_parameters = TuningParameters::builder()
+                .max_number_of_steps(limit)
+                .build()
+                .unwrap();
+            let mut rng = Some(fastrand::Rng::with_seed(29));
+            let mut x = 0.0;
+            let mut draws = Vec::new();
+            for _ in 0..100 {
+                let calls;
+                (x, calls) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                    x,
+                    |x| -0.5 * x * x,
+                    true,
+                    &tuning_parameters,
+                    &mut rng,
+                );
+                draws.push((x, calls));
+            }
+            draws
+        };
+        assert_eq!(draw(StepLimit::Max(1)), draw(StepLimit::None));
+    }
+
+    #[test]
+    fn test_step_limit_from_sentinel() {
+        assert_eq!(StepLimit::from(0), StepLimit::Unlimited);
+        assert_eq!(StepLimit::from(1), StepLimit::None);
+        assert_eq!(StepLimit::from(10), StepLimit::Max(10));
+        let tuning_parameters = TuningParameters::builder()
+            .max_number_of_steps(10)
+            .build()
+            .unwrap();
+        assert_eq!(tuning_parameters.max_number_of_steps(), StepLimit::Max(10));
+    }
 }
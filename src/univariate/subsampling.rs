@@ -0,0 +1,269 @@
+use crate::math::default_rng;
+use crate::univariate::retrospective::indicator_slice_sampler;
+use crate::univariate::stepping_out::TuningParameters;
+
+// Log density tractable(x) + sum_i log_term(x, data[i]) over a large data set, where tractable
+// (e.g., a log prior plus the sum of Taylor approximations of the terms about a mode, as control
+// variates) is cheap to evaluate for all of the data at once and log_term is the remainder for one
+// datum. Given difference_range(x, z) = (a, b) with
+// a <= log_term(z, d) - log_term(x, d) <= b for every datum d, comparisons with the slice are
+// resolved from a random batch of m of the data, adding batch_size, then 2 batch_size,
+// 4 batch_size, ... more terms only while bounds on the full sum cannot decide. By default the
+// bounds add (n - m) a and (n - m) b for the remaining terms, which hold with certainty so that
+// the sampler remains exact, but they are tight only when the remainders vary little. With
+// confidence_bounds, the bounds are instead those of Hoeffding's inequality for the mean of the
+// batch, as in the adaptive subsampling of Bardenet, Doucet, and Holmes (2014), which need far
+// less of the data but make each comparison wrong with probability up to error_probability.
+pub struct Subsampled<D, T, L, R> {
+    data: Vec<D>,
+    tractable: T,
+    log_term: L,
+    difference_range: R,
+    batch_size: usize,
+    error_probability: Option<f64>,
+    rng: fastrand::Rng,
+    terms_evaluated: u64,
+}
+
+impl<D, T, L, R> Subsampled<D, T, L, R>
+where
+    T: FnMut(f64) -> f64,
+    L: FnMut(f64, &D) -> f64,
+    R: FnMut(f64, f64) -> (f64, f64),
+{
+    pub fn new(
+        data: Vec<D>,
+        tractable: T,
+        log_term: L,
+        difference_range: R,
+        batch_size: usize,
+        rng: &mut Option<fastrand::Rng>,
+    ) -> Self {
+        let mut maybe;
+        let rng = match rng {
+            Some(rng) => rng,
+            None => {
+                maybe = default_rng();
+                &mut maybe
+            }
+        };
+        Self {
+            data,
+            tractable,
+            log_term,
+            difference_range,
+            batch_size: batch_size.max(1),
+            error_probability: None,
+            rng: rng.fork(),
+            terms_evaluated: 0,
+        }
+    }
+
+    // Resolves comparisons with Hoeffding bounds that fail with probability error_probability in
+    // (0, 1), making the sampler approximate.
+    pub fn confidence_bounds(mut self, error_probability: f64) -> Self {
+        self.error_probability = Some(error_probability.clamp(f64::MIN_POSITIVE, 1.0));
+        self
+    }
+
+    // Number of evaluations of log_term so far, each costing one datum at one point.
+    pub fn terms_evaluated(&self) -> u64 {
+        self.terms_evaluated
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Whether log f(z) - log f(x) > e, as decided from as few of the data as possible.
+    fn in_slice(&mut self, x: f64, z: f64, e: f64) -> bool {
+        let n = self.data.len();
+        let (a, b) = (self.difference_range)(x, z);
+        let tractable = (self.tractable)(z) - (self.tractable)(x);
+        let log_inverse_error = self.error_probability.map(|p| (2.0 / p).ln());
+        let mut sum = 0.0;
+        let mut m = 0;
+        let mut batch_size = self.batch_size;
+        loop {
+            let remaining = (n - m) as f64;
+            if remaining == 0.0 {
+                return tractable + sum > e;
+            }
+            let (mut lower, mut upper) = (
+                tractable + sum + remaining * a,
+                tractable + sum + remaining * b,
+            );
+            if let (Some(log_inverse_error), true) = (log_inverse_error, m > 0) {
+                let estimate = tractable + sum * n as f64 / m as f64;
+                let half_width = n as f64 * (b - a) * (log_inverse_error / (2.0 * m as f64)).sqrt();
+                lower = lower.max(estimate - half_width);
+                upper = upper.min(estimate + half_width);
+            }
+            if lower > e {
+                return true;
+            }
+            if upper <= e {
+                return false;
+            }
+            // Extends the random batch by a partial Fisher-Yates shuffle
+            let end = n.min(m + batch_size);
+            for i in m..end {
+                let j = self.rng.usize(i..n);
+                self.data.swap(i, j);
+                let datum = &self.data[i];
+                sum += (self.log_term)(z, datum) - (self.log_term)(x, datum);
+            }
+            self.terms_evaluated += 2 * (end - m) as u64;
+            m = end;
+            batch_size *= 2;
+        }
+    }
+}
+
+// Stepping out and shrinkage for a sum over tall data, resolving each comparison with the slice
+// from batches of the data as described for Subsampled. Returns the new state and the number of
+// comparisons with the slice (see terms_evaluated for their cost).
+pub fn subsampled_slice_sampler<D, T, L, R>(
+    x: f64,
+    target: &mut Subsampled<D, T, L, R>,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64)
+where
+    T: FnMut(f64) -> f64,
+    L: FnMut(f64, &D) -> f64,
+    R: FnMut(f64, f64) -> (f64, f64),
+{
+    let mut maybe;
+    let rng = match rng {
+        Some(rng) => rng,
+        None => {
+            maybe = default_rng();
+            &mut maybe
+        }
+    };
+    let mut comparisons = 0;
+    let x1 = indicator_slice_sampler(
+        x,
+        |z, e| {
+            comparisons += 1;
+            target.in_slice(x, z, e)
+        },
+        tuning_parameters,
+        rng,
+    );
+    (x1, comparisons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Standardized first and second moments of draws for the location of Cauchy observations
+    // with a flat prior, and the cost of the comparisons as a fraction of full-data evaluations.
+    // The terms are -ln(1 + (y - x)^2), whose second derivative lies in [-2, 1/4], so that the
+    // remainders after their second-order Taylor approximations about c have derivatives bounded
+    // by 9/4 |x - c|, which bounds their differences through the mean value theorem.
+    fn cauchy_location(error_probability: Option<f64>) -> (f64, f64, f64) {
+        let mut rng = fastrand::Rng::with_seed(179);
+        let n = 5_000;
+        let data: Vec<f64> = (0..n)
+            .map(|_| (core::f64::consts::PI * (rng.f64() - 0.5)).tan())
+            .collect();
+        // Reference moments on a grid about the mode
+        let grid: Vec<f64> = (0..400).map(|i| -0.2 + 0.001 * i as f64).collect();
+        let log_densities: Vec<f64> = grid
+            .iter()
+            .map(|x| data.iter().map(|y| -(1.0 + (y - x).powi(2)).ln()).sum())
+            .collect();
+        let (c, max) = grid.iter().zip(&log_densities).fold(
+            (0.0, f64::NEG_INFINITY),
+            |(c, max), (&x, &log_density)| {
+                if log_density > max {
+                    (x, log_density)
+                } else {
+                    (c, max)
+                }
+            },
+        );
+        let weights: Vec<f64> = log_densities.iter().map(|l| (l - max).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        let mean = grid.iter().zip(&weights).map(|(x, w)| x * w).sum::<f64>() / total;
+        let variance = grid
+            .iter()
+            .zip(&weights)
+            .map(|(x, w)| (x - mean).powi(2) * w)
+            .sum::<f64>()
+            / total;
+        // Control variates: the sum of the quadratic approximations about c
+        let quadratic = move |u: f64, y: &f64| {
+            let v = y - c;
+            let s = 1.0 + v * v;
+            -s.ln() + 2.0 * v / s * u - (1.0 - v * v) / (s * s) * u * u
+        };
+        let (mut c0, mut c1, mut c2) = (0.0, 0.0, 0.0);
+        for y in &data {
+            c0 += quadratic(0.0, y);
+            c1 += quadratic(1.0, y) - quadratic(-1.0, y);
+            c2 += quadratic(1.0, y) + quadratic(-1.0, y) - 2.0 * quadratic(0.0, y);
+        }
+        let mut target = Subsampled::new(
+            data,
+            move |x: f64| {
+                let u = x - c;
+                c0 + 0.5 * c1 * u + 0.5 * c2 * u * u
+            },
+            move |x: f64, y: &f64| -(1.0 + (y - x).powi(2)).ln() - quadratic(x - c, y),
+            move |x: f64, z: f64| {
+                let bound = 2.25 * (z - x).abs() * (x - c).abs().max((z - c).abs());
+                (-bound, bound)
+            },
+            100,
+            &mut Some(rng.fork()),
+        );
+        if let Some(error_probability) = error_probability {
+            target = target.confidence_bounds(error_probability);
+        }
+        let tuning_parameters = TuningParameters::builder()
+            .width(3.0 * variance.sqrt())
+            .build()
+            .unwrap();
+        let mut rng = Some(rng);
+        let mut x = c;
+        let mut sum = 0.0;
+        let mut sum_of_squares = 0.0;
+        let mut comparisons = 0;
+        let n_samples = 1_000;
+        for _ in 0..n_samples {
+            let count;
+            (x, count) = subsampled_slice_sampler(x, &mut target, &tuning_parameters, &mut rng);
+            let z = (x - mean) / variance.sqrt();
+            sum += z;
+            sum_of_squares += z * z;
+            comparisons += count;
+        }
+        let m = n_samples as f64;
+        let cost = target.terms_evaluated() as f64 / (2 * n) as f64 / comparisons as f64;
+        (sum / m, sum_of_squares / m, cost)
+    }
+
+    #[test]
+    fn test_exact_bounds() {
+        let (mean, second_moment, cost) = cauchy_location(None);
+        assert!(mean.abs() < 0.1);
+        assert!((second_moment - 1.0).abs() < 0.1);
+        assert!(cost < 1.0);
+    }
+
+    #[test]
+    fn test_confidence_bounds() {
+        let (mean, second_moment, cost) = cauchy_location(Some(0.01));
+        assert!(mean.abs() < 0.1);
+        assert!((second_moment - 1.0).abs() < 0.1);
+        assert!(cost < 0.5);
+    }
+}
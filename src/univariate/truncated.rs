@@ -0,0 +1,135 @@
+use crate::error::{budgeted_target, check_bounds, BudgetExhausted};
+use crate::target::UnivariateTarget;
+use crate::univariate::expansion::{expansion_and_shrinkage, ExpansionStrategy, SteppingOut};
+use crate::univariate::stepping_out::TuningParameters;
+use crate::univariate::Phases;
+
+// Stepping out that treats points outside [lower, upper] as outside the slice without evaluating
+// the target there, and clips the expanded interval to the bounds. Clipping is a deterministic
+// function of the interval, so shrinkage from the clipped interval remains valid.
+pub(crate) struct TruncatedSteppingOut {
+    pub(crate) stepping_out: SteppingOut,
+    pub(crate) lower: f64,
+    pub(crate) upper: f64,
+}
+
+impl ExpansionStrategy for TruncatedSteppingOut {
+    fn expand<E, F, U>(
+        &self,
+        x: f64,
+        y: f64,
+        interval: (f64, f64),
+        f: &mut F,
+        u: &mut U,
+    ) -> Result<(f64, f64), E>
+    where
+        F: FnMut(f64) -> Result<f64, E>,
+        U: FnMut() -> f64,
+    {
+        // Returning the slice level marks a point as outside the slice
+        let mut truncated = |z: f64| {
+            if z < self.lower || z > self.upper {
+                Ok(y)
+            } else {
+                f(z)
+            }
+        };
+        let (l, r) = self
+            .stepping_out
+            .expand(x, y, interval, &mut truncated, u)?;
+        Ok((l.max(self.lower), r.min(self.upper)))
+    }
+}
+
+// Draw from the target restricted to [lower, upper], where either bound may be infinite, using
+// stepping out (with the given tuning parameters) up to the bounds followed by shrinkage, so
+// that the target is never evaluated outside the bounds. The current state is returned unchanged
+// if the evaluation budget (if any) is exhausted. Panics if the bounds are NaN or do not contain
+// x.
+pub fn sample_truncated<T: UnivariateTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    lower: f64,
+    upper: f64,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    if let Err(error) = check_bounds(x, lower, upper) {
+        panic!("{}", error);
+    }
+    let on_log_scale = target.on_log_scale();
+    let strategy = TruncatedSteppingOut {
+        stepping_out: SteppingOut::new(tuning_parameters.max_number_of_steps()),
+        lower,
+        upper,
+    };
+    let result = expansion_and_shrinkage(
+        x,
+        budgeted_target(
+            |x| Ok(target.evaluate(x)),
+            tuning_parameters.max_evaluations(),
+            BudgetExhausted,
+        ),
+        on_log_scale,
+        tuning_parameters.width(),
+        tuning_parameters.min_width().unwrap_or(0.0),
+        0,
+        &strategy,
+        &mut Phases::default(),
+        rng,
+    );
+    match result {
+        Ok((x1, evaluation_counter, _)) => (x1, evaluation_counter),
+        Err(BudgetExhausted) => (x, tuning_parameters.max_evaluations().unwrap_or(0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::LogDensity;
+
+    #[test]
+    fn test_truncated_normal() {
+        // Standard normal truncated to [1, infinity), with mean phi(1) / (1 - Phi(1))
+        let mut target = LogDensity(|x: f64| {
+            assert!(x >= 1.0);
+            -0.5 * x * x
+        });
+        let tuning_parameters = TuningParameters::new();
+        let mut rng = Some(fastrand::Rng::with_seed(103));
+        let mut x = 1.5;
+        let mut sum = 0.0;
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (x, _) = sample_truncated(
+                x,
+                &mut target,
+                1.0,
+                f64::INFINITY,
+                &tuning_parameters,
+                &mut rng,
+            );
+            sum += x;
+        }
+        assert!((sum / (n_samples as f64) - 1.525135).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_truncated_to_interval() {
+        let mut target = LogDensity(|x: f64| {
+            assert!((-0.5..=0.25).contains(&x));
+            0.0
+        });
+        let tuning_parameters = TuningParameters::builder().width(10.0).build().unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(107));
+        let mut x = 0.0;
+        let mut sum = 0.0;
+        let n_samples = 100_000;
+        for _ in 0..n_samples {
+            (x, _) = sample_truncated(x, &mut target, -0.5, 0.25, &tuning_parameters, &mut rng);
+            sum += x;
+        }
+        assert!((sum / (n_samples as f64) + 0.125).abs() < 0.01);
+    }
+}
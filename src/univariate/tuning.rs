@@ -0,0 +1,271 @@
+use crate::error::{
+    check_max_evaluations, check_min_width, check_start, check_step_limit, check_support,
+    check_width, SliceError, TuningError,
+};
+use crate::target::UnivariateTarget;
+use crate::univariate::doubling::{self, try_univariate_slice_sampler_doubling_and_shrinkage};
+use crate::univariate::stepping_out::try_univariate_slice_sampler_stepping_out_and_shrinkage;
+use crate::univariate::StepLimit;
+
+// Tuning parameters of the stepping out and doubling samplers: the initial width, the limit on
+// expansion (Neal's m when stepping out and p when doubling), optional bounds on the support, an
+// optional evaluation budget, and an optional minimum width, together with an extension holding
+// the settings specific to one procedure. Stepping out has none, so stepping_out::TuningParameters
+// is TuningParameters<()>, while doubling::TuningParameters extends these with
+// doubling::DoublingExtension (e.g., skipping the acceptance check).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningParameters<X = ()> {
+    width: f64,
+    max_number_of_expansions: StepLimit,
+    bounds: Option<(f64, f64)>,
+    max_evaluations: Option<u64>,
+    min_width: Option<f64>,
+    extension: X,
+}
+
+impl<X: Default> TuningParameters<X> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn builder() -> TuningParametersBuilder<X> {
+        TuningParametersBuilder {
+            tuning_parameters: Default::default(),
+        }
+    }
+}
+
+impl<X> TuningParameters<X> {
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    pub fn max_number_of_expansions(&self) -> StepLimit {
+        self.max_number_of_expansions
+    }
+    pub fn bounds(&self) -> Option<(f64, f64)> {
+        self.bounds
+    }
+    pub fn max_evaluations(&self) -> Option<u64> {
+        self.max_evaluations
+    }
+    pub fn min_width(&self) -> Option<f64> {
+        self.min_width
+    }
+    pub fn extension(&self) -> &X {
+        &self.extension
+    }
+    // The same common settings with another extension, e.g., to switch a configuration from
+    // stepping out to doubling.
+    pub fn with_extension<Y>(&self, extension: Y) -> TuningParameters<Y> {
+        TuningParameters {
+            width: self.width,
+            max_number_of_expansions: self.max_number_of_expansions,
+            bounds: self.bounds,
+            max_evaluations: self.max_evaluations,
+            min_width: self.min_width,
+            extension,
+        }
+    }
+}
+
+impl TuningParameters<()> {
+    pub fn max_number_of_steps(&self) -> StepLimit {
+        self.max_number_of_expansions
+    }
+}
+
+impl<X: Default> Default for TuningParameters<X> {
+    fn default() -> Self {
+        TuningParameters {
+            width: 1.0,
+            max_number_of_expansions: StepLimit::Unlimited,
+            bounds: None,
+            max_evaluations: None,
+            min_width: None,
+            extension: X::default(),
+        }
+    }
+}
+
+// Builder for TuningParameters whose build method rejects invalid settings. That the bounds
+// contain the state is checked when sampling.
+#[derive(Debug)]
+pub struct TuningParametersBuilder<X = ()> {
+    tuning_parameters: TuningParameters<X>,
+}
+
+impl<X> TuningParametersBuilder<X> {
+    pub fn width(mut self, value: f64) -> Self {
+        self.tuning_parameters.width = value;
+        self
+    }
+    pub fn max_number_of_expansions<L: Into<StepLimit>>(mut self, value: L) -> Self {
+        self.tuning_parameters.max_number_of_expansions = value.into();
+        self
+    }
+    // Restricts the support to [lower, upper], where either bound may be infinite. Stepping out
+    // never evaluates the target outside the bounds, while doubling treats the target as zero
+    // there.
+    pub fn bounds(mut self, lower: f64, upper: f64) -> Self {
+        self.tuning_parameters.bounds = Some((lower, upper));
+        self
+    }
+    pub fn max_evaluations(mut self, value: u64) -> Self {
+        self.tuning_parameters.max_evaluations = Some(value);
+        self
+    }
+    // Stops shrinkage once the interval is narrower than value, keeping the current state (or,
+    // for the try_ samplers, failing with SliceError::DegenerateInterval), rather than shrinking
+    // towards denormal widths when no proposal is accepted, e.g., for a discontinuous or
+    // mis-scaled target.
+    pub fn min_width(mut self, value: f64) -> Self {
+        self.tuning_parameters.min_width = Some(value);
+        self
+    }
+    pub(crate) fn extension_mut(&mut self) -> &mut X {
+        &mut self.tuning_parameters.extension
+    }
+    pub fn build(self) -> Result<TuningParameters<X>, TuningError> {
+        let tp = self.tuning_parameters;
+        check_width(tp.width)?;
+        check_step_limit(tp.max_number_of_expansions)?;
+        check_max_evaluations(tp.max_evaluations)?;
+        check_min_width(tp.min_width, tp.width)?;
+        if let Some((lower, upper)) = tp.bounds {
+            check_support(lower, upper)?;
+        }
+        Ok(tp)
+    }
+}
+
+impl TuningParametersBuilder<()> {
+    pub fn max_number_of_steps<L: Into<StepLimit>>(self, value: L) -> Self {
+        self.max_number_of_expansions(value)
+    }
+}
+
+// Procedure used to expand the initial interval around the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expansion {
+    SteppingOut,
+    Doubling,
+}
+
+// Updates x for the target using the given expansion procedure followed by shrinkage (doubling
+// with the default extension), returning the new state and the number of target evaluations.
+// Returns an error if x is not finite or lies outside the bounds, and as the try_ samplers do.
+pub fn sample<T: UnivariateTarget + ?Sized>(
+    x: f64,
+    target: &mut T,
+    expansion: Expansion,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), SliceError> {
+    let x = check_start(x)?;
+    let on_log_scale = target.on_log_scale();
+    let f = |x| target.evaluate(x);
+    match expansion {
+        Expansion::SteppingOut => try_univariate_slice_sampler_stepping_out_and_shrinkage(
+            x,
+            f,
+            on_log_scale,
+            tuning_parameters,
+            rng,
+        ),
+        Expansion::Doubling => try_univariate_slice_sampler_doubling_and_shrinkage(
+            x,
+            f,
+            on_log_scale,
+            &tuning_parameters.with_extension(doubling::DoublingExtension::default()),
+            rng,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::LogDensity;
+    use crate::univariate::stepping_out::univariate_slice_sampler_stepping_out_and_shrinkage;
+
+    #[test]
+    fn test_extensions() {
+        let tuning_parameters = TuningParameters::<()>::builder()
+            .width(2.0)
+            .max_number_of_steps(StepLimit::Max(5))
+            .max_evaluations(100)
+            .build()
+            .unwrap();
+        assert_eq!(tuning_parameters.max_number_of_steps(), StepLimit::Max(5));
+        let doubling = tuning_parameters.with_extension(doubling::DoublingExtension::default());
+        assert_eq!(doubling.width(), 2.0);
+        assert_eq!(doubling.max_number_of_doubles(), StepLimit::Max(5));
+        assert_eq!(doubling.max_evaluations(), Some(100));
+        assert!(!doubling.skip_acceptance_check_for_unimodal());
+        assert_eq!(doubling.with_extension(()), tuning_parameters);
+    }
+
+    #[test]
+    fn test_bounded_support() {
+        // Standard exponential, with mean 1, through both expansion procedures
+        let tuning_parameters = TuningParameters::builder()
+            .bounds(0.0, f64::INFINITY)
+            .build()
+            .unwrap();
+        for expansion in [Expansion::SteppingOut, Expansion::Doubling] {
+            let mut target = LogDensity(|x: f64| -x);
+            let mut rng = Some(fastrand::Rng::with_seed(113));
+            let mut x = 1.0;
+            let mut sum = 0.0;
+            let n_samples = 100_000;
+            for _ in 0..n_samples {
+                (x, _) = sample(x, &mut target, expansion, &tuning_parameters, &mut rng).unwrap();
+                assert!(x >= 0.0);
+                sum += x;
+            }
+            assert!((sum / (n_samples as f64) - 1.0).abs() < 0.02);
+            assert_eq!(
+                sample(-1.0, &mut target, expansion, &tuning_parameters, &mut rng),
+                Err(SliceError::InvalidBounds {
+                    left: 0.0,
+                    right: f64::INFINITY
+                })
+            );
+        }
+        // The entry points themselves honor the bounds, stepping out never evaluating outside
+        let mut rng = Some(fastrand::Rng::with_seed(127));
+        let mut x = 1.0;
+        for _ in 0..1_000 {
+            (x, _) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                x,
+                |x: f64| {
+                    assert!(x >= 0.0);
+                    -x
+                },
+                true,
+                &tuning_parameters,
+                &mut rng,
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        assert_eq!(
+            TuningParameters::<()>::builder().bounds(5.0, 1.0).build(),
+            Err(TuningError::InvalidBounds {
+                lower: 5.0,
+                upper: 1.0
+            })
+        );
+        assert!(matches!(
+            TuningParameters::<()>::builder()
+                .bounds(f64::NAN, 1.0)
+                .build(),
+            Err(TuningError::InvalidBounds { .. })
+        ));
+        assert!(TuningParameters::<()>::builder()
+            .bounds(f64::NEG_INFINITY, f64::INFINITY)
+            .build()
+            .is_ok());
+    }
+}
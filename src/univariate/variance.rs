@@ -0,0 +1,210 @@
+use crate::error::TuningError;
+use crate::univariate::stepping_out::{stepping_out_and_shrinkage_with_interval, TuningParameters};
+use crate::univariate::Phases;
+
+// Trigamma function for a > 0, by the recurrence up to 10 and then its asymptotic series.
+fn trigamma(mut a: f64) -> f64 {
+    let mut sum = 0.0;
+    while a < 10.0 {
+        sum += 1.0 / (a * a);
+        a += 1.0;
+    }
+    let b = 1.0 / (a * a);
+    sum + 1.0 / a + b / 2.0 + b / a * (1.0 / 6.0 - b * (1.0 / 30.0 - b * (1.0 / 42.0 - b / 30.0)))
+}
+
+// Standard deviation of the log of an inverse-gamma variable with the given shape, which depends
+// on neither the scale nor the data beyond the shape.
+pub fn log_inverse_gamma_standard_deviation(shape: f64) -> f64 {
+    trigamma(shape).sqrt()
+}
+
+fn check_shape(shape: f64) -> Result<f64, TuningError> {
+    if shape.is_finite() && shape > 0.0 {
+        Ok(shape)
+    } else {
+        Err(TuningError::InvalidShape(shape))
+    }
+}
+
+// Slice sampler for a variance whose full conditional (with log density f if on_log_scale) is
+// nearly inverse-gamma with the given shape, e.g., shape a + n / 2 for the variance of n normal
+// effects under an inverse-gamma(a, b) or similar prior. The update is on the log variance, where
+// the conditional is close to normal with the standard deviation given by
+// log_inverse_gamma_standard_deviation, and the width in the tuning parameters is in units of
+// that standard deviation (e.g., 2.5), so that the initial interval is well scaled however
+// concentrated the conditional is and stepping out is rarely needed. Returns the new variance
+// and the number of evaluations of f, or an error if the shape is not finite and positive.
+pub fn variance_slice_sampler<S: FnMut(f64) -> f64>(
+    variance: f64,
+    mut f: S,
+    on_log_scale: bool,
+    shape: f64,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), TuningError> {
+    let shape = check_shape(shape)?;
+    let w = tuning_parameters.width() * log_inverse_gamma_standard_deviation(shape);
+    let (eta, evaluation_counter, _) = stepping_out_and_shrinkage_with_interval(
+        variance.ln(),
+        |eta| {
+            let fx = f(eta.exp());
+            if on_log_scale {
+                fx + eta
+            } else {
+                fx * eta.exp()
+            }
+        },
+        on_log_scale,
+        w,
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    );
+    Ok((eta.exp(), evaluation_counter))
+}
+
+// Like variance_slice_sampler, but for a standard deviation (with log density f if
+// on_log_scale) whose square is nearly inverse-gamma with the given shape, the log standard
+// deviation being half the log variance.
+pub fn standard_deviation_slice_sampler<S: FnMut(f64) -> f64>(
+    standard_deviation: f64,
+    mut f: S,
+    on_log_scale: bool,
+    shape: f64,
+    tuning_parameters: &TuningParameters,
+    rng: &mut Option<fastrand::Rng>,
+) -> Result<(f64, u64), TuningError> {
+    let shape = check_shape(shape)?;
+    let w = tuning_parameters.width() * 0.5 * log_inverse_gamma_standard_deviation(shape);
+    let (eta, evaluation_counter, _) = stepping_out_and_shrinkage_with_interval(
+        standard_deviation.ln(),
+        |eta| {
+            let fx = f(eta.exp());
+            if on_log_scale {
+                fx + eta
+            } else {
+                fx * eta.exp()
+            }
+        },
+        on_log_scale,
+        w,
+        tuning_parameters,
+        &mut Phases::default(),
+        rng,
+    );
+    Ok((eta.exp(), evaluation_counter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::stepping_out::univariate_slice_sampler_stepping_out_and_shrinkage;
+
+    #[test]
+    fn test_trigamma() {
+        assert!((trigamma(1.0) - core::f64::consts::PI.powi(2) / 6.0).abs() < 1e-10);
+        assert!((trigamma(0.5) - core::f64::consts::PI.powi(2) / 2.0).abs() < 1e-10);
+        assert!((trigamma(100.0) - 0.010_050_166_663_333_571).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_variance_slice_sampler() {
+        // Conditional of the variance of 400 normal effects with sum of squares 800 under an
+        // inverse-gamma(2, 1) prior, which is inverse-gamma(202, 401) with mean 2
+        let shape = 202.0;
+        let scale = 401.0;
+        let log_density = |v: f64| -(shape + 1.0) * v.ln() - scale / v;
+        let tuning_parameters = TuningParameters::builder().width(2.5).build().unwrap();
+        let mut rng = Some(fastrand::Rng::with_seed(239));
+        let mut variance = 1.0;
+        let mut sd = 1.0;
+        let mut sum = 0.0;
+        let mut sd_sum = 0.0;
+        let mut evaluations = 0;
+        let n_samples = 20_000;
+        for _ in 0..n_samples {
+            let count;
+            (variance, count) = variance_slice_sampler(
+                variance,
+                log_density,
+                true,
+                shape,
+                &tuning_parameters,
+                &mut rng,
+            )
+            .unwrap();
+            evaluations += count;
+            sum += variance;
+            (sd, _) = standard_deviation_slice_sampler(
+                sd,
+                |s| log_density(s * s) + (2.0 * s).ln(),
+                true,
+                shape,
+                &tuning_parameters,
+                &mut rng,
+            )
+            .unwrap();
+            sd_sum += sd * sd;
+        }
+        let n = n_samples as f64;
+        assert!((sum / n - 2.0).abs() < 0.01);
+        assert!((sd_sum / n - 2.0).abs() < 0.01);
+        // A unit width on the log variance, about seven standard deviations, costs more
+        let mut rng = Some(fastrand::Rng::with_seed(239));
+        let mut eta = 0.0;
+        let mut generic_evaluations = 0;
+        for _ in 0..n_samples {
+            let count;
+            (eta, count) = univariate_slice_sampler_stepping_out_and_shrinkage(
+                eta,
+                |eta: f64| log_density(eta.exp()) + eta,
+                true,
+                &TuningParameters::new(),
+                &mut rng,
+            );
+            generic_evaluations += count;
+        }
+        assert!(evaluations < generic_evaluations);
+    }
+
+    #[test]
+    fn test_invalid_shape() {
+        let tuning_parameters = TuningParameters::new();
+        for shape in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                variance_slice_sampler(
+                    1.0,
+                    |v: f64| -v,
+                    true,
+                    shape,
+                    &tuning_parameters,
+                    &mut None
+                ),
+                Err(TuningError::InvalidShape(shape))
+            );
+            assert_eq!(
+                standard_deviation_slice_sampler(
+                    1.0,
+                    |s: f64| -s,
+                    true,
+                    shape,
+                    &tuning_parameters,
+                    &mut None
+                ),
+                Err(TuningError::InvalidShape(shape))
+            );
+        }
+        assert!(matches!(
+            variance_slice_sampler(
+                1.0,
+                |v: f64| -v,
+                true,
+                f64::NAN,
+                &tuning_parameters,
+                &mut None
+            ),
+            Err(TuningError::InvalidShape(_))
+        ));
+    }
+}
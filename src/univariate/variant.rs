@@ -0,0 +1,247 @@
+use crate::math::default_rng;
+use crate::target::UnivariateTarget;
+use crate::univariate::doubling::{self, univariate_slice_sampler_doubling_and_shrinkage};
+use crate::univariate::shrinkage::univariate_slice_sampler_shrinkage;
+use crate::univariate::stepping_out::{self, univariate_slice_sampler_stepping_out_and_shrinkage};
+
+// Univariate slice sampler chosen at runtime, e.g., from a configuration file, together with its
+// tuning parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SliceVariant {
+    SteppingOut(stepping_out::TuningParameters),
+    Doubling(doubling::TuningParameters),
+    FixedInterval { left: f64, right: f64 },
+}
+
+// Updates x for the target using the given variant, returning the new state and the number of
+// target evaluations.
+pub fn sample<T: UnivariateTarget + ?Sized>(
+    variant: &SliceVariant,
+    x: f64,
+    target: &mut T,
+    rng: &mut Option<fastrand::Rng>,
+) -> (f64, u64) {
+    let on_log_scale = target.on_log_scale();
+    let f = |x| target.evaluate(x);
+    match variant {
+        SliceVariant::SteppingOut(tuning_parameters) => {
+            univariate_slice_sampler_stepping_out_and_shrinkage(
+                x,
+                f,
+                on_log_scale,
+                tuning_parameters,
+                rng,
+            )
+        }
+        SliceVariant::Doubling(tuning_parameters) => {
+            univariate_slice_sampler_doubling_and_shrinkage(
+                x,
+                f,
+                on_log_scale,
+                tuning_parameters,
+                rng,
+            )
+        }
+        SliceVariant::FixedInterval { left, right } => {
+            univariate_slice_sampler_shrinkage(x, f, on_log_scale, *left, *right, rng)
+        }
+    }
+}
+
+// Object-safe univariate sampler, so that frameworks can hold a Box<dyn UnivariateSampler> per
+// parameter and swap algorithms at runtime.
+pub trait UnivariateSampler {
+    fn draw(&mut self, x: f64, target: &mut dyn UnivariateTarget) -> f64;
+}
+
+// Slice sampler for a variant, owning its random number generator and counting target
+// evaluations. The generator is created with the sampler, so updates never construct one.
+#[derive(Debug, Clone)]
+pub struct SliceSampler {
+    variant: SliceVariant,
+    rng: Option<fastrand::Rng>,
+    evaluations: u64,
+}
+
+impl SliceSampler {
+    pub fn new(variant: SliceVariant) -> Self {
+        Self {
+            variant,
+            rng: Some(default_rng()),
+            evaluations: 0,
+        }
+    }
+    pub fn with_seed(variant: SliceVariant, seed: u64) -> Self {
+        Self {
+            rng: Some(fastrand::Rng::with_seed(seed)),
+            ..Self::new(variant)
+        }
+    }
+    pub fn variant(&self) -> &SliceVariant {
+        &self.variant
+    }
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+    // Runs out.len() consecutive updates starting from x, writing the draws to out and returning
+    // the number of target evaluations.
+    pub fn sample_n<T: UnivariateTarget + ?Sized>(
+        &mut self,
+        mut x: f64,
+        target: &mut T,
+        out: &mut [f64],
+    ) -> u64 {
+        let mut evaluations = 0;
+        for draw in out.iter_mut() {
+            let evaluation_counter;
+            (x, evaluation_counter) = sample(&self.variant, x, target, &mut self.rng);
+            evaluations += evaluation_counter;
+            *draw = x;
+        }
+        self.evaluations += evaluations;
+        evaluations
+    }
+}
+
+impl UnivariateSampler for SliceSampler {
+    fn draw(&mut self, x: f64, target: &mut dyn UnivariateTarget) -> f64 {
+        let (x1, evaluation_counter) = sample(&self.variant, x, target, &mut self.rng);
+        self.evaluations += evaluation_counter;
+        x1
+    }
+}
+
+// Source of approximate draws from a target, running updates_per_draw updates of a slice
+// sampler from the last state for each draw, for code that consumes draws one at a time (e.g.,
+// through Iterator) rather than running a chain. The draws are correlated and are approximate
+// until the chain has converged, so the starting point should come from a run past burn-in.
+#[derive(Debug, Clone)]
+pub struct StationarySampler<T> {
+    sampler: SliceSampler,
+    target: T,
+    x: f64,
+    updates_per_draw: usize,
+}
+
+impl<T: UnivariateTarget> StationarySampler<T> {
+    pub fn new(sampler: SliceSampler, target: T, x: f64, updates_per_draw: usize) -> Self {
+        Self {
+            sampler,
+            target,
+            x,
+            updates_per_draw: updates_per_draw.max(1),
+        }
+    }
+    pub fn sample(&mut self) -> f64 {
+        for _ in 0..self.updates_per_draw {
+            self.x = self.sampler.draw(self.x, &mut self.target);
+        }
+        self.x
+    }
+    pub fn state(&self) -> f64 {
+        self.x
+    }
+    pub fn sampler(&self) -> &SliceSampler {
+        &self.sampler
+    }
+    pub fn into_inner(self) -> (SliceSampler, T, f64) {
+        (self.sampler, self.target, self.x)
+    }
+}
+
+impl<T: UnivariateTarget> Iterator for StationarySampler<T> {
+    type Item = f64;
+    fn next(&mut self) -> Option<f64> {
+        Some(self.sample())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::Density;
+
+    #[test]
+    fn test_variants() {
+        let variants = [
+            SliceVariant::SteppingOut(stepping_out::TuningParameters::new()),
+            SliceVariant::Doubling(doubling::TuningParameters::new()),
+            SliceVariant::FixedInterval {
+                left: 0.0,
+                right: 1.0,
+            },
+        ];
+        let mut target = Density(|x: f64| if (0.0..=1.0).contains(&x) { x } else { 0.0 });
+        let mut rng = Some(fastrand::Rng::with_seed(43));
+        for variant in &variants {
+            let mut x = 0.5;
+            let mut sum = 0.0;
+            let n_samples = 50_000;
+            for _ in 0..n_samples {
+                (x, _) = sample(variant, x, &mut target, &mut rng);
+                sum += x;
+            }
+            assert!((sum / (n_samples as f64) - 2.0 / 3.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_boxed_samplers() {
+        let mut samplers: [Box<dyn UnivariateSampler>; 2] = [
+            Box::new(SliceSampler::with_seed(
+                SliceVariant::SteppingOut(stepping_out::TuningParameters::new()),
+                47,
+            )),
+            Box::new(SliceSampler::with_seed(
+                SliceVariant::Doubling(doubling::TuningParameters::new()),
+                53,
+            )),
+        ];
+        let mut target = crate::target::LogDensity(|x: f64| -0.5 * x * x);
+        let mut x = [0.0, 0.0];
+        let mut sums = [0.0, 0.0];
+        let n_samples = 50_000;
+        for _ in 0..n_samples {
+            for (i, sampler) in samplers.iter_mut().enumerate() {
+                x[i] = sampler.draw(x[i], &mut target);
+                sums[i] += x[i] * x[i];
+            }
+        }
+        for sum in sums {
+            assert!((sum / (n_samples as f64) - 1.0).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_sample_n() {
+        // A batch continues the chain exactly as the same number of single draws would
+        let variant = SliceVariant::Doubling(doubling::TuningParameters::new());
+        let mut target = crate::target::LogDensity(|x: f64| -0.5 * x * x);
+        let mut batched = SliceSampler::with_seed(variant.clone(), 59);
+        let mut draws = [0.0; 100];
+        let evaluations = batched.sample_n(0.0, &mut target, &mut draws);
+        let mut single = SliceSampler::with_seed(variant, 59);
+        let mut x = 0.0;
+        for draw in draws {
+            x = single.draw(x, &mut target);
+            assert_eq!(x, draw);
+        }
+        assert_eq!(evaluations, single.evaluations());
+        assert_eq!(batched.evaluations(), single.evaluations());
+    }
+
+    #[test]
+    fn test_stationary_sampler() {
+        let variant = SliceVariant::SteppingOut(stepping_out::TuningParameters::new());
+        let sampler = SliceSampler::with_seed(variant, 191);
+        let target = crate::target::LogDensity(|x: f64| -0.5 * x * x);
+        let draws = StationarySampler::new(sampler, target, 0.0, 3);
+        let n = 20_000;
+        let (sum, sum_of_squares) = draws.take(n).fold((0.0, 0.0), |(sum, sum_of_squares), x| {
+            (sum + x, sum_of_squares + x * x)
+        });
+        let n = n as f64;
+        assert!((sum / n).abs() < 0.05);
+        assert!((sum_of_squares / n - 1.0).abs() < 0.05);
+    }
+}
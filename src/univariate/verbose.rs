@@ -0,0 +1,143 @@
+use crate::error::check_width;
+use crate::univariate::expansion::{expansion_and_shrinkage, ExpansionStrategy};
+use crate::univariate::Observer;
+use core::convert::Infallible;
+
+// Point proposed during shrinkage, with the interval from which it was drawn, the target at the
+// point, and whether it was accepted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Proposal {
+    pub interval: (f64, f64),
+    pub point: f64,
+    pub density: f64,
+    pub accepted: bool,
+}
+
+// Full record of one update, e.g., for animating how expansion and shrinkage proceed: the slice
+// level, the randomly positioned initial interval, the points evaluated (with the target there)
+// while expanding it in the order evaluated, the expanded interval, and every proposal during
+// shrinkage, the last of which is accepted unless the interval collapsed onto the current state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateTrace {
+    pub slice_level: f64,
+    pub initial_interval: (f64, f64),
+    pub expansion_points: Vec<(f64, f64)>,
+    pub expanded_interval: (f64, f64),
+    pub proposals: Vec<Proposal>,
+    pub draw: f64,
+    pub evaluations: u64,
+}
+
+impl Observer for UpdateTrace {
+    fn slice(&mut self, y: f64, initial_interval: (f64, f64)) {
+        self.slice_level = y;
+        self.initial_interval = initial_interval;
+    }
+    fn expansion_evaluation(&mut self, z: f64, fz: f64) {
+        self.expansion_points.push((z, fz));
+    }
+    fn expanded(&mut self, interval: (f64, f64)) {
+        self.expanded_interval = interval;
+    }
+    fn proposal(&mut self, interval: (f64, f64), x1: f64, fx1: f64, accepted: bool) {
+        self.proposals.push(Proposal {
+            interval,
+            point: x1,
+            density: fx1,
+            accepted,
+        });
+    }
+}
+
+// Performs one update of x with the expansion strategy (e.g., SteppingOut or Doubling) and
+// initial width w followed by shrinkage, exactly as the other samplers do, returning its full
+// record. Panics if w is not positive and finite.
+pub fn trace_update<X: ExpansionStrategy, S: FnMut(f64) -> f64>(
+    x: f64,
+    mut f: S,
+    on_log_scale: bool,
+    w: f64,
+    strategy: &X,
+    rng: &mut Option<fastrand::Rng>,
+) -> UpdateTrace {
+    if let Err(error) = check_width(w) {
+        panic!("{}", error);
+    }
+    let mut trace = UpdateTrace::default();
+    let result = expansion_and_shrinkage(
+        x,
+        |x| Ok::<f64, Infallible>(f(x)),
+        on_log_scale,
+        w,
+        0.0,
+        0,
+        strategy,
+        &mut trace,
+        rng,
+    );
+    match result {
+        Ok((x1, evaluation_counter, _)) => {
+            trace.draw = x1;
+            trace.evaluations = evaluation_counter;
+        }
+        Err(never) => match never {},
+    }
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univariate::expansion::{
+        univariate_slice_sampler_expansion_and_shrinkage, SteppingOut,
+    };
+    use crate::univariate::StepLimit;
+
+    #[test]
+    fn test_trace_matches_update() {
+        let f = |x: f64| -0.5 * x * x;
+        let strategy = SteppingOut::new(StepLimit::Unlimited);
+        let trace = trace_update(
+            1.0,
+            f,
+            true,
+            0.25,
+            &strategy,
+            &mut Some(fastrand::Rng::with_seed(67)),
+        );
+        let (draw, evaluations) = univariate_slice_sampler_expansion_and_shrinkage(
+            1.0,
+            f,
+            true,
+            0.25,
+            &strategy,
+            &mut Some(fastrand::Rng::with_seed(67)),
+        );
+        assert_eq!((trace.draw, trace.evaluations), (draw, evaluations));
+        let (l, r) = trace.initial_interval;
+        assert!(l < 1.0 && 1.0 < r && (r - l - 0.25).abs() < 1e-12);
+        // Stepping out ends with one endpoint outside the slice on each side
+        let outside = trace
+            .expansion_points
+            .iter()
+            .filter(|(_, fz)| *fz <= trace.slice_level)
+            .count();
+        assert_eq!(outside, 2);
+        // Each proposal is drawn from the interval left by the previous rejections
+        let mut interval = trace.expanded_interval;
+        for proposal in &trace.proposals {
+            assert_eq!(proposal.interval, interval);
+            if proposal.point < 1.0 {
+                interval.0 = proposal.point;
+            } else {
+                interval.1 = proposal.point;
+            }
+        }
+        let last = trace.proposals.last().unwrap();
+        assert!(last.accepted && last.point == draw && last.density > trace.slice_level);
+        assert_eq!(
+            trace.evaluations,
+            1 + trace.expansion_points.len() as u64 + trace.proposals.len() as u64
+        );
+    }
+}